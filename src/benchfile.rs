@@ -1,12 +1,14 @@
 // Copyright © 2022 Daniel Getz
 // SPDX-License-Identifier: MIT
 
-use nom::bytes::complete::take_while1;
-use nom::character::complete::{char, space0};
-use nom::combinator::{eof, opt};
+use nom::branch::alt;
+use nom::bytes::complete::{tag, take_while1};
+use nom::character::complete::{char, satisfy, space0};
+use nom::combinator::{eof, map, not, opt, recognize, verify};
 use nom::multi::separated_list1;
-use nom::sequence::{delimited, preceded, separated_pair, terminated};
+use nom::sequence::{delimited, pair, preceded, separated_pair, terminated};
 use nom::IResult;
+use unicode_normalization::char::is_combining_mark;
 
 #[cfg(test)]
 mod tests;
@@ -19,24 +21,146 @@ type Target<'a> = Vec<&'a str>;
 
 type Targets<'a> = Vec<Target<'a>>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bench<'a> {
+    pub start_words: StartWords<'a>,
+    pub targets: Targets<'a>,
+    pub weight: u32,
+    /// False for benches commented out with `;;`, which are kept track of but not run.
+    pub enabled: bool,
+    /// True for benches written with `<=>`, which also go from each target to each start word.
+    pub bidirectional: bool,
+}
+
+impl<'a> Bench<'a> {
+    /// For a bidirectional bench, the bench going the other way, from each target to each start word.
+    pub fn reversed(&self) -> Option<Bench<'a>> {
+        self.bidirectional.then(|| Bench {
+            start_words: self.targets.iter().flatten().copied().collect(),
+            targets: self.start_words.iter().map(|&word| vec![word]).collect(),
+            weight: self.weight,
+            enabled: self.enabled,
+            bidirectional: false,
+        })
+    }
+
+    /// The start words side of the bench as `format_bench_file` writes it:
+    /// sorted, without duplicates, and with `;; ` in front if it's disabled.
+    pub fn canonical_start(&self) -> String {
+        let mut start_words = self.start_words.clone();
+        start_words.sort_unstable();
+        start_words.dedup();
+        let prefix = if self.enabled { "" } else { ";; " };
+        format!("{}{}", prefix, start_words.join(", "))
+    }
+
+    /// The rest of the bench after the start words as `format_bench_file` writes it:
+    /// the `=` or `<=>`, the targets sorted without duplicates, and the weight if it isn't 1.
+    pub fn canonical_rest(&self) -> String {
+        let mut targets: Vec<Vec<&str>> = self
+            .targets
+            .iter()
+            .map(|target| {
+                let mut options = target.clone();
+                options.sort_unstable();
+                options.dedup();
+                options
+            })
+            .collect();
+        targets.sort_unstable();
+        targets.dedup();
+        let targets: Vec<String> = targets.iter().map(|options| options.join(" | ")).collect();
+        let op = if self.bidirectional { "<=>" } else { "=" };
+        let mut rest = format!("{} {}", op, targets.join(", "));
+        if self.weight != 1 {
+            rest.push_str(&format!(" *{}", self.weight));
+        }
+        rest
+    }
+}
+
+/// A bench file line, split up: its bench, if it has one, and its comment after the `;`, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchLine<'a> {
+    pub bench: Option<Bench<'a>>,
+    pub comment: Option<&'a str>,
+}
+
 fn word(input: &str) -> IRes<&str> {
-    take_while1(|c: char| c.is_alphanumeric())(input)
+    take_while1(|c: char| c.is_alphanumeric() || is_combining_mark(c))(input)
 }
 
 fn start_words(input: &str) -> IRes<StartWords> {
     separated_list1(delimited(space0, char(','), space0), word)(input)
 }
 
+/// A target word, or a pattern like `*inho` or `casa*`.
+/// A `*` followed by digits after a word is the bench's weight, not a prefix pattern.
+fn target_option(input: &str) -> IRes<&str> {
+    alt((
+        recognize(pair(char('*'), word)),
+        terminated(
+            recognize(pair(word, char('*'))),
+            not(satisfy(|c| c.is_ascii_digit())),
+        ),
+        word,
+    ))(input)
+}
+
 fn target(input: &str) -> IRes<Target> {
-    separated_list1(delimited(space0, char('|'), space0), word)(input)
+    separated_list1(delimited(space0, char('|'), space0), target_option)(input)
 }
 
 fn targets(input: &str) -> IRes<Targets> {
     separated_list1(delimited(space0, char(','), space0), target)(input)
 }
 
-fn bench(input: &str) -> IRes<(StartWords, Targets)> {
-    separated_pair(start_words, delimited(space0, char('='), space0), targets)(input)
+fn weight(input: &str) -> IRes<u32> {
+    preceded(
+        char('*'),
+        verify(nom::character::complete::u32, |&weight| weight > 0),
+    )(input)
+}
+
+fn one_way_bench(input: &str) -> IRes<Bench> {
+    map(
+        pair(
+            separated_pair(start_words, delimited(space0, char('='), space0), targets),
+            opt(preceded(space0, weight)),
+        ),
+        |((start_words, targets), weight)| Bench {
+            start_words,
+            targets,
+            weight: weight.unwrap_or(1),
+            enabled: true,
+            bidirectional: false,
+        },
+    )(input)
+}
+
+/// Both sides are plain words, so that either side can be the start words.
+fn bidirectional_bench(input: &str) -> IRes<Bench> {
+    map(
+        pair(
+            separated_pair(
+                start_words,
+                delimited(space0, tag("<=>"), space0),
+                start_words,
+            ),
+            opt(preceded(space0, weight)),
+        ),
+        |((start_words, targets), weight)| Bench {
+            start_words,
+            targets: targets.into_iter().map(|word| vec![word]).collect(),
+            weight: weight.unwrap_or(1),
+            enabled: true,
+            bidirectional: true,
+        },
+    )(input)
+}
+
+fn bench(input: &str) -> IRes<Bench> {
+    alt((one_way_bench, bidirectional_bench))(input)
 }
 
 fn remainder(input: &str) -> IRes<&str> {
@@ -47,9 +171,50 @@ fn comment(input: &str) -> IRes<&str> {
     preceded(char(';'), remainder)(input)
 }
 
-pub fn bench_line(input: &str) -> IRes<Option<(StartWords, Targets)>> {
+fn disabled_bench(input: &str) -> IRes<Bench> {
+    map(preceded(pair(tag(";;"), space0), bench), |bench| Bench {
+        enabled: false,
+        ..bench
+    })(input)
+}
+
+/// A line like `[verbs]` that puts the benches after it in a section, with the name trimmed.
+pub fn section_header(input: &str) -> IRes<&str> {
     terminated(
-        delimited(space0, opt(bench), preceded(space0, opt(comment))),
+        delimited(
+            pair(space0, char('[')),
+            map(
+                verify(take_while1(|c: char| c != ']' && c != ';'), |name: &str| {
+                    !name.trim().is_empty()
+                }),
+                str::trim,
+            ),
+            pair(char(']'), preceded(space0, opt(comment))),
+        ),
         eof,
     )(input)
 }
+
+/// Like `bench_line`, but also keeps the line's comment.
+pub fn bench_line_detailed(input: &str) -> IRes<BenchLine> {
+    map(
+        alt((
+            terminated(
+                pair(
+                    preceded(space0, map(disabled_bench, Some)),
+                    preceded(space0, opt(comment)),
+                ),
+                eof,
+            ),
+            terminated(
+                pair(preceded(space0, opt(bench)), preceded(space0, opt(comment))),
+                eof,
+            ),
+        )),
+        |(bench, comment)| BenchLine { bench, comment },
+    )(input)
+}
+
+pub fn bench_line(input: &str) -> IRes<Option<Bench>> {
+    map(bench_line_detailed, |line| line.bench)(input)
+}
@@ -3,11 +3,24 @@
 
 use super::*;
 
+fn simple_bench<'a>(start_words: StartWords<'a>, targets: Targets<'a>) -> Bench<'a> {
+    Bench {
+        start_words,
+        targets,
+        weight: 1,
+        enabled: true,
+        bidirectional: false,
+    }
+}
+
 #[test]
 fn test_simple() {
     assert_eq!(
         bench_line("bulacha = bolacha"),
-        Ok(("", Some((vec!["bulacha"], vec![vec!["bolacha"]]))))
+        Ok((
+            "",
+            Some(simple_bench(vec!["bulacha"], vec![vec!["bolacha"]]))
+        ))
     );
 }
 
@@ -15,7 +28,21 @@ fn test_simple() {
 fn test_unicode() {
     assert_eq!(
         bench_line("assõ = ação"),
-        Ok(("", Some((vec!["assõ"], vec![vec!["ação"]]))))
+        Ok(("", Some(simple_bench(vec!["assõ"], vec![vec!["ação"]]))))
+    );
+}
+
+#[test]
+fn test_decomposed_unicode() {
+    assert_eq!(
+        bench_line("asso\u{303} = ac\u{327}a\u{303}o"),
+        Ok((
+            "",
+            Some(simple_bench(
+                vec!["asso\u{303}"],
+                vec![vec!["ac\u{327}a\u{303}o"]]
+            ))
+        ))
     );
 }
 
@@ -23,7 +50,10 @@ fn test_unicode() {
 fn test_multiple_starting_words() {
     assert_eq!(
         bench_line("abc, def , ghi = xyz"),
-        Ok(("", Some((vec!["abc", "def", "ghi"], vec![vec!["xyz"]]))))
+        Ok((
+            "",
+            Some(simple_bench(vec!["abc", "def", "ghi"], vec![vec!["xyz"]]))
+        ))
     );
 }
 
@@ -31,7 +61,10 @@ fn test_multiple_starting_words() {
 fn test_target_options() {
     assert_eq!(
         bench_line("start = one | two"),
-        Ok(("", Some((vec!["start"], vec![vec!["one", "two"]]))))
+        Ok((
+            "",
+            Some(simple_bench(vec!["start"], vec![vec!["one", "two"]]))
+        ))
     );
 }
 
@@ -39,7 +72,10 @@ fn test_target_options() {
 fn test_multiple_targets() {
     assert_eq!(
         bench_line("start = one, two"),
-        Ok(("", Some((vec!["start"], vec![vec!["one"], vec!["two"]]))))
+        Ok((
+            "",
+            Some(simple_bench(vec!["start"], vec![vec!["one"], vec!["two"]]))
+        ))
     );
 }
 
@@ -49,7 +85,10 @@ fn test_multiple_targets_with_options() {
         bench_line("start = one, two | three"),
         Ok((
             "",
-            Some((vec!["start"], vec![vec!["one"], vec!["two", "three"]]))
+            Some(simple_bench(
+                vec!["start"],
+                vec![vec!["one"], vec!["two", "three"]]
+            ))
         ))
     );
 }
@@ -68,10 +107,232 @@ fn test_comment_line() {
     }
 }
 
+#[test]
+fn test_disabled_bench() {
+    for line in [";;a = b", " ;; a = b ; not yet", ";;  a = b  "] {
+        assert_eq!(
+            bench_line(line),
+            Ok((
+                "",
+                Some(Bench {
+                    enabled: false,
+                    ..simple_bench(vec!["a"], vec![vec!["b"]])
+                })
+            ))
+        );
+    }
+    for line in ["; a = b", ";a = b", ";; just a note", ";;;"] {
+        assert_eq!(bench_line(line), Ok(("", None)));
+    }
+}
+
 #[test]
 fn test_comment_after_content() {
     assert_eq!(
         bench_line("start = one, two ;, three | four"),
-        Ok(("", Some((vec!["start"], vec![vec!["one"], vec!["two"]]))))
+        Ok((
+            "",
+            Some(simple_bench(vec!["start"], vec![vec!["one"], vec!["two"]]))
+        ))
+    );
+}
+
+#[test]
+fn test_weight() {
+    assert_eq!(
+        bench_line("start = one, two | three *3"),
+        Ok((
+            "",
+            Some(Bench {
+                start_words: vec!["start"],
+                targets: vec![vec!["one"], vec!["two", "three"]],
+                weight: 3,
+                enabled: true,
+                bidirectional: false,
+            })
+        ))
+    );
+    assert_eq!(
+        bench_line("start = one*12 ; comment"),
+        Ok((
+            "",
+            Some(Bench {
+                start_words: vec!["start"],
+                targets: vec![vec!["one"]],
+                weight: 12,
+                enabled: true,
+                bidirectional: false,
+            })
+        ))
+    );
+}
+
+#[test]
+fn test_bidirectional() {
+    let bench = Bench {
+        bidirectional: true,
+        ..simple_bench(vec!["bulacha"], vec![vec!["bolacha"]])
+    };
+    for line in ["bulacha <=> bolacha", "  bulacha<=>bolacha ; both ways"] {
+        assert_eq!(bench_line(line), Ok(("", Some(bench.clone()))));
+    }
+    assert_eq!(
+        bench.reversed(),
+        Some(simple_bench(vec!["bolacha"], vec![vec!["bulacha"]]))
+    );
+    assert_eq!(
+        bench_line(";; a <=> b"),
+        Ok((
+            "",
+            Some(Bench {
+                enabled: false,
+                bidirectional: true,
+                ..simple_bench(vec!["a"], vec![vec!["b"]])
+            })
+        ))
+    );
+}
+
+#[test]
+fn test_bidirectional_multiple_words() {
+    let (_, bench) = bench_line("a, b <=> c, d *2").unwrap();
+    let bench = bench.unwrap();
+    assert_eq!(
+        bench,
+        Bench {
+            start_words: vec!["a", "b"],
+            targets: vec![vec!["c"], vec!["d"]],
+            weight: 2,
+            enabled: true,
+            bidirectional: true,
+        }
+    );
+    assert_eq!(
+        bench.reversed(),
+        Some(Bench {
+            start_words: vec!["c", "d"],
+            targets: vec![vec!["a"], vec!["b"]],
+            weight: 2,
+            enabled: true,
+            bidirectional: false,
+        })
+    );
+    assert_eq!(simple_bench(vec!["a"], vec![vec!["b"]]).reversed(), None);
+}
+
+#[test]
+fn test_bad_bidirectional() {
+    for line in [
+        "a <=> b | c",
+        "a <=> casa*",
+        "a <=>",
+        "<=> b",
+        "a <= b",
+        "a <=> b = c",
+    ] {
+        assert!(bench_line(line).is_err(), "{:?}", line);
+    }
+}
+
+#[test]
+fn test_bad_weight() {
+    for line in [
+        "start = one *",
+        "start = one *0",
+        "start = one *x",
+        "start = one * 2",
+    ] {
+        assert!(bench_line(line).is_err(), "{:?} should not parse", line);
+    }
+}
+
+#[test]
+fn test_target_patterns() {
+    assert_eq!(
+        bench_line("start = *inho | casa*, *a"),
+        Ok((
+            "",
+            Some(simple_bench(
+                vec!["start"],
+                vec![vec!["*inho", "casa*"], vec!["*a"]]
+            ))
+        ))
     );
+    assert_eq!(
+        bench_line("start = casa**2"),
+        Ok((
+            "",
+            Some(Bench {
+                weight: 2,
+                ..simple_bench(vec!["start"], vec![vec!["casa*"]])
+            })
+        ))
+    );
+}
+
+#[test]
+fn test_bad_target_patterns() {
+    for line in ["start = *", "start = **a", "start = a**", "*start = a"] {
+        assert!(bench_line(line).is_err(), "{:?} should not parse", line);
+    }
+}
+
+#[test]
+fn test_section_header() {
+    assert_eq!(section_header("[verbs]"), Ok(("", "verbs")));
+    assert_eq!(
+        section_header("  [ foreign words ]  ; loanwords"),
+        Ok(("", "foreign words"))
+    );
+    for bad in [
+        "[]",
+        "[  ]",
+        "[verbs",
+        "verbs]",
+        "[verbs] extra",
+        "[a;b]",
+        "a = b",
+    ] {
+        assert!(section_header(bad).is_err(), "{:?}", bad);
+    }
+    assert!(bench_line("[verbs]").is_err());
+}
+
+#[test]
+fn test_bench_line_detailed_comment() {
+    assert_eq!(
+        bench_line_detailed("a = b ; why"),
+        Ok((
+            "",
+            BenchLine {
+                bench: Some(simple_bench(vec!["a"], vec![vec!["b"]])),
+                comment: Some(" why"),
+            }
+        ))
+    );
+    let (_, line) = bench_line_detailed(";; a = b ;off").unwrap();
+    assert!(!line.bench.unwrap().enabled);
+    assert_eq!(line.comment, Some("off"));
+    assert_eq!(
+        bench_line_detailed("  ; just a note"),
+        Ok((
+            "",
+            BenchLine {
+                bench: None,
+                comment: Some(" just a note"),
+            }
+        ))
+    );
+}
+
+#[test]
+fn test_canonical_bench() {
+    let (_, bench) = bench_line("z, a = y | b, c *2").unwrap();
+    let bench = bench.unwrap();
+    assert_eq!(bench.canonical_start(), "a, z");
+    assert_eq!(bench.canonical_rest(), "= b | y, c *2");
+    let (_, bench) = bench_line(";; b <=> a").unwrap();
+    let bench = bench.unwrap();
+    assert_eq!(bench.canonical_start(), ";; b");
+    assert_eq!(bench.canonical_rest(), "<=> a");
 }
@@ -0,0 +1,2460 @@
+// Copyright © 2022 Daniel Getz
+// SPDX-License-Identifier: MIT
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::zip;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
+
+use buscaluso_bench::sqlite::{BenchDb, BenchSessionId};
+use buscaluso_bench::{
+    compile_bench, compile_session, file_sha256_hex, fmt_duration, fmt_duration_range,
+    format_bench_file, sample_std_dev, session_stats, target_option_matches,
+    target_option_matches_any_case, BenchKey, BenchNameStyle, BenchResult, BenchResultCompiler,
+    BenchRunCfg, CompileSteps, CompiledBenchResult, GroupStats, SearchError, SearchLimit,
+    CASE_INSENSITIVE_INFO, COMPARE_ALPHA, COMPARE_DROP_FRACTION, COMPARE_INDEX_EQUIVALENT,
+    COMPARE_MIN_DIFFERENCE, DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED, SESSION_COMPLETE,
+    SESSION_STATUS_INFO,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use time::macros::format_description;
+use time::{Date, OffsetDateTime};
+
+#[cfg(test)]
+mod tests;
+
+#[derive(Parser)]
+struct Cli {
+    /// Database file
+    #[arg(long, default_value = "bench.sqlite3")]
+    db: PathBuf,
+
+    /// File to write the output to, instead of standard output
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// Number of decimal places to show in durations
+    #[arg(long, default_value_t = 4)]
+    precision: usize,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+/// How commands that support it format their output.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum OutputFormat {
+    Table,
+    Json,
+}
+
+/// Formats that `export` can write a session's results in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExportFormat {
+    /// A JSON object of each bench's estimates, shaped like Criterion's `estimates.json`
+    Criterion,
+    /// A JSON object of each bench's score in seconds, for `compare-baseline`
+    Baseline,
+}
+
+/// How `export` writes bench names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum NameStyle {
+    /// As they're stored, like `start = target1 | target2`
+    Default,
+    /// Without spaces or `|`, like `start/target1+target2`, for tools that use names as paths
+    Path,
+}
+
+impl NameStyle {
+    fn style(self) -> BenchNameStyle<'static> {
+        match self {
+            NameStyle::Default => BenchNameStyle::DEFAULT,
+            NameStyle::Path => BenchNameStyle::PATH,
+        }
+    }
+}
+
+/// A bench's name in the given style, or as it is if it can't be split up.
+fn bench_name_with_style(bench: &str, style: NameStyle) -> String {
+    match BenchKey::from_name(bench) {
+        Some(key) => key.name_with_style(&style.style()),
+        None => bench.to_string(),
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Lists all sessions.
+    ListSessions {
+        /// Only list sessions started on or after this date, as YYYY-MM-DD (UTC)
+        #[arg(long, value_parser = parse_date)]
+        since: Option<SystemTime>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+
+        /// Info names to show as extra columns, separated by commas,
+        /// instead of tag, version_buscaluso, machine, search_rules_hash and dict_word_count
+        #[arg(long, value_delimiter = ',')]
+        columns: Option<Vec<String>>,
+    },
+
+    /// Shows a session's metadata, starting with the machine, tag and command line arguments.
+    /// Doesn't show multiline values, except in JSON.
+    Show {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Shows the metadata that differs between two sessions.
+    /// Doesn't show multiline values.
+    InfoDiff {
+        /// Session ID
+        session_a: BenchSessionId,
+        /// Session ID
+        session_b: BenchSessionId,
+    },
+
+    /// Outputs a single metadata value from a session.
+    Get {
+        /// Session ID
+        session: BenchSessionId,
+        info_key: String,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
+    },
+
+    /// Shows some quick statistics of a session's results.
+    Stats {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// Also break the results down by whether the start word has accents
+        #[arg(long)]
+        by_accent: bool,
+
+        /// Also break the results down by the bench file section each bench was in
+        #[arg(long)]
+        by_section: bool,
+    },
+
+    /// Writes a self-contained HTML report of a session's results.
+    Html {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// HTML file to write, instead of the usual output
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Outputs a session's compiled results as Prometheus metrics.
+    Prometheus {
+        /// Session ID
+        session: BenchSessionId,
+    },
+
+    /// Outputs a session's compiled results in another tool's format.
+    Export {
+        /// Session ID
+        session: BenchSessionId,
+
+        #[arg(long, value_enum)]
+        format: ExportFormat,
+
+        /// How to write the bench names
+        #[arg(long, value_enum, default_value_t = NameStyle::Default)]
+        name_style: NameStyle,
+    },
+
+    /// Suggests an index equivalent for scoring, from how much time
+    /// each result position costs in a session's successful results.
+    Calibrate {
+        /// Session ID
+        session: BenchSessionId,
+    },
+
+    /// Shows statistics of all the session's results.
+    Results {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// Show found indices as a percentage of the dictionary's size
+        #[arg(long)]
+        relative: bool,
+
+        /// Mark the times of benches whose standard deviation is more than this fraction of their mean
+        #[arg(long, default_value_t = RESULTS_COV_THRESHOLD)]
+        cov_threshold: f64,
+    },
+
+    /// Shows how a bench's score was worked out from its results:
+    /// each result's score, which were dropped, and the mean of the rest.
+    Explain {
+        /// Session ID
+        session: BenchSessionId,
+        /// Bench name, like "kurassom = coração"
+        bench: String,
+    },
+
+    /// Shows every result of a bench, fastest first, marking the ones that compiling it drops.
+    BenchDetail {
+        /// Session ID
+        session: BenchSessionId,
+        /// Bench name, like "kurassom = coração"
+        bench: String,
+    },
+
+    /// Shows how often each of a bench's target options was the one its successful results found.
+    OptionStats {
+        /// Session ID
+        session: BenchSessionId,
+        /// Bench name, like "kurassom = coração | corações"
+        bench: String,
+    },
+
+    /// Lists the search errors in a session's results.
+    Errors {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// Count the errors by their kind, instead of by their exact message
+        #[arg(long)]
+        by_kind: bool,
+    },
+
+    /// Lists the benches that never found their target in any session,
+    /// with how many sessions they were run in.
+    NeverFound,
+
+    /// Sets or replaces a session's tag.
+    /// Opens the database for writing.
+    Tag {
+        /// Session ID
+        session: BenchSessionId,
+        /// Tag
+        tag: String,
+    },
+
+    /// Shows the words found by traced searches from a start word.
+    Trace {
+        /// Session ID
+        session: BenchSessionId,
+        /// Start word
+        start_word: String,
+    },
+
+    /// Deletes a session's successful results, keeping only the failures.
+    /// Opens the database for writing.
+    PruneSuccesses {
+        /// Session ID
+        session: BenchSessionId,
+    },
+
+    /// Shows how a bench's score changed over its most recent sessions.
+    Trend {
+        /// Bench name, like "kurassom = coração"
+        bench: String,
+
+        /// How many of the most recent sessions to show
+        #[arg(long, default_value_t = 10)]
+        last: usize,
+    },
+
+    /// Shows the best score each bench ever got, and in which session.
+    /// Compiles every session's results, so it can take a while.
+    Records,
+
+    /// Compiles and stores a session's results, so that `results`, `stats` and `compare`
+    /// don't have to compile them again. Opens the database for writing.
+    Summarize {
+        /// Session ID
+        session: BenchSessionId,
+    },
+
+    /// Writes every session's info, weights, results and traces as JSON, one record per line.
+    Dump {
+        /// File to write the archive to, instead of the usual output
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+
+    /// Adds the sessions from an archive made by `dump`. Opens the database for writing.
+    Load {
+        /// Archive file
+        archive: PathBuf,
+    },
+
+    /// Rewrites a bench file in place in a canonical form: runs of benches sorted by start words,
+    /// with their targets sorted and their `=`s lined up. Doesn't use the database.
+    FormatBenchfile {
+        /// Bench file
+        path: PathBuf,
+    },
+
+    /// Shrinks the database file after deleting results, and updates its query statistics.
+    /// Opens the database for writing.
+    Vacuum,
+
+    /// Compares a session's scores against a JSON baseline
+    /// of bench names to scores in seconds (or null, if not found).
+    CompareBaseline {
+        /// Session ID
+        session: BenchSessionId,
+        /// Baseline JSON file
+        baseline: PathBuf,
+
+        /// Smallest score difference, in seconds, to report
+        #[arg(long, default_value_t = COMPARE_MIN_DIFFERENCE)]
+        min_difference: f64,
+
+        /// Exit with an error code if any bench regressed
+        #[arg(long)]
+        fail_on_regression: bool,
+    },
+
+    /// Checks whether the given rules, dictionary and bench files are the ones a session used,
+    /// by their hashes. Exits with an error code if any differ.
+    VerifyInputs {
+        /// Session ID
+        session: BenchSessionId,
+
+        /// Rules file
+        #[arg(long)]
+        rules: Option<PathBuf>,
+
+        /// Dictionary file
+        #[arg(long)]
+        dict: Option<PathBuf>,
+
+        /// Bench file; give it once for each, in the same order as the session
+        #[arg(long = "bench")]
+        benches: Vec<PathBuf>,
+    },
+
+    /// Compares the results of two sessions.
+    Compare {
+        /// Session ID
+        session_a: BenchSessionId,
+        /// Session ID
+        session_b: BenchSessionId,
+
+        /// Exit with an error code if B is worse than A
+        #[arg(long)]
+        fail_on_regression: bool,
+
+        /// Total score difference, in seconds, that counts as a regression
+        #[arg(long, default_value_t = COMPARE_MIN_DIFFERENCE)]
+        regression_threshold: f64,
+
+        /// Significance level for a bench's difference in score to be listed
+        #[arg(long, default_value_t = COMPARE_ALPHA)]
+        alpha: f64,
+
+        /// Don't warn when the sessions used different rules or dictionaries
+        #[arg(long)]
+        allow_mismatch: bool,
+    },
+}
+
+const LIST_SESSIONS_EXTRA_COLUMNS: &[&str] = &[
+    "tag",
+    "version_buscaluso",
+    "machine",
+    "search_rules_hash",
+    "dict_word_count",
+];
+
+/// What `list-sessions` shows for a session's status: it flags sessions whose run never finished,
+/// which most likely crashed, and leaves it blank for sessions from before it was recorded.
+fn list_sessions_status(status: &str) -> &str {
+    match status {
+        "" | SESSION_COMPLETE => status,
+        _ => "\u{26a0} incomplete",
+    }
+}
+
+/// Picks the info names to show as extra `list-sessions` columns: the requested ones, or else the
+/// defaults. Also returns the requested ones that aren't in `known_names`, which will be blank.
+fn list_sessions_columns(
+    requested: Option<&[String]>,
+    known_names: &BTreeSet<String>,
+) -> (Vec<String>, Vec<String>) {
+    match requested {
+        Some(requested) => {
+            let unknown = requested
+                .iter()
+                .filter(|name| !known_names.contains(*name))
+                .cloned()
+                .collect();
+            (requested.to_vec(), unknown)
+        }
+        None => (
+            LIST_SESSIONS_EXTRA_COLUMNS
+                .iter()
+                .map(|&name| name.to_owned())
+                .collect(),
+            Vec::new(),
+        ),
+    }
+}
+
+/// How much the times of a bench's results can vary, as their standard deviation over their mean,
+/// before `results` marks them as noisy.
+const RESULTS_COV_THRESHOLD: f64 = 0.25;
+
+/// The mark `results` puts after noisy times.
+const NOISY_TIME_MARKER: &str = " *";
+
+/// What `option-stats` calls the wins it can't put down to an option, like ones from older versions
+/// that didn't record the word they found.
+const UNKNOWN_OPTION_LABEL: &str = "(unknown)";
+
+/// What `errors --by-kind` calls errors whose kind wasn't recorded, like ones from older versions.
+const UNKNOWN_ERROR_KIND_LABEL: &str = "(unknown)";
+
+/// How many times in a row a bench's score has to get worse for `trend` to warn about it.
+const TREND_ALARM_RISES: usize = 2;
+
+/// Session info that `show` lists first, in this order, before the rest alphabetically.
+const SHOW_FIRST_KEYS: &[&str] = &["machine", "tag", "cli_args"];
+
+/// Session info that should match for a comparison to make sense, and what to call it.
+const COMPARE_MATCHING_INFO: &[(&str, &str)] = &[
+    ("search_rules_hash", "rules"),
+    ("search_dict_hash", "dictionaries"),
+    (EFFECTIVE_TIMEOUT_INFO, "effective timeouts"),
+];
+
+const FOUND_INDEX_BUCKETS: &[(usize, &str)] = &[
+    (0, "0"),
+    (2, "1-2"),
+    (8, "3-8"),
+    (32, "9-32"),
+    (usize::MAX, "33+"),
+];
+
+/// Arrows for how each score changed from the one before: "↑" when it got worse,
+/// "↓" when it got better, and "=" when it changed by less than `min_difference` seconds.
+/// Not finding the target counts as worse than any score. The first score gets an empty string.
+fn trend_arrows(scores: &[Option<Duration>], min_difference: f64) -> Vec<&'static str> {
+    let mut arrows = vec![""; scores.len().min(1)];
+    for pair in scores.windows(2) {
+        arrows.push(match (pair[0], pair[1]) {
+            (Some(prior), Some(score)) => {
+                let difference = score.as_secs_f64() - prior.as_secs_f64();
+                if difference >= min_difference {
+                    "↑"
+                } else if difference <= -min_difference {
+                    "↓"
+                } else {
+                    "="
+                }
+            }
+            (Some(_), None) => "↑",
+            (None, Some(_)) => "↓",
+            (None, None) => "=",
+        });
+    }
+    arrows
+}
+
+/// Whether compiling kept the result that scored `i`th best, or dropped it.
+fn compile_step_label(steps: &CompileSteps, i: usize) -> &'static str {
+    if i < steps.drop_low {
+        "dropped (best)"
+    } else if i >= steps.scored.len() - steps.drop_high {
+        "dropped (worst)"
+    } else {
+        "kept"
+    }
+}
+
+/// A table of how many benches were found, and their average score, in each group.
+fn group_table<L: Display>(
+    column: &str,
+    groups: impl IntoIterator<Item = (L, GroupStats)>,
+    precision: usize,
+) -> AlignedTable {
+    let mut table = AlignedTable::new_cloned([column, "FOUND", "AVERAGE SCORE"], " | ");
+    for (label, group) in groups {
+        table.add_row(vec![
+            label.to_string(),
+            format!(
+                "{} / {} ({:.1}%)",
+                group.num_found,
+                group.num_benches,
+                group.percent_found()
+            ),
+            fmt_duration(&group.average_score(), precision),
+        ]);
+    }
+    table
+}
+
+fn format_datetime(when: SystemTime) -> String {
+    OffsetDateTime::from(when)
+        .format(format_description!(
+            "[year]-[month]-[day] [hour]:[minute]:[second]"
+        ))
+        .expect("Couldn't format SystemTime into Y-M-D H:M:S")
+}
+
+/// Fits a least-squares line of elapsed time against found index, and returns its slope
+/// in seconds per index. Gives `None` unless there are at least two different indices
+/// and the slope is positive.
+fn estimate_index_equivalent(points: &[(usize, Duration)]) -> Option<f64> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|&(x, _)| x as f64).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y.as_secs_f64()).sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance = 0.0;
+    for (x, y) in points {
+        let dx = *x as f64 - mean_x;
+        covariance += dx * (y.as_secs_f64() - mean_y);
+        variance += dx * dx;
+    }
+    let slope = covariance / variance;
+    (variance > 0.0 && slope > 0.0).then_some(slope)
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const HTML_STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+th { background: #eee; }
+tr.not-found td { color: #a00; }
+pre { margin: 0; white-space: pre-wrap; }
+"#;
+
+/// Renders a session's metadata and compiled results as a standalone HTML page.
+fn html_report(
+    session: BenchSessionId,
+    info: &BTreeMap<String, String>,
+    compiled: &BTreeMap<String, CompiledBenchResult>,
+    precision: usize,
+) -> String {
+    let num_found = compiled
+        .iter()
+        .filter(|(_, result)| result.found_index.is_some())
+        .count();
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str(&format!(
+        "<title>Buscaluso bench session {}</title>\n<style>{}</style>\n</head>\n<body>\n",
+        session, HTML_STYLE
+    ));
+    html.push_str(&format!("<h1>Session {}</h1>\n", session));
+    html.push_str(&format!(
+        "<p>Found {} / {} ({:.1}%)</p>\n",
+        num_found,
+        compiled.len(),
+        num_found as f64 / compiled.len() as f64 * 100.0
+    ));
+
+    html.push_str("<h2>Results</h2>\n<table>\n");
+    html.push_str("<tr><th>Bench</th><th>Score</th><th>Index</th><th>Time (sec)</th></tr>\n");
+    for (bench, result) in compiled {
+        let class = if result.found_index.is_some() {
+            ""
+        } else {
+            " class=\"not-found\""
+        };
+        html.push_str(&format!(
+            "<tr{}><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+            class,
+            escape_html(bench),
+            escape_html(&fmt_duration(&result.score, precision)),
+            escape_html(&fmt_range(&result.found_index)),
+            escape_html(&fmt_duration_range(&result.elapsed, precision)),
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Session info</h2>\n<table>\n");
+    for (key, value) in info {
+        html.push_str(&format!(
+            "<tr><th>{}</th><td><pre>{}</pre></td></tr>\n",
+            escape_html(key),
+            escape_html(value)
+        ));
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// Escapes a Prometheus label value.
+fn escape_label_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Formats compiled bench results as lines of Prometheus's text exposition format.
+/// Benches that never found their target get no score or index lines.
+fn prometheus_lines(
+    session: BenchSessionId,
+    compiled: &BTreeMap<String, CompiledBenchResult>,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let session_label = format!("session=\"{}\"", session);
+    lines.push("# TYPE buscaluso_bench_score_seconds gauge".to_string());
+    for (bench, result) in compiled {
+        if let Some(score) = result.score {
+            lines.push(format!(
+                "buscaluso_bench_score_seconds{{bench=\"{}\",{}}} {}",
+                escape_label_value(bench),
+                session_label,
+                score.as_secs_f64()
+            ));
+        }
+    }
+    lines.push("# TYPE buscaluso_bench_found_index gauge".to_string());
+    for (bench, result) in compiled {
+        if let Some(found_index) = &result.found_index {
+            lines.push(format!(
+                "buscaluso_bench_found_index{{bench=\"{}\",{}}} {}",
+                escape_label_value(bench),
+                session_label,
+                found_index.start()
+            ));
+        }
+    }
+    let num_found = compiled
+        .iter()
+        .filter(|(_, result)| result.found_index.is_some())
+        .count();
+    lines.push("# TYPE buscaluso_bench_found_fraction gauge".to_string());
+    lines.push(format!(
+        "buscaluso_bench_found_fraction{{{}}} {}",
+        session_label,
+        num_found as f64 / compiled.len() as f64
+    ));
+    lines
+}
+
+/// A line of a `dump` archive, which doesn't depend on how the database is laid out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ArchiveRecord {
+    Info {
+        session: BenchSessionId,
+        name: String,
+        value: String,
+    },
+    Weight {
+        session: BenchSessionId,
+        bench: String,
+        weight: f64,
+    },
+    Result {
+        session: BenchSessionId,
+        start_word: String,
+        targets: BTreeSet<String>,
+        /// Missing from archives dumped before it was added, whose results are added as new runs.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        run_idx: Option<usize>,
+        duration: f64,
+        found_at: Option<usize>,
+        err: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        err_kind: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        found_word: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        setup_secs: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        first_result_secs: Option<f64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stopped_by: Option<SearchLimit>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        peak_rss: Option<u64>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        section: Option<String>,
+    },
+    Trace {
+        session: BenchSessionId,
+        start_word: String,
+        run: usize,
+        words: Vec<String>,
+    },
+}
+
+/// Writes every session's info, weights, results and traces as JSON lines, returning how many.
+/// Results of benches whose names couldn't be split up when migrating an older database are left out.
+fn dump_archive(db: &mut BenchDb, out: &mut dyn Write) -> Result<usize, Box<dyn Error>> {
+    let mut num_records = 0;
+    let mut write_record = |out: &mut dyn Write, record: ArchiveRecord| -> io::Result<()> {
+        serde_json::to_writer(&mut *out, &record)?;
+        num_records += 1;
+        writeln!(out)
+    };
+    for session in db.all_session_ids()? {
+        for (name, value) in db.get_all_info(session)? {
+            write_record(
+                out,
+                ArchiveRecord::Info {
+                    session,
+                    name,
+                    value,
+                },
+            )?;
+        }
+        for (bench, weight) in db.get_weights(session)? {
+            write_record(
+                out,
+                ArchiveRecord::Weight {
+                    session,
+                    bench,
+                    weight,
+                },
+            )?;
+        }
+        let sections = db.get_sections(session)?;
+        for key in db.get_bench_keys(session)? {
+            let section = sections.get(&key.name());
+            for (run_idx, result) in db.get_runs(session, &key.name())? {
+                let (found_at, err) = match result.found_index() {
+                    Ok(found_at) => (found_at, None),
+                    Err(err) => (None, Some(err.to_string())),
+                };
+                write_record(
+                    out,
+                    ArchiveRecord::Result {
+                        session,
+                        start_word: key.start_word.clone(),
+                        targets: key.targets.clone(),
+                        run_idx: Some(run_idx),
+                        duration: result.elapsed().as_secs_f64(),
+                        found_at,
+                        err,
+                        err_kind: result.error_kind().map(str::to_string),
+                        found_word: result.found_word().map(str::to_string),
+                        setup_secs: result.setup().map(|setup| setup.as_secs_f64()),
+                        first_result_secs: result.first_result().map(|first| first.as_secs_f64()),
+                        stopped_by: result.stopped_by(),
+                        peak_rss: result.peak_rss(),
+                        section: section.cloned(),
+                    },
+                )?;
+            }
+        }
+        for start_word in db.get_traced_start_words(session)? {
+            for (run, words) in db.get_traces(session, &start_word)?.into_iter().enumerate() {
+                write_record(
+                    out,
+                    ArchiveRecord::Trace {
+                        session,
+                        start_word: start_word.clone(),
+                        run,
+                        words,
+                    },
+                )?;
+            }
+        }
+    }
+    Ok(num_records)
+}
+
+/// Adds all the records from a `dump` archive, returning how many.
+/// Fails without adding anything if any of its sessions are already in the database.
+fn load_archive(db: &mut BenchDb, input: impl BufRead) -> Result<usize, Box<dyn Error>> {
+    let mut records = Vec::new();
+    for line in input.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            records.push(serde_json::from_str::<ArchiveRecord>(&line)?);
+        }
+    }
+    let existing: BTreeSet<BenchSessionId> = db.all_session_ids()?.into_iter().collect();
+    for record in &records {
+        let (ArchiveRecord::Info { session, .. }
+        | ArchiveRecord::Weight { session, .. }
+        | ArchiveRecord::Result { session, .. }
+        | ArchiveRecord::Trace { session, .. }) = record;
+        if existing.contains(session) {
+            return Err(format!("Session {} is already in the database", session).into());
+        }
+    }
+    db.conn.execute_batch("begin")?;
+    for record in &records {
+        match record {
+            ArchiveRecord::Info {
+                session,
+                name,
+                value,
+            } => db.set_info(*session, name, value)?,
+            ArchiveRecord::Weight {
+                session,
+                bench,
+                weight,
+            } => db.set_weight(*session, bench, *weight)?,
+            ArchiveRecord::Result {
+                session,
+                start_word,
+                targets,
+                run_idx,
+                duration,
+                found_at,
+                err,
+                err_kind,
+                found_word,
+                setup_secs,
+                first_result_secs,
+                stopped_by,
+                peak_rss,
+                section,
+            } => {
+                let found_index = match err {
+                    Some(err) => Err(SearchError {
+                        kind: err_kind.clone(),
+                        message: err.clone(),
+                    }),
+                    None => Ok(*found_at),
+                };
+                let result = BenchResult::new(found_index, Duration::from_secs_f64(*duration))
+                    .with_stopped_by(*stopped_by)
+                    .with_peak_rss(*peak_rss)
+                    .with_found_word(found_word.clone())
+                    .with_setup(setup_secs.map(Duration::from_secs_f64))
+                    .with_first_result(first_result_secs.map(Duration::from_secs_f64));
+                let key = BenchKey::new(start_word, targets);
+                match run_idx {
+                    Some(run_idx) => {
+                        db.add_run(*session, &key, *run_idx, result)?;
+                    }
+                    None => db.add_result(*session, &key, result)?,
+                }
+                if let Some(section) = section {
+                    db.set_section(*session, &key.name(), section)?;
+                }
+            }
+            ArchiveRecord::Trace {
+                session,
+                start_word,
+                run,
+                words,
+            } => db.add_trace(*session, start_word, *run, words)?,
+        }
+    }
+    db.conn.execute_batch("commit")?;
+    Ok(records.len())
+}
+
+/// Picks out the sessions that were run with `--quick`, whose timings can't be trusted.
+fn quick_mode_sessions(
+    db: &mut BenchDb,
+    sessions: Vec<BenchSessionId>,
+) -> rusqlite::Result<Vec<BenchSessionId>> {
+    let mut quick = Vec::new();
+    for session in sessions {
+        if db.get_info(session, "quick_mode")? == "true" {
+            quick.push(session);
+        }
+    }
+    Ok(quick)
+}
+
+/// Formats a bench file with `format_bench_file`, replacing it with a renamed temporary file
+/// so that it's never left half written. Returns whether it changed.
+fn format_benchfile_in_place(path: &Path) -> Result<bool, Box<dyn Error>> {
+    let original = std::fs::read_to_string(path)?;
+    let formatted = format_bench_file(&original)?;
+    if formatted == original {
+        return Ok(false);
+    }
+    let mut temp_name = path.file_name().ok_or("Not a file path")?.to_owned();
+    temp_name.push(".tmp");
+    let temp_path = path.with_file_name(temp_name);
+    std::fs::write(&temp_path, formatted)?;
+    std::fs::rename(&temp_path, path)?;
+    Ok(true)
+}
+
+/// Opens the given file to write output to, or standard output if there's none.
+fn open_output(path: Option<&Path>) -> io::Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    })
+}
+
+/// Orders session info for `show`: the `SHOW_FIRST_KEYS` that are present, then the rest.
+fn show_order(mut info: BTreeMap<String, String>) -> Vec<(String, String)> {
+    let mut ordered: Vec<(String, String)> = SHOW_FIRST_KEYS
+        .iter()
+        .filter_map(|&key| info.remove_entry(key))
+        .collect();
+    ordered.extend(info);
+    ordered
+}
+
+fn hide_multiline(value: String) -> String {
+    if value.contains('\n') {
+        "<...>".to_string()
+    } else {
+        value
+    }
+}
+
+/// Returns each info key whose value differs between the two sessions, with both values.
+/// A key missing from one session has an empty value there.
+fn info_diff(
+    db: &mut BenchDb,
+    session_a: BenchSessionId,
+    session_b: BenchSessionId,
+) -> rusqlite::Result<Vec<(String, String, String)>> {
+    let mut info_a = db.get_all_info(session_a)?;
+    let mut info_b = db.get_all_info(session_b)?;
+    let keys: BTreeSet<String> = info_a.keys().chain(info_b.keys()).cloned().collect();
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| {
+            let value_a = info_a.remove(&key).unwrap_or_default();
+            let value_b = info_b.remove(&key).unwrap_or_default();
+            (value_a != value_b).then_some((key, value_a, value_b))
+        })
+        .collect())
+}
+
+/// Returns which of the given info keys have different values in the two sessions.
+fn mismatched_info<'k>(
+    db: &mut BenchDb,
+    session_a: BenchSessionId,
+    session_b: BenchSessionId,
+    keys: impl IntoIterator<Item = &'k str>,
+) -> rusqlite::Result<Vec<&'k str>> {
+    let mut mismatched = Vec::new();
+    for key in keys {
+        let (a, b) = (
+            compare_info(db, session_a, key)?,
+            compare_info(db, session_b, key)?,
+        );
+        // Sessions from before the effective timeout was recorded may not have any way to tell
+        let unknown = key == EFFECTIVE_TIMEOUT_INFO && (a.is_empty() || b.is_empty());
+        if a != b && !unknown {
+            mismatched.push(key);
+        }
+    }
+    Ok(mismatched)
+}
+
+const EFFECTIVE_TIMEOUT_INFO: &str = "effective_timeout_secs";
+
+/// Gets a session's info to compare with another's, working out the effective timeout
+/// from its `bench_config` if it's from before that was recorded.
+fn compare_info(db: &mut BenchDb, session: BenchSessionId, key: &str) -> rusqlite::Result<String> {
+    let value = db.get_info(session, key)?;
+    if value.is_empty() && key == EFFECTIVE_TIMEOUT_INFO {
+        if let Ok(run_cfg) = toml::from_str::<BenchRunCfg>(&db.get_info(session, "bench_config")?) {
+            return Ok(run_cfg.effective_timeout().as_secs_f64().to_string());
+        }
+    }
+    Ok(value)
+}
+
+/// Parses a YYYY-MM-DD date into the time at the start of that day in UTC.
+fn parse_date(s: &str) -> Result<SystemTime, String> {
+    let date = Date::parse(s, format_description!("[year]-[month]-[day]"))
+        .map_err(|err| err.to_string())?;
+    Ok(date.midnight().assume_utc().into())
+}
+
+/// What to say when a command finds nothing to show for a session: `empty` if the session
+/// is there but doesn't have what the command needs, or otherwise that there's no such session.
+fn missing_session_message(
+    db: &mut BenchDb,
+    session: BenchSessionId,
+    empty: &'static str,
+) -> rusqlite::Result<&'static str> {
+    Ok(if db.session_exists(session)? {
+        empty
+    } else {
+        "Session not found"
+    })
+}
+
+/// The session's `dict_word_count` info, if it's there and not zero.
+fn dict_word_count(db: &mut BenchDb, session: BenchSessionId) -> rusqlite::Result<Option<usize>> {
+    Ok(db
+        .get_info(session, "dict_word_count")?
+        .parse()
+        .ok()
+        .filter(|&count| count > 0))
+}
+
+fn percent_of(index: f64, dict_word_count: usize) -> f64 {
+    index / dict_word_count as f64 * 100.0
+}
+
+/// Formats a range of found indices as percentages of the dictionary's size,
+/// with `precision` decimal places.
+fn fmt_relative_range(
+    range: &Option<RangeInclusive<usize>>,
+    dict_word_count: usize,
+    precision: usize,
+) -> String {
+    let percent = |index: usize| percent_of(index as f64, dict_word_count);
+    match range {
+        Some(range) if range.start() == range.end() => {
+            format!("{:.*}%", precision, percent(*range.start()))
+        }
+        Some(range) => format!(
+            "{:.*}% .. {:.*}%",
+            precision,
+            percent(*range.start()),
+            precision,
+            percent(*range.end())
+        ),
+        None => "--".into(),
+    }
+}
+
+fn found_index_histogram(
+    found_indices: impl IntoIterator<Item = Option<usize>>,
+) -> Vec<(&'static str, usize)> {
+    let mut counts = vec![0; FOUND_INDEX_BUCKETS.len() + 1];
+    for found_index in found_indices {
+        let bucket = match found_index {
+            Some(index) => FOUND_INDEX_BUCKETS
+                .iter()
+                .position(|&(max_index, _)| index <= max_index)
+                .unwrap(),
+            None => FOUND_INDEX_BUCKETS.len(),
+        };
+        counts[bucket] += 1;
+    }
+    zip(
+        FOUND_INDEX_BUCKETS
+            .iter()
+            .map(|&(_, label)| label)
+            .chain(["not found"]),
+        counts,
+    )
+    .collect()
+}
+
+/// Tallies how many wins each target option got, from counts of the words that successful results
+/// found, in the options' order. The last count is of the wins that can't be put down to any option,
+/// because they didn't record their word, or it doesn't match.
+/// With `case_insensitive`, words match options regardless of case, like they did in the search.
+fn option_tallies(
+    options: &BTreeSet<String>,
+    found_words: &[(Option<String>, usize)],
+    case_insensitive: bool,
+) -> (Vec<(String, usize)>, usize) {
+    let matches = if case_insensitive {
+        target_option_matches_any_case
+    } else {
+        target_option_matches
+    };
+    let mut tallies: Vec<(String, usize)> =
+        options.iter().map(|option| (option.clone(), 0)).collect();
+    let mut unattributed = 0;
+    for (word, count) in found_words {
+        let tally = word
+            .as_deref()
+            .and_then(|word| tallies.iter_mut().find(|(option, _)| matches(option, word)));
+        match tally {
+            Some((_, wins)) => *wins += count,
+            None => unattributed += count,
+        }
+    }
+    (tallies, unattributed)
+}
+
+/// One estimate like those in Criterion's `estimates.json`, in nanoseconds.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CriterionEstimate {
+    confidence_interval: CriterionInterval,
+    point_estimate: f64,
+    standard_error: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CriterionInterval {
+    confidence_level: f64,
+    lower_bound: f64,
+    upper_bound: f64,
+}
+
+/// The estimates Criterion's dashboards read for a benchmark.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct CriterionEstimates {
+    mean: CriterionEstimate,
+    median: CriterionEstimate,
+    std_dev: CriterionEstimate,
+}
+
+/// The confidence level of the interval `export --format criterion` gives for the mean.
+const CRITERION_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// How many standard errors either side of the mean make up its confidence interval.
+const CRITERION_CONFIDENCE_Z: f64 = 1.96;
+
+impl CriterionEstimate {
+    fn new(point_estimate: f64, standard_error: f64) -> CriterionEstimate {
+        let margin = CRITERION_CONFIDENCE_Z * standard_error;
+        CriterionEstimate {
+            confidence_interval: CriterionInterval {
+                confidence_level: CRITERION_CONFIDENCE_LEVEL,
+                lower_bound: point_estimate - margin,
+                upper_bound: point_estimate + margin,
+            },
+            point_estimate,
+            standard_error,
+        }
+    }
+}
+
+/// Approximates Criterion's estimates for a bench that found its target, from its scores
+/// rather than raw times: the mean is the bench's score, and the median and standard deviation
+/// are of its kept scores. Only the mean gets a standard error; the others are exact for the
+/// scores we have. Without kept scores, like from an older summary, the median is the mean
+/// and the standard deviation is zero.
+fn criterion_estimates(compiled: &CompiledBenchResult) -> Option<CriterionEstimates> {
+    let nanos = |duration: Duration| duration.as_secs_f64() * 1e9;
+    let mean = nanos(compiled.score?);
+    let scores: Vec<f64> = compiled.kept_scores.iter().copied().map(nanos).collect();
+    let median = match scores.len() {
+        0 => mean,
+        n if n % 2 == 1 => scores[n / 2],
+        n => (scores[n / 2 - 1] + scores[n / 2]) / 2.0,
+    };
+    let std_dev = sample_std_dev(&scores).unwrap_or(0.0);
+    let standard_error = match scores.len() {
+        0 => 0.0,
+        n => std_dev / (n as f64).sqrt(),
+    };
+    Some(CriterionEstimates {
+        mean: CriterionEstimate::new(mean, standard_error),
+        median: CriterionEstimate::new(median, 0.0),
+        std_dev: CriterionEstimate::new(std_dev, 0.0),
+    })
+}
+
+/// How an input file compares to the one a session used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputCheck {
+    Matches,
+    Differs,
+    /// The session didn't store a hash for it.
+    NotRecorded,
+}
+
+impl InputCheck {
+    fn label(self) -> &'static str {
+        match self {
+            InputCheck::Matches => "matches",
+            InputCheck::Differs => "DIFFERS",
+            InputCheck::NotRecorded => "not recorded",
+        }
+    }
+}
+
+/// Compares a file's hash to the one stored in a session's info, which is empty if there isn't one.
+fn check_input_hash(stored: &str, current: &str) -> InputCheck {
+    if stored.is_empty() {
+        InputCheck::NotRecorded
+    } else if stored == current {
+        InputCheck::Matches
+    } else {
+        InputCheck::Differs
+    }
+}
+
+/// Compares bench files' hashes to the session's `bench_files_hash`, a JSON array of them in order.
+fn check_bench_hashes(stored: &str, current: &[String]) -> InputCheck {
+    if stored.is_empty() {
+        return InputCheck::NotRecorded;
+    }
+    match serde_json::from_str::<Vec<String>>(stored) {
+        Ok(stored) if stored == current => InputCheck::Matches,
+        _ => InputCheck::Differs,
+    }
+}
+
+/// A bench whose score changed from the baseline.
+#[derive(Debug, Clone, PartialEq)]
+struct BaselineChange {
+    bench: String,
+    baseline: Option<f64>,
+    current: Option<f64>,
+    is_regression: bool,
+}
+
+/// Finds the benches whose scores differ from the baseline by at least `min_difference` seconds,
+/// or that were found in only one of the two. Benches missing from `current` are skipped.
+fn compare_to_baseline(
+    baseline: &BTreeMap<String, Option<f64>>,
+    current: &BTreeMap<String, Option<f64>>,
+    min_difference: f64,
+) -> Vec<BaselineChange> {
+    let mut changes = Vec::new();
+    for (bench, &baseline_score) in baseline {
+        let Some(&current_score) = current.get(bench) else {
+            continue;
+        };
+        let is_regression = match (baseline_score, current_score) {
+            (Some(b), Some(c)) if (c - b).abs() >= min_difference => c > b,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            _ => continue,
+        };
+        changes.push(BaselineChange {
+            bench: bench.clone(),
+            baseline: baseline_score,
+            current: current_score,
+            is_regression,
+        });
+    }
+    changes
+}
+
+/// Which of two compiled results of a bench is better by enough to list. Finding the target when
+/// the other didn't always counts. Otherwise the difference has to be significant at level `alpha`,
+/// or for summaries stored without their samples, at least `COMPARE_MIN_DIFFERENCE`.
+fn significant_winner(
+    result_a: &CompiledBenchResult,
+    result_b: &CompiledBenchResult,
+    alpha: f64,
+) -> Verdict {
+    let difference = result_a.difference(result_b);
+    let is_significant = |x: &CompiledBenchResult, y: &CompiledBenchResult| {
+        if difference.is_infinite() {
+            true
+        } else if x.has_samples() && y.has_samples() {
+            x.is_significantly_better_than(y, alpha)
+        } else {
+            difference.abs() >= COMPARE_MIN_DIFFERENCE
+        }
+    };
+    if result_a.is_better_than(result_b) && is_significant(result_a, result_b) {
+        Verdict::ABetter
+    } else if result_b.is_better_than(result_a) && is_significant(result_b, result_a) {
+        Verdict::BBetter
+    } else {
+        Verdict::Same
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Verdict {
+    ABetter,
+    BBetter,
+    Same,
+}
+
+#[derive(Debug, Clone, Default)]
+struct CompareTally {
+    wins_a: usize,
+    wins_b: usize,
+    total_difference: f64,
+}
+
+impl CompareTally {
+    fn add(&mut self, result_a: &CompiledBenchResult, result_b: &CompiledBenchResult) -> f64 {
+        let difference = result_a.difference(result_b);
+        if difference.is_finite() {
+            self.total_difference += difference;
+        } else if difference < 0.0 {
+            self.wins_a += 1;
+        } else {
+            self.wins_b += 1;
+        }
+        difference
+    }
+
+    /// Finding more targets trumps any difference in score.
+    fn verdict(&self, threshold: f64) -> Verdict {
+        if self.wins_a > self.wins_b {
+            Verdict::ABetter
+        } else if self.wins_b > self.wins_a {
+            Verdict::BBetter
+        } else if self.total_difference < -threshold {
+            Verdict::ABetter
+        } else if self.total_difference > threshold {
+            Verdict::BBetter
+        } else {
+            Verdict::Same
+        }
+    }
+}
+
+fn fmt_range<T: Display + Eq>(range: &Option<RangeInclusive<T>>) -> String {
+    match range {
+        Some(range) => {
+            let start = range.start();
+            let end = range.end();
+            if start == end {
+                format!("{}", start)
+            } else {
+                format!("{} .. {}", start, end)
+            }
+        }
+        None => "--".into(),
+    }
+}
+
+impl Command {
+    /// Whether this command changes the database, and so needs to open it for writing.
+    fn is_writable(&self) -> bool {
+        matches!(
+            self,
+            Command::Tag { .. }
+                | Command::PruneSuccesses { .. }
+                | Command::Summarize { .. }
+                | Command::Load { .. }
+                | Command::Vacuum
+        )
+    }
+
+    /// Whether this command reads or writes the database at all.
+    fn uses_db(&self) -> bool {
+        !matches!(self, Command::FormatBenchfile { .. })
+    }
+
+    /// The sessions whose results or info this command shows.
+    fn shown_sessions(&self) -> Vec<BenchSessionId> {
+        match *self {
+            Command::Show { session, .. }
+            | Command::Get { session, .. }
+            | Command::Stats { session, .. }
+            | Command::Html { session, .. }
+            | Command::Prometheus { session }
+            | Command::Export { session, .. }
+            | Command::Calibrate { session }
+            | Command::Results { session, .. }
+            | Command::Explain { session, .. }
+            | Command::BenchDetail { session, .. }
+            | Command::OptionStats { session, .. }
+            | Command::Errors { session, .. }
+            | Command::Trace { session, .. }
+            | Command::CompareBaseline { session, .. } => vec![session],
+            Command::InfoDiff {
+                session_a,
+                session_b,
+            }
+            | Command::Compare {
+                session_a,
+                session_b,
+                ..
+            } => vec![session_a, session_b],
+            _ => vec![],
+        }
+    }
+
+    fn run(
+        &self,
+        db: &mut BenchDb,
+        out: &mut dyn Write,
+        precision: usize,
+    ) -> Result<ExitCode, Box<dyn Error>> {
+        match *self {
+            Command::ListSessions {
+                since,
+                format,
+                ref columns,
+            } => {
+                let (columns, unknown) =
+                    list_sessions_columns(columns.as_deref(), &db.info_names()?);
+                for name in unknown {
+                    eprintln!("No session has any info named {:?}", name);
+                }
+                let sessions = db.list_sessions(since.map(BenchSessionId::from_timestamp))?;
+                let mut table = AlignedTable::new_cloned(
+                    ["SESSION ID", "WHEN", "NUM BENCHES", "STATUS"]
+                        .into_iter()
+                        .chain(columns.iter().map(String::as_str)),
+                    " | ",
+                );
+                let mut json_sessions = Vec::new();
+                for session in sessions {
+                    let when = format_datetime(session.start_time);
+                    let status = db.get_info(session.id, SESSION_STATUS_INFO)?;
+                    let mut extra = Vec::new();
+                    for key in &columns {
+                        extra.push(db.get_info(session.id, key)?);
+                    }
+                    match format {
+                        OutputFormat::Table => {
+                            let mut row = vec![
+                                session.id.to_string(),
+                                when,
+                                session.num_benches.to_string(),
+                                list_sessions_status(&status).to_string(),
+                            ];
+                            row.extend(extra);
+                            table.add_row(row);
+                        }
+                        OutputFormat::Json => {
+                            let mut object = serde_json::Map::new();
+                            object.insert("session_id".into(), serde_json::to_value(session.id)?);
+                            object.insert("when".into(), when.into());
+                            object.insert("num_benches".into(), session.num_benches.into());
+                            object.insert("status".into(), status.into());
+                            for (key, value) in zip(&columns, extra) {
+                                object.insert(key.clone(), value.into());
+                            }
+                            json_sessions.push(serde_json::Value::Object(object));
+                        }
+                    }
+                }
+
+                match format {
+                    OutputFormat::Table => writeln!(out, "{}", table)?,
+                    OutputFormat::Json => {
+                        writeln!(out, "{}", serde_json::to_string_pretty(&json_sessions)?)?
+                    }
+                }
+            }
+
+            Command::Show { session, format } => {
+                let mut table = AlignedTable::new_cloned(["KEY", "VALUE"], " | ");
+                let info = db.get_all_info(session)?;
+                if info.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no info")?
+                    );
+                } else if format == OutputFormat::Json {
+                    writeln!(out, "{}", serde_json::to_string_pretty(&info)?)?;
+                } else {
+                    for (key, value) in show_order(info) {
+                        table.add_row(vec![key, hide_multiline(value)]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::InfoDiff {
+                session_a,
+                session_b,
+            } => {
+                let diff = info_diff(db, session_a, session_b)?;
+                if diff.is_empty() {
+                    eprintln!("No differences");
+                } else {
+                    let mut table = AlignedTable::new_cloned(["KEY", "A", "B"], " | ");
+                    for (key, value_a, value_b) in diff {
+                        table.add_row(vec![key, hide_multiline(value_a), hide_multiline(value_b)]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::Get {
+                session,
+                ref info_key,
+                format,
+            } => {
+                let value = db.get_info(session, info_key)?;
+                if format == OutputFormat::Json {
+                    writeln!(out, "{}", serde_json::to_string(&value)?)?;
+                } else {
+                    write!(out, "{}", value)?;
+                    if !value.is_empty() && !value.ends_with('\n') {
+                        writeln!(out)?;
+                    }
+                }
+            }
+
+            Command::Results {
+                session,
+                relative,
+                cov_threshold,
+            } => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                )
+                .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED);
+                let compiled_benches = compile_session(db, &compiler, session)?;
+                if compiled_benches.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    let dict_word_count = if relative {
+                        let count = dict_word_count(db, session)?;
+                        if count.is_none() {
+                            eprintln!(
+                                "No dictionary word count in the session info; showing absolute indices"
+                            );
+                        }
+                        count
+                    } else {
+                        None
+                    };
+                    let index_header = match dict_word_count {
+                        Some(_) => "INDEX (% of dict)",
+                        None => "INDEX",
+                    };
+                    let fmt_index =
+                        |found_index: &Option<RangeInclusive<usize>>| match dict_word_count {
+                            Some(count) => fmt_relative_range(found_index, count, precision),
+                            None => fmt_range(found_index),
+                        };
+                    let mut table = AlignedTable::new_cloned(
+                        ["BENCH", "SCORE", "95% CI", index_header, "TIME (sec)"],
+                        " | ",
+                    );
+                    let num_benches = compiled_benches.len();
+                    let mut num_found = 0;
+                    let mut total_score = Duration::ZERO;
+                    let mut total_index = 0;
+                    let mut total_elapsed = Duration::ZERO..=Duration::ZERO;
+                    let mut any_noisy = false;
+                    for (bench, compiled) in compiled_benches {
+                        if let (Some(score), Some(found_index), Some(elapsed)) =
+                            (compiled.score, &compiled.found_index, &compiled.elapsed)
+                        {
+                            num_found += 1;
+                            total_score += score;
+                            total_index += found_index.start();
+                            total_elapsed = *total_elapsed.start() + *elapsed.start()
+                                ..=*total_elapsed.end() + *elapsed.end();
+                        }
+                        let mut time = fmt_duration_range(&compiled.elapsed, precision);
+                        if compiled.elapsed_cov.is_some_and(|cov| cov > cov_threshold) {
+                            time.push_str(NOISY_TIME_MARKER);
+                            any_noisy = true;
+                        }
+                        table.add_row(vec![
+                            bench,
+                            fmt_duration(&compiled.score, precision),
+                            fmt_duration_range(&compiled.score_ci, precision),
+                            fmt_index(&compiled.found_index),
+                            time,
+                        ]);
+                    }
+                    if num_found > 0 {
+                        let n = num_found as u32;
+                        table.set_footer(vec![
+                            format!("AVERAGE ({} / {} found)", num_found, num_benches),
+                            fmt_duration(&Some(total_score / n), precision),
+                            String::new(),
+                            match dict_word_count {
+                                Some(count) => format!(
+                                    "{:.*}%",
+                                    precision,
+                                    percent_of(total_index as f64 / num_found as f64, count)
+                                ),
+                                None => format!("{:.1}", total_index as f64 / num_found as f64),
+                            },
+                            fmt_duration_range(
+                                &Some(*total_elapsed.start() / n..=*total_elapsed.end() / n),
+                                precision,
+                            ),
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                    if any_noisy {
+                        writeln!(
+                            out,
+                            "{} Times vary by a standard deviation of more than {}% of their mean",
+                            NOISY_TIME_MARKER.trim(),
+                            cov_threshold * 100.0
+                        )?;
+                    }
+                }
+            }
+
+            Command::Html {
+                session,
+                out: ref html_path,
+            } => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let compiled = compile_session(db, &compiler, session)?;
+                if compiled.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    let html =
+                        html_report(session, &db.get_all_info(session)?, &compiled, precision);
+                    match html_path {
+                        Some(path) => std::fs::write(path, html)?,
+                        None => out.write_all(html.as_bytes())?,
+                    }
+                }
+            }
+
+            Command::Prometheus { session } => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let compiled = compile_session(db, &compiler, session)?;
+                if compiled.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    for line in prometheus_lines(session, &compiled) {
+                        writeln!(out, "{}", line)?;
+                    }
+                }
+            }
+
+            Command::Export {
+                session,
+                format,
+                name_style,
+            } => {
+                let benches = db.get_bench_names(session)?;
+                if benches.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    let compiler = BenchResultCompiler::new(
+                        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                        COMPARE_DROP_FRACTION,
+                    );
+                    match format {
+                        ExportFormat::Criterion => {
+                            let mut estimates = BTreeMap::new();
+                            for bench in benches {
+                                let compiled = compile_bench(db, &compiler, session, &bench)?;
+                                match criterion_estimates(&compiled) {
+                                    Some(bench_estimates) => {
+                                        estimates.insert(
+                                            bench_name_with_style(&bench, name_style),
+                                            bench_estimates,
+                                        );
+                                    }
+                                    None => eprintln!(
+                                        "Skipping {:?}, which never found its target",
+                                        bench
+                                    ),
+                                }
+                            }
+                            writeln!(out, "{}", serde_json::to_string_pretty(&estimates)?)?;
+                        }
+                        ExportFormat::Baseline => {
+                            let scores: BTreeMap<String, Option<f64>> =
+                                compile_session(db, &compiler, session)?
+                                    .into_iter()
+                                    .map(|(bench, compiled)| {
+                                        (
+                                            bench_name_with_style(&bench, name_style),
+                                            compiled.score.map(|score| score.as_secs_f64()),
+                                        )
+                                    })
+                                    .collect();
+                            writeln!(out, "{}", serde_json::to_string_pretty(&scores)?)?;
+                        }
+                    }
+                }
+            }
+
+            Command::Calibrate { session } => {
+                let points: rusqlite::Result<Vec<(usize, Duration)>> = db
+                    .conn
+                    .prepare(
+                        r#"
+                        select found_at, duration
+                            from bench_run
+                            where session_id = ?
+                              and found_at is not null
+                              and err is null
+                        "#,
+                    )?
+                    .query_map([session], |row| {
+                        Ok((row.get(0)?, Duration::from_secs_f64(row.get(1)?)))
+                    })?
+                    .collect();
+                let points = points?;
+                match estimate_index_equivalent(&points) {
+                    Some(index_equivalent) => writeln!(
+                        out,
+                        "Suggested index equivalent: {:.4} sec (from {} results; currently {:.4} sec)",
+                        index_equivalent,
+                        points.len(),
+                        COMPARE_INDEX_EQUIVALENT
+                    )?,
+                    None => eprintln!("Not enough varied successful results to calibrate"),
+                }
+            }
+
+            Command::Stats {
+                session,
+                by_accent,
+                by_section,
+            } => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let stats = session_stats(db, &compiler, session)?;
+                if stats.all.num_benches == 0 {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    stats.write_summary(out, precision)?;
+                    let mut table = AlignedTable::new_cloned(["INDEX", "BENCHES"], " | ");
+                    for (label, count) in found_index_histogram(stats.found_indices) {
+                        table.add_row(vec![label.to_string(), count.to_string()]);
+                    }
+                    writeln!(out, "\n{}", table)?;
+                    if by_accent {
+                        let table = group_table("START WORD", stats.accent_groups, precision);
+                        writeln!(out, "\n{}", table)?;
+                    }
+                    if by_section {
+                        let table = group_table("SECTION", stats.section_groups, precision);
+                        writeln!(out, "\n{}", table)?;
+                    }
+                }
+            }
+
+            Command::Errors {
+                session,
+                by_kind: true,
+            } => {
+                let errors = db.get_error_kind_counts(session)?;
+                if errors.is_empty() {
+                    eprintln!("No errors found");
+                } else {
+                    let mut table = AlignedTable::new_cloned(
+                        ["KIND", "COUNT", "EXAMPLE ERROR", "EXAMPLE BENCH"],
+                        " | ",
+                    );
+                    for error in errors {
+                        table.add_row(vec![
+                            error
+                                .kind
+                                .unwrap_or_else(|| UNKNOWN_ERROR_KIND_LABEL.into()),
+                            error.count.to_string(),
+                            error.example_err,
+                            error.example_bench,
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::Errors {
+                session,
+                by_kind: false,
+            } => {
+                let errors = db.get_error_counts(session)?;
+                if errors.is_empty() {
+                    eprintln!("No errors found");
+                } else {
+                    let mut table =
+                        AlignedTable::new_cloned(["ERROR", "COUNT", "EXAMPLE BENCH"], " | ");
+                    for error in errors {
+                        table.add_row(vec![
+                            error.err,
+                            error.count.to_string(),
+                            error.example_bench,
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::NeverFound => {
+                let benches = db.get_never_found_benches()?;
+                if benches.is_empty() {
+                    eprintln!("Every bench found its target at least once");
+                } else {
+                    let mut table = AlignedTable::new_cloned(["BENCH", "SESSIONS"], " | ");
+                    for (bench, num_sessions) in benches {
+                        table.add_row(vec![bench, num_sessions.to_string()]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::Tag { session, ref tag } => {
+                db.set_info(session, "tag", tag)?;
+            }
+
+            Command::BenchDetail { session, ref bench } => {
+                let results = db.get_results(session, bench)?;
+                if results.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Bench not found in session")?
+                    );
+                } else {
+                    let compiler = BenchResultCompiler::new(
+                        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                        COMPARE_DROP_FRACTION,
+                    );
+                    let steps = compiler.compile_verbose(&results);
+                    let mut used = vec![""; results.len()];
+                    for (i, &(_, result)) in steps.scored.iter().enumerate() {
+                        let run = results
+                            .iter()
+                            .position(|r| std::ptr::eq(r, result))
+                            .unwrap();
+                        used[run] = compile_step_label(&steps, i);
+                    }
+                    let mut runs: Vec<usize> = (0..results.len()).collect();
+                    runs.sort_by_key(|&run| results[run].elapsed());
+                    let mut table = AlignedTable::new_cloned(
+                        ["RUN", "TIME (sec)", "FOUND AT", "ERROR", "USED"],
+                        " | ",
+                    );
+                    for run in runs {
+                        let result = &results[run];
+                        let (found_at, err) = match result.found_index() {
+                            Ok(Some(index)) => (index.to_string(), String::new()),
+                            Ok(None) => ("not found".to_string(), String::new()),
+                            Err(err) => (String::new(), err.to_string()),
+                        };
+                        table.add_row(vec![
+                            (run + 1).to_string(),
+                            fmt_duration(&Some(result.elapsed()), precision),
+                            found_at,
+                            err,
+                            used[run].to_string(),
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::OptionStats { session, ref bench } => {
+                let Some(key) = BenchKey::from_name(bench) else {
+                    eprintln!("Not a bench name: {:?}", bench);
+                    return Ok(ExitCode::FAILURE);
+                };
+                let found_words = db.get_found_word_counts(session, bench)?;
+                if found_words.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(
+                            db,
+                            session,
+                            "No successful results for the bench"
+                        )?
+                    );
+                } else {
+                    let total: usize = found_words.iter().map(|(_, count)| count).sum();
+                    let case_insensitive = db.get_info(session, CASE_INSENSITIVE_INFO)? == "true";
+                    let (tallies, unattributed) =
+                        option_tallies(&key.targets, &found_words, case_insensitive);
+                    let mut table = AlignedTable::new_cloned(["OPTION", "WINS", "SHARE"], " | ");
+                    let share = |wins: usize| format!("{:.1}%", wins as f64 / total as f64 * 100.0);
+                    for (option, wins) in tallies {
+                        table.add_row(vec![option, wins.to_string(), share(wins)]);
+                    }
+                    if unattributed > 0 {
+                        table.add_row(vec![
+                            UNKNOWN_OPTION_LABEL.to_string(),
+                            unattributed.to_string(),
+                            share(unattributed),
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::Explain { session, ref bench } => {
+                let results = db.get_results(session, bench)?;
+                if results.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Bench not found in session")?
+                    );
+                } else {
+                    let compiler = BenchResultCompiler::new(
+                        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                        COMPARE_DROP_FRACTION,
+                    );
+                    let steps = compiler.compile_verbose(&results);
+                    writeln!(out, "Compiler: {}", compiler.describe())?;
+                    let mut table =
+                        AlignedTable::new_cloned(["SCORE", "INDEX", "TIME (sec)", "USED"], " | ");
+                    let num_results = steps.scored.len();
+                    for (i, &(score, result)) in steps.scored.iter().enumerate() {
+                        let used = compile_step_label(&steps, i);
+                        table.add_row(vec![
+                            fmt_duration(
+                                &score.is_finite().then(|| Duration::from_secs_f64(score)),
+                                precision,
+                            ),
+                            match result.found_index() {
+                                Ok(Some(index)) => index.to_string(),
+                                Ok(None) => "not found".to_string(),
+                                Err(err) => format!("error: {}", err),
+                            },
+                            fmt_duration(&Some(result.elapsed()), precision),
+                            used.to_string(),
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                    writeln!(
+                        out,
+                        "Dropped the best {} and worst {} of {} results",
+                        steps.drop_low, steps.drop_high, num_results
+                    )?;
+                    writeln!(
+                        out,
+                        "Score: mean of the {} kept = {} sec",
+                        steps.kept().len(),
+                        fmt_duration(&steps.compiled.score, precision)
+                    )?;
+                }
+            }
+
+            Command::Trace {
+                session,
+                ref start_word,
+            } => {
+                let traces = db.get_traces(session, start_word)?;
+                if traces.is_empty() {
+                    eprintln!("No trace found");
+                } else {
+                    let mut table = AlignedTable::new_cloned(["RUN", "INDEX", "WORD"], " | ");
+                    for (run, words) in traces.into_iter().enumerate() {
+                        for (word_idx, word) in words.into_iter().enumerate() {
+                            table.add_row(vec![run.to_string(), word_idx.to_string(), word]);
+                        }
+                    }
+                    writeln!(out, "{}", table)?;
+                }
+            }
+
+            Command::PruneSuccesses { session } => {
+                let deleted = db.delete_successful_results(session)?;
+                writeln!(out, "Deleted {} successful results", deleted)?;
+            }
+
+            Command::Trend { ref bench, last } => {
+                let mut sessions = db.get_bench_sessions(bench)?;
+                if sessions.is_empty() {
+                    eprintln!("Bench not found");
+                } else {
+                    sessions.drain(..sessions.len().saturating_sub(last));
+                    let compiler = BenchResultCompiler::new(
+                        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                        COMPARE_DROP_FRACTION,
+                    );
+                    let mut scores = Vec::new();
+                    for &session in &sessions {
+                        scores.push(compile_bench(db, &compiler, session, bench)?.score);
+                    }
+                    let arrows = trend_arrows(&scores, COMPARE_MIN_DIFFERENCE);
+                    let mut table =
+                        AlignedTable::new_cloned(["SESSION", "WHEN", "SCORE", "CHANGE"], " | ");
+                    for (session, (score, arrow)) in zip(&sessions, zip(&scores, &arrows)) {
+                        table.add_row(vec![
+                            session.to_string(),
+                            format_datetime(session.start_time()),
+                            fmt_duration(score, precision),
+                            arrow.to_string(),
+                        ]);
+                    }
+                    writeln!(out, "{}", table)?;
+                    let rising = arrows
+                        .iter()
+                        .rev()
+                        .take_while(|&&arrow| arrow == "↑")
+                        .count();
+                    if rising >= TREND_ALARM_RISES {
+                        writeln!(
+                            out,
+                            "\u{26a0} score got worse in each of the last {} sessions",
+                            rising
+                        )?;
+                    }
+                }
+            }
+
+            Command::Records => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let mut records: Vec<(String, (BenchSessionId, Duration))> =
+                    db.best_scores(&compiler)?.into_iter().collect();
+                records.sort_by_key(|&(_, (_, score))| score);
+                let mut table =
+                    AlignedTable::new_cloned(["BENCH", "BEST SCORE", "SESSION", "WHEN"], " | ");
+                for (bench, (session, score)) in records {
+                    table.add_row(vec![
+                        bench,
+                        fmt_duration(&Some(score), precision),
+                        session.to_string(),
+                        format_datetime(session.start_time()),
+                    ]);
+                }
+                writeln!(out, "{}", table)?;
+            }
+
+            Command::Summarize { session } => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                )
+                .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED);
+                let benches = db.get_bench_names(session)?;
+                if benches.is_empty() {
+                    eprintln!(
+                        "{}",
+                        missing_session_message(db, session, "Session has no results")?
+                    );
+                } else {
+                    for bench in &benches {
+                        let compiled = compiler.compile(db.get_results(session, bench)?);
+                        db.set_summary(session, bench, &compiler.describe(), &compiled)?;
+                    }
+                    writeln!(out, "Summarized {} benches", benches.len())?;
+                }
+            }
+
+            Command::Dump {
+                out: ref archive_path,
+            } => {
+                let num_records = match archive_path {
+                    Some(path) => {
+                        let mut file = BufWriter::new(File::create(path)?);
+                        let num_records = dump_archive(db, &mut file)?;
+                        file.flush()?;
+                        num_records
+                    }
+                    None => dump_archive(db, out)?,
+                };
+                eprintln!("Dumped {} records", num_records);
+            }
+
+            Command::Load { ref archive } => {
+                let num_records = load_archive(db, BufReader::new(File::open(archive)?))?;
+                writeln!(out, "Loaded {} records", num_records)?;
+            }
+
+            Command::Vacuum => {
+                let size_before = db.size_bytes()?;
+                db.vacuum()?;
+                writeln!(
+                    out,
+                    "Database size: {} bytes before, {} bytes after",
+                    size_before,
+                    db.size_bytes()?
+                )?;
+            }
+
+            Command::VerifyInputs {
+                session,
+                ref rules,
+                ref dict,
+                ref benches,
+            } => {
+                if !db.session_exists(session)? {
+                    eprintln!("Session not found");
+                    return Ok(ExitCode::FAILURE);
+                }
+                let mut table = AlignedTable::new_cloned(["INPUT", "FILE", "STATUS"], " | ");
+                let mut any_differ = false;
+                let mut add_check = |input: &str, file: String, check: InputCheck| {
+                    any_differ |= check == InputCheck::Differs;
+                    table.add_row(vec![input.to_string(), file, check.label().to_string()]);
+                };
+                for (input, path, info_key) in [
+                    ("rules", rules, "search_rules_hash"),
+                    ("dict", dict, "search_dict_hash"),
+                ] {
+                    if let Some(path) = path {
+                        let check = check_input_hash(
+                            &db.get_info(session, info_key)?,
+                            &file_sha256_hex(path)?,
+                        );
+                        add_check(input, path.display().to_string(), check);
+                    }
+                }
+                if !benches.is_empty() {
+                    let hashes = benches
+                        .iter()
+                        .map(|path| file_sha256_hex(path))
+                        .collect::<io::Result<Vec<String>>>()?;
+                    let paths: Vec<String> = benches
+                        .iter()
+                        .map(|path| path.display().to_string())
+                        .collect();
+                    let check =
+                        check_bench_hashes(&db.get_info(session, "bench_files_hash")?, &hashes);
+                    add_check("benches", paths.join(", "), check);
+                }
+                if table.is_empty() {
+                    eprintln!("No files to verify; give --rules, --dict or --bench");
+                    return Ok(ExitCode::FAILURE);
+                }
+                writeln!(out, "{}", table)?;
+                if any_differ {
+                    if !db.get_info(session, "dict_filter")?.is_empty()
+                        || !db.get_info(session, "dict_subset")?.is_empty()
+                    {
+                        eprintln!("The session filtered its dictionary, so its hash is of the words it kept");
+                    }
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+
+            Command::FormatBenchfile { ref path } => {
+                if format_benchfile_in_place(path)? {
+                    writeln!(out, "Formatted {}", path.display())?;
+                } else {
+                    writeln!(out, "{} is already formatted", path.display())?;
+                }
+            }
+
+            Command::CompareBaseline {
+                session,
+                ref baseline,
+                min_difference,
+                fail_on_regression,
+            } => {
+                let baseline: BTreeMap<String, Option<f64>> =
+                    serde_json::from_str(&std::fs::read_to_string(baseline)?)?;
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let mut current = BTreeMap::new();
+                for bench in baseline.keys() {
+                    let results = db.get_results(session, bench)?;
+                    if !results.is_empty() {
+                        let score = compiler.compile(results).score;
+                        current.insert(bench.clone(), score.map(|s| s.as_secs_f64()));
+                    }
+                }
+                let missing = baseline.len() - current.len();
+                if missing > 0 {
+                    eprintln!("{} baseline benches not found in the session", missing);
+                }
+                let changes = compare_to_baseline(&baseline, &current, min_difference);
+                let mut table =
+                    AlignedTable::new_cloned(["BENCH", "BASELINE", "CURRENT", "CHANGE"], " | ");
+                let mut num_regressions = 0;
+                for change in changes {
+                    if change.is_regression {
+                        num_regressions += 1;
+                    }
+                    let secs = |score: Option<f64>| {
+                        fmt_duration(&score.map(Duration::from_secs_f64), precision)
+                    };
+                    table.add_row(vec![
+                        change.bench,
+                        secs(change.baseline),
+                        secs(change.current),
+                        if change.is_regression {
+                            "worse"
+                        } else {
+                            "better"
+                        }
+                        .to_string(),
+                    ]);
+                }
+                if table.is_empty() {
+                    writeln!(out, "No differences from the baseline")?;
+                } else {
+                    writeln!(out, "{}", table)?;
+                }
+                if fail_on_regression && num_regressions > 0 {
+                    return Ok(ExitCode::FAILURE);
+                }
+            }
+
+            Command::Compare {
+                session_a,
+                session_b,
+                fail_on_regression,
+                regression_threshold,
+                alpha,
+                allow_mismatch,
+            } => {
+                if !allow_mismatch {
+                    let keys = COMPARE_MATCHING_INFO.iter().map(|&(key, _)| key);
+                    let mismatched = mismatched_info(db, session_a, session_b, keys)?;
+                    for &(key, what) in COMPARE_MATCHING_INFO {
+                        if mismatched.contains(&key) {
+                            eprintln!(
+                                "\u{26a0} sessions used different {}; comparison may be invalid",
+                                what
+                            );
+                        }
+                    }
+                }
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                let mut compiled_b = compile_session(db, &compiler, session_b)?;
+                let benches: Vec<(String, CompiledBenchResult, CompiledBenchResult)> =
+                    compile_session(db, &compiler, session_a)?
+                        .into_iter()
+                        .filter_map(|(bench, result_a)| {
+                            let result_b = compiled_b.remove(&bench)?;
+                            Some((bench, result_a, result_b))
+                        })
+                        .collect();
+                if benches.is_empty() {
+                    let mut both_exist = true;
+                    for session in [session_a, session_b] {
+                        if !db.session_exists(session)? {
+                            eprintln!("Session {} not found", session);
+                            both_exist = false;
+                        }
+                    }
+                    if both_exist {
+                        eprintln!("No benches in common");
+                    }
+                } else {
+                    let mut tables: [AlignedTable; 2] = [(); 2].map(|_| {
+                        AlignedTable::new_cloned(
+                            [
+                                "BENCH",
+                                "A: SCORE",
+                                "B: SCORE",
+                                "A: INDEX",
+                                "A: TIME (sec)",
+                                "B: INDEX",
+                                "B: TIME (sec)",
+                            ],
+                            " | ",
+                        )
+                    });
+                    let mut tally = CompareTally::default();
+                    for (bench, result_a, result_b) in benches {
+                        tally.add(&result_a, &result_b);
+                        let table = match significant_winner(&result_a, &result_b, alpha) {
+                            Verdict::ABetter => Some(&mut tables[0]),
+                            Verdict::BBetter => Some(&mut tables[1]),
+                            Verdict::Same => None,
+                        };
+                        if let Some(table) = table {
+                            table.add_row(vec![
+                                bench,
+                                fmt_duration(&result_a.score, precision),
+                                fmt_duration(&result_b.score, precision),
+                                fmt_range(&result_a.found_index),
+                                fmt_duration_range(&result_a.elapsed, precision),
+                                fmt_range(&result_b.found_index),
+                                fmt_duration_range(&result_b.elapsed, precision),
+                            ]);
+                        }
+                    }
+                    if tally.wins_a > 0 {
+                        writeln!(out, "A found {} that B didn't", tally.wins_a)?;
+                    }
+                    if tally.wins_b > 0 {
+                        writeln!(out, "B found {} that A didn't", tally.wins_b)?;
+                    }
+                    let total_difference = tally.total_difference;
+                    write!(out, "Total minor score differences: ")?;
+                    if total_difference > 0.0 {
+                        writeln!(
+                            out,
+                            "B better by {} sec",
+                            fmt_duration(
+                                &Some(Duration::from_secs_f64(total_difference)),
+                                precision
+                            )
+                        )?;
+                    } else if total_difference < 0.0 {
+                        writeln!(
+                            out,
+                            "A better by {} sec",
+                            fmt_duration(
+                                &Some(Duration::from_secs_f64(-total_difference)),
+                                precision
+                            )
+                        )?;
+                    } else {
+                        writeln!(out, "none")?;
+                    }
+                    for (name, table) in zip(["A", "B"], tables) {
+                        if !table.is_empty() {
+                            writeln!(out, "\nBetter in {}:\n{}", name, table)?;
+                        }
+                    }
+                    if fail_on_regression && tally.verdict(regression_threshold) == Verdict::ABetter
+                    {
+                        return Ok(ExitCode::FAILURE);
+                    }
+                }
+            }
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AlignedTable {
+    rows: Vec<Vec<String>>,
+    footer: Option<Vec<String>>,
+    separator: String,
+    widths: Vec<usize>,
+}
+
+impl AlignedTable {
+    fn new(header: Vec<String>, separator: String) -> AlignedTable {
+        assert!(!header.is_empty());
+        let widths = header.iter().map(String::len).collect();
+        AlignedTable {
+            rows: vec![header],
+            footer: None,
+            separator,
+            widths,
+        }
+    }
+
+    fn new_cloned(
+        header: impl IntoIterator<Item = impl AsRef<str>>,
+        separator: impl AsRef<str>,
+    ) -> AlignedTable {
+        Self::new(
+            header.into_iter().map(|s| s.as_ref().to_string()).collect(),
+            separator.as_ref().to_string(),
+        )
+    }
+
+    fn get_header(&self) -> &[String] {
+        &self.rows[0]
+    }
+
+    fn get_num_cols(&self) -> usize {
+        self.get_header().len()
+    }
+
+    fn add_row(&mut self, row: Vec<String>) {
+        self.update_widths(&row);
+        self.rows.push(row);
+    }
+
+    /// Sets a row to show below the others, after a dashed line, such as for totals.
+    fn set_footer(&mut self, footer: Vec<String>) {
+        self.update_widths(&footer);
+        self.footer = Some(footer);
+    }
+
+    fn update_widths(&mut self, row: &[String]) {
+        assert!(row.len() == self.get_num_cols());
+        for (i, len) in row.iter().map(String::len).enumerate() {
+            if len > self.widths[i] {
+                self.widths[i] = len;
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rows.len() <= 1
+    }
+}
+
+impl Display for AlignedTable {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fmt_row = |f: &mut std::fmt::Formatter<'_>, row: &[String]| {
+            write!(f, "{:1$}", row[0], self.widths[0])?;
+            for (value, width) in zip(row, &self.widths).skip(1) {
+                write!(f, "{}{:2$}", self.separator, value, width)?;
+            }
+            Ok(()) as std::fmt::Result
+        };
+
+        fmt_row(f, &self.rows[0])?;
+        for row in &self.rows[1..] {
+            writeln!(f)?;
+            fmt_row(f, row)?;
+        }
+        if let Some(footer) = &self.footer {
+            let line_width =
+                self.widths.iter().sum::<usize>() + self.separator.len() * (self.widths.len() - 1);
+            writeln!(f)?;
+            write!(f, "{}", "-".repeat(line_width))?;
+            writeln!(f)?;
+            fmt_row(f, footer)?;
+        }
+        Ok(())
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let flags = if cli.command.is_writable() {
+        OpenFlags::SQLITE_OPEN_READ_WRITE
+    } else {
+        OpenFlags::SQLITE_OPEN_READ_ONLY
+    };
+    let conn = if cli.command.uses_db() {
+        Connection::open_with_flags(&cli.db, flags)
+    } else {
+        Connection::open_in_memory()
+    };
+    let mut db = match BenchDb::new(conn.expect("Error opening db file")) {
+        Ok(db) => db,
+        Err(err) => {
+            eprintln!("Error opening {}: {}", cli.db.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut out = open_output(cli.output.as_deref()).expect("Error opening output file");
+    for session in quick_mode_sessions(&mut db, cli.command.shown_sessions())
+        .expect("Error reading session info")
+    {
+        eprintln!(
+            "\u{26a0} session {} was run with --quick; its timings are unreliable",
+            session
+        );
+    }
+    let exit_code = cli
+        .command
+        .run(&mut db, &mut out, cli.precision)
+        .expect("Error running command");
+    out.flush().expect("Error writing output");
+    exit_code
+}
@@ -0,0 +1,1328 @@
+// Copyright © 2022 Daniel Getz
+// SPDX-License-Identifier: MIT
+
+use super::*;
+use buscaluso_bench::sha256_hex;
+
+#[test]
+fn test_found_index_histogram() {
+    assert_eq!(
+        found_index_histogram([
+            Some(0),
+            Some(0),
+            Some(1),
+            Some(2),
+            Some(3),
+            Some(8),
+            Some(9),
+            Some(32),
+            Some(33),
+            Some(1000),
+            None,
+        ]),
+        vec![
+            ("0", 2),
+            ("1-2", 2),
+            ("3-8", 2),
+            ("9-32", 2),
+            ("33+", 2),
+            ("not found", 1),
+        ]
+    );
+}
+
+#[test]
+fn test_found_index_histogram_empty() {
+    assert!(found_index_histogram([])
+        .iter()
+        .all(|&(_, count)| count == 0));
+}
+
+fn compiled_with_score(score: Option<f64>) -> CompiledBenchResult {
+    CompiledBenchResult {
+        score: score.map(Duration::from_secs_f64),
+        errors: Vec::new(),
+        found_index: score.map(|_| 0..=0),
+        elapsed: score.map(|s| Duration::from_secs_f64(s)..=Duration::from_secs_f64(s)),
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    }
+}
+
+#[test]
+fn test_bench_name_with_style() {
+    assert_eq!(
+        bench_name_with_style("a = b | c", NameStyle::Default),
+        "a = b | c"
+    );
+    assert_eq!(bench_name_with_style("a = b | c", NameStyle::Path), "a/b+c");
+    assert_eq!(bench_name_with_style("old", NameStyle::Path), "old");
+}
+
+#[test]
+fn test_criterion_estimates() -> serde_json::Result<()> {
+    let compiled = CompiledBenchResult {
+        kept_scores: [1.0, 2.0, 4.0, 5.0]
+            .into_iter()
+            .map(Duration::from_secs_f64)
+            .collect(),
+        ..compiled_with_score(Some(3.0))
+    };
+    let estimates = criterion_estimates(&compiled).unwrap();
+    assert_eq!(estimates.mean.point_estimate, 3e9);
+    assert_eq!(estimates.median.point_estimate, 3e9);
+    assert!((estimates.std_dev.point_estimate - 10f64.sqrt() / 3f64.sqrt() * 1e9).abs() < 1.0);
+
+    let json = serde_json::to_value(&estimates)?;
+    for key in ["mean", "median", "std_dev"] {
+        let estimate = json[key].as_object().unwrap();
+        assert!(estimate["point_estimate"].is_f64(), "{}", key);
+        assert!(estimate["standard_error"].is_f64(), "{}", key);
+        let interval = estimate["confidence_interval"].as_object().unwrap();
+        for interval_key in ["confidence_level", "lower_bound", "upper_bound"] {
+            assert!(interval[interval_key].is_f64(), "{}.{}", key, interval_key);
+        }
+    }
+
+    assert_eq!(criterion_estimates(&compiled_with_score(None)), None);
+    let without_samples = criterion_estimates(&compiled_with_score(Some(0.5))).unwrap();
+    assert_eq!(without_samples.median.point_estimate, 0.5e9);
+    assert_eq!(without_samples.std_dev.point_estimate, 0.0);
+    Ok(())
+}
+
+fn tally_of(pairs: &[(Option<f64>, Option<f64>)]) -> CompareTally {
+    let mut tally = CompareTally::default();
+    for &(a, b) in pairs {
+        tally.add(&compiled_with_score(a), &compiled_with_score(b));
+    }
+    tally
+}
+
+#[test]
+fn test_compare_verdict_by_score() {
+    let tally = tally_of(&[(Some(1.0), Some(2.0)), (Some(1.0), Some(1.5))]);
+    assert_eq!(tally.verdict(0.1), Verdict::ABetter);
+    assert_eq!(tally.verdict(2.0), Verdict::Same);
+    let tally = tally_of(&[(Some(2.0), Some(1.0)), (Some(1.0), Some(1.25))]);
+    assert_eq!(tally.verdict(0.1), Verdict::BBetter);
+    assert_eq!(tally.verdict(1.0), Verdict::Same);
+}
+
+#[test]
+fn test_compare_verdict_wins_trump_score() {
+    let tally = tally_of(&[(None, Some(9.0)), (Some(1.0), Some(5.0))]);
+    assert_eq!(tally.wins_b, 1);
+    assert_eq!(tally.verdict(0.1), Verdict::BBetter);
+    let tally = tally_of(&[(Some(9.0), None), (None, Some(1.0)), (Some(5.0), Some(1.0))]);
+    assert_eq!(tally.verdict(0.1), Verdict::BBetter);
+    let tally = tally_of(&[(Some(9.0), None), (None, None)]);
+    assert_eq!(tally.verdict(0.1), Verdict::ABetter);
+}
+
+#[test]
+fn test_parse_date() {
+    assert_eq!(
+        parse_date("2024-01-01"),
+        Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200))
+    );
+    assert_eq!(
+        BenchSessionId::from_timestamp(parse_date("1970-01-02").unwrap()).to_string(),
+        "86400"
+    );
+    assert!(parse_date("2024-13-01").is_err());
+    assert!(parse_date("yesterday").is_err());
+}
+
+#[test]
+fn test_mismatched_info() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid_a = db.new_session_id()?;
+    let sid_b = BenchSessionId::from_timestamp(sid_a.start_time() + Duration::from_secs(1));
+    db.set_info(sid_a, "search_rules_hash", "aaa")?;
+    db.set_info(sid_b, "search_rules_hash", "aaa")?;
+    db.set_info(sid_a, "search_dict_hash", "bbb")?;
+    db.set_info(sid_b, "search_dict_hash", "ccc")?;
+    db.set_info(sid_a, "only_a", "x")?;
+    assert_eq!(
+        mismatched_info(
+            &mut db,
+            sid_a,
+            sid_b,
+            ["search_rules_hash", "search_dict_hash", "only_a", "neither"]
+        )?,
+        vec!["search_dict_hash", "only_a"]
+    );
+    assert!(mismatched_info(&mut db, sid_a, sid_a, ["search_dict_hash"])?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_mismatched_info_effective_timeout() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid_a = db.new_session_id()?;
+    let sid_b = BenchSessionId::from_timestamp(sid_a.start_time() + Duration::from_secs(1));
+    let sid_c = BenchSessionId::from_timestamp(sid_a.start_time() + Duration::from_secs(2));
+    let keys = [EFFECTIVE_TIMEOUT_INFO];
+    // Without the key or a config, there's nothing to compare
+    db.set_info(sid_a, EFFECTIVE_TIMEOUT_INFO, "5")?;
+    assert!(mismatched_info(&mut db, sid_a, sid_b, keys)?.is_empty());
+
+    // An older session's timeout comes from its config
+    let config = "repeat = 1\nrepeat_failed = 1\ntimeout = 5\n";
+    db.set_info(sid_b, "bench_config", config)?;
+    assert!(mismatched_info(&mut db, sid_a, sid_b, keys)?.is_empty());
+    db.set_info(
+        sid_c,
+        "bench_config",
+        "repeat = 1\nrepeat_failed = 1\ntimeout = 8\n",
+    )?;
+    assert_eq!(mismatched_info(&mut db, sid_a, sid_c, keys)?, keys);
+    Ok(())
+}
+
+#[test]
+fn test_quick_mode_sessions() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid_a = db.new_session_id()?;
+    let sid_b = BenchSessionId::from_timestamp(sid_a.start_time() + Duration::from_secs(1));
+    db.set_info(sid_a, "quick_mode", "true")?;
+    db.set_info(sid_b, "machine", "m1")?;
+    assert_eq!(
+        quick_mode_sessions(&mut db, vec![sid_a, sid_b])?,
+        vec![sid_a]
+    );
+    let compare = Command::Compare {
+        session_a: sid_b,
+        session_b: sid_a,
+        fail_on_regression: false,
+        regression_threshold: 0.0,
+        alpha: COMPARE_ALPHA,
+        allow_mismatch: false,
+    };
+    assert_eq!(
+        quick_mode_sessions(&mut db, compare.shown_sessions())?,
+        vec![sid_a]
+    );
+    assert!(Command::Records.shown_sessions().is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_info_diff() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid_a = db.new_session_id()?;
+    let sid_b = BenchSessionId::from_timestamp(sid_a.start_time() + Duration::from_secs(1));
+    db.set_info(sid_a, "machine", "m1")?;
+    db.set_info(sid_b, "machine", "m1")?;
+    db.set_info(sid_a, "search_dict_hash", "aaa")?;
+    db.set_info(sid_b, "search_dict_hash", "bbb")?;
+    db.set_info(sid_a, "only_a", "x")?;
+    db.set_info(sid_b, "only_b", "y")?;
+    assert_eq!(
+        info_diff(&mut db, sid_a, sid_b)?,
+        vec![
+            ("only_a".to_string(), "x".to_string(), "".to_string()),
+            ("only_b".to_string(), "".to_string(), "y".to_string()),
+            (
+                "search_dict_hash".to_string(),
+                "aaa".to_string(),
+                "bbb".to_string()
+            ),
+        ]
+    );
+    assert!(info_diff(&mut db, sid_a, sid_a)?.is_empty());
+    Ok(())
+}
+
+fn linear_points(intercept: f64, slope: f64, indices: &[usize]) -> Vec<(usize, Duration)> {
+    indices
+        .iter()
+        .map(|&i| (i, Duration::from_secs_f64(intercept + slope * i as f64)))
+        .collect()
+}
+
+#[test]
+fn test_estimate_index_equivalent_linear() {
+    let estimate =
+        estimate_index_equivalent(&linear_points(0.5, 0.125, &[0, 0, 1, 3, 8, 20])).unwrap();
+    assert!((estimate - 0.125).abs() < 1e-6, "{}", estimate);
+    let estimate = estimate_index_equivalent(&linear_points(0.0, 2.0, &[5, 1])).unwrap();
+    assert!((estimate - 2.0).abs() < 1e-6, "{}", estimate);
+}
+
+#[test]
+fn test_estimate_index_equivalent_noisy() {
+    let mut points = linear_points(1.0, 0.25, &[0, 2, 4, 6, 8]);
+    for (i, (_, elapsed)) in points.iter_mut().enumerate() {
+        let noise = Duration::from_millis(10);
+        *elapsed = if i % 2 == 0 {
+            *elapsed + noise
+        } else {
+            *elapsed - noise
+        };
+    }
+    let estimate = estimate_index_equivalent(&points).unwrap();
+    assert!((estimate - 0.25).abs() < 0.01, "{}", estimate);
+}
+
+#[test]
+fn test_estimate_index_equivalent_none() {
+    assert_eq!(estimate_index_equivalent(&[]), None);
+    assert_eq!(
+        estimate_index_equivalent(&linear_points(1.0, 0.0, &[3])),
+        None
+    );
+    assert_eq!(
+        estimate_index_equivalent(&linear_points(1.0, 0.5, &[3, 3, 3])),
+        None
+    );
+    assert_eq!(
+        estimate_index_equivalent(&linear_points(10.0, -0.5, &[0, 1, 2])),
+        None
+    );
+}
+
+#[test]
+fn test_open_output_file_matches() -> Result<(), Box<dyn Error>> {
+    let mut table = AlignedTable::new_cloned(["KEY", "VALUE"], " | ");
+    table.add_row(vec!["ação".to_string(), "1.5".to_string()]);
+    table.add_row(vec!["x".to_string(), "<...>".to_string()]);
+
+    let mut in_memory: Vec<u8> = Vec::new();
+    writeln!(in_memory, "{}", table)?;
+
+    let path = std::env::temp_dir().join(format!("benchdb-test-output-{}.txt", std::process::id()));
+    {
+        let mut out = open_output(Some(&path))?;
+        writeln!(out, "{}", table)?;
+        out.flush()?;
+    }
+    let from_file = std::fs::read(&path)?;
+    std::fs::remove_file(&path)?;
+    assert_eq!(from_file, in_memory);
+    Ok(())
+}
+
+#[test]
+fn test_prometheus_lines() {
+    let session: BenchSessionId = "1700000000".parse().unwrap();
+    let found = CompiledBenchResult {
+        score: Some(Duration::from_secs_f64(0.125)),
+        errors: vec![],
+        found_index: Some(3..=5),
+        elapsed: Some(Duration::from_secs_f64(0.1)..=Duration::from_secs_f64(0.2)),
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    };
+    assert_eq!(
+        prometheus_lines(
+            session,
+            &BTreeMap::from([("a\"b\\c = d".to_string(), found.clone())])
+        ),
+        vec![
+            "# TYPE buscaluso_bench_score_seconds gauge",
+            r#"buscaluso_bench_score_seconds{bench="a\"b\\c = d",session="1700000000"} 0.125"#,
+            "# TYPE buscaluso_bench_found_index gauge",
+            r#"buscaluso_bench_found_index{bench="a\"b\\c = d",session="1700000000"} 3"#,
+            "# TYPE buscaluso_bench_found_fraction gauge",
+            r#"buscaluso_bench_found_fraction{session="1700000000"} 1"#,
+        ]
+    );
+
+    let not_found = CompiledBenchResult {
+        score: None,
+        errors: vec![],
+        found_index: None,
+        elapsed: None,
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    };
+    let lines = prometheus_lines(
+        session,
+        &BTreeMap::from([
+            ("x = y".to_string(), found),
+            ("z = w".to_string(), not_found),
+        ]),
+    );
+    assert!(!lines.iter().any(|line| line.contains("z = w")));
+    assert_eq!(
+        lines.last().unwrap(),
+        r#"buscaluso_bench_found_fraction{session="1700000000"} 0.5"#
+    );
+}
+
+#[test]
+fn test_html_report() {
+    let session: BenchSessionId = "1700000000".parse().unwrap();
+    let found = CompiledBenchResult {
+        score: Some(Duration::from_secs_f64(0.5)),
+        errors: vec![],
+        found_index: Some(2..=4),
+        elapsed: Some(Duration::from_secs_f64(0.25)..=Duration::from_secs_f64(0.75)),
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    };
+    let not_found = CompiledBenchResult {
+        score: None,
+        errors: vec![],
+        found_index: None,
+        elapsed: None,
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    };
+    let info = BTreeMap::from([
+        ("machine".to_string(), "<laptop> & co".to_string()),
+        ("search_rules".to_string(), "a = b\nc = d\n".to_string()),
+    ]);
+    let html = html_report(
+        session,
+        &info,
+        &BTreeMap::from([
+            ("x<y> & z = w".to_string(), found.clone()),
+            ("um = dois".to_string(), not_found),
+        ]),
+        4,
+    );
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<h1>Session 1700000000</h1>"));
+    assert!(html.contains("<p>Found 1 / 2 (50.0%)</p>"));
+    assert!(html.contains(&format!(
+        "<tr><td>x&lt;y&gt; &amp; z = w</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+        fmt_duration(&found.score, 4),
+        fmt_range(&found.found_index),
+        fmt_duration_range(&found.elapsed, 4),
+    )));
+    assert!(html.contains("<tr class=\"not-found\"><td>um = dois</td>"));
+    assert!(html.contains("<th>machine</th><td><pre>&lt;laptop&gt; &amp; co</pre></td>"));
+    assert!(!html.contains("x<y>"));
+    assert!(!html.contains("<laptop>"));
+}
+
+#[test]
+fn test_compare_to_baseline() -> Result<(), serde_json::Error> {
+    let baseline: BTreeMap<String, Option<f64>> = serde_json::from_str(
+        r#"{
+            "slower = x": 1.0,
+            "faster = y": 2.0,
+            "same = z": 0.5,
+            "lost = w": 0.25,
+            "gained = v": null,
+            "missing = u": 1.0
+        }"#,
+    )?;
+    let current = BTreeMap::from([
+        ("slower = x".to_string(), Some(1.5)),
+        ("faster = y".to_string(), Some(1.0)),
+        ("same = z".to_string(), Some(0.51)),
+        ("lost = w".to_string(), None),
+        ("gained = v".to_string(), Some(3.0)),
+    ]);
+    let change = |bench: &str, baseline, current, is_regression| BaselineChange {
+        bench: bench.to_string(),
+        baseline,
+        current,
+        is_regression,
+    };
+    assert_eq!(
+        compare_to_baseline(&baseline, &current, 1.0 / 32.0),
+        vec![
+            change("faster = y", Some(2.0), Some(1.0), false),
+            change("gained = v", None, Some(3.0), false),
+            change("lost = w", Some(0.25), None, true),
+            change("slower = x", Some(1.0), Some(1.5), true),
+        ]
+    );
+    assert!(compare_to_baseline(&baseline, &baseline, 1.0 / 32.0).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_export_baseline_then_compare() -> Result<(), Box<dyn Error>> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    let key = |name| BenchKey::from_name(name).unwrap();
+    db.add_result(
+        session,
+        &key("a = b"),
+        BenchResult::success(0, Duration::from_millis(500)),
+    )?;
+    db.add_result(
+        session,
+        &key("c = d"),
+        BenchResult::not_found(Duration::from_secs(1)),
+    )?;
+
+    let mut exported = Vec::new();
+    let export = Command::Export {
+        session,
+        format: ExportFormat::Baseline,
+        name_style: NameStyle::Default,
+    };
+    assert_eq!(export.run(&mut db, &mut exported, 4)?, ExitCode::SUCCESS);
+    let baseline: BTreeMap<String, Option<f64>> = serde_json::from_slice(&exported)?;
+    assert_eq!(baseline.keys().collect::<Vec<_>>(), vec!["a = b", "c = d"]);
+    assert_eq!(baseline["c = d"], None);
+
+    let baseline_path =
+        std::env::temp_dir().join(format!("benchdb-test-baseline-{}.json", std::process::id()));
+    std::fs::write(&baseline_path, &exported)?;
+    let compare = Command::CompareBaseline {
+        session,
+        baseline: baseline_path.clone(),
+        min_difference: COMPARE_MIN_DIFFERENCE,
+        fail_on_regression: true,
+    };
+    let exit_code = compare.run(&mut db, &mut Vec::new(), 4);
+    std::fs::remove_file(&baseline_path)?;
+    assert_eq!(exit_code?, ExitCode::SUCCESS);
+    Ok(())
+}
+
+#[test]
+fn test_show_order() {
+    let info = BTreeMap::from([
+        ("bench_config".to_string(), "repeat = 3".to_string()),
+        (
+            "cli_args".to_string(),
+            r#"["buscaluso-bench","-c","x.toml"]"#.to_string(),
+        ),
+        ("dict_word_count".to_string(), "2".to_string()),
+        ("machine".to_string(), "laptop".to_string()),
+    ]);
+    let keys: Vec<String> = show_order(info).into_iter().map(|(key, _)| key).collect();
+    assert_eq!(
+        keys,
+        vec!["machine", "cli_args", "bench_config", "dict_word_count"]
+    );
+}
+
+fn run_to_string(command: Command, db: &mut BenchDb) -> String {
+    let mut out = Vec::new();
+    command.run(db, &mut out, 4).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn test_json_format() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    db.set_info(session, "machine", "laptop")?;
+    db.set_info(session, "search_rules", "a = b\nc = d\n")?;
+    db.conn.execute(
+        "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', 0.5, 0)",
+        (session,),
+    )?;
+
+    let info: serde_json::Value = serde_json::from_str(&run_to_string(
+        Command::Show {
+            session,
+            format: OutputFormat::Json,
+        },
+        &mut db,
+    ))
+    .unwrap();
+    assert_eq!(
+        info,
+        serde_json::json!({"machine": "laptop", "search_rules": "a = b\nc = d\n"})
+    );
+
+    let value: String = serde_json::from_str(&run_to_string(
+        Command::Get {
+            session,
+            info_key: "search_rules".into(),
+            format: OutputFormat::Json,
+        },
+        &mut db,
+    ))
+    .unwrap();
+    assert_eq!(value, "a = b\nc = d\n");
+
+    let sessions: serde_json::Value = serde_json::from_str(&run_to_string(
+        Command::ListSessions {
+            since: None,
+            format: OutputFormat::Json,
+            columns: None,
+        },
+        &mut db,
+    ))
+    .unwrap();
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(
+        sessions[0]["session_id"],
+        session.to_string().parse::<u64>().unwrap()
+    );
+    assert_eq!(sessions[0]["when"], format_datetime(session.start_time()));
+    assert_eq!(sessions[0]["num_benches"], 1);
+    assert_eq!(sessions[0]["machine"], "laptop");
+    assert_eq!(sessions[0]["tag"], "");
+
+    let sessions: serde_json::Value = serde_json::from_str(&run_to_string(
+        Command::ListSessions {
+            since: None,
+            format: OutputFormat::Json,
+            columns: Some(vec!["machine".into(), "nonsense".into()]),
+        },
+        &mut db,
+    ))
+    .unwrap();
+    assert_eq!(
+        sessions[0].as_object().unwrap().keys().collect::<Vec<_>>(),
+        [
+            "machine",
+            "nonsense",
+            "num_benches",
+            "session_id",
+            "status",
+            "when"
+        ]
+    );
+    assert_eq!(sessions[0]["nonsense"], "");
+    Ok(())
+}
+
+#[test]
+fn test_list_sessions_status() -> rusqlite::Result<()> {
+    assert_eq!(list_sessions_status(SESSION_COMPLETE), "complete");
+    assert_eq!(list_sessions_status(""), "");
+    assert_eq!(list_sessions_status("running"), "\u{26a0} incomplete");
+
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let crashed = "1000".parse::<BenchSessionId>().unwrap();
+    let finished = "2000".parse::<BenchSessionId>().unwrap();
+    // A run that crashed never got to store its results, so it only has info
+    db.set_info(crashed, SESSION_STATUS_INFO, "running")?;
+    db.conn.execute(
+        "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', 0.5, 0)",
+        (finished,),
+    )?;
+    db.set_info(finished, SESSION_STATUS_INFO, SESSION_COMPLETE)?;
+    let output = run_to_string(
+        Command::ListSessions {
+            since: None,
+            format: OutputFormat::Table,
+            columns: Some(vec![]),
+        },
+        &mut db,
+    );
+    let status = |session: BenchSessionId| {
+        let row = output
+            .lines()
+            .find(|line| line.starts_with(&session.to_string()))
+            .unwrap();
+        row.split('|').nth(3).unwrap().trim().to_string()
+    };
+    assert_eq!(status(crashed), "\u{26a0} incomplete", "{}", output);
+    let num_benches = |session: BenchSessionId| {
+        let row = output
+            .lines()
+            .find(|line| line.starts_with(&session.to_string()))
+            .unwrap();
+        row.split('|').nth(2).unwrap().trim().to_string()
+    };
+    assert_eq!(num_benches(crashed), "0", "{}", output);
+    assert_eq!(status(finished), "complete", "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_list_sessions_columns() {
+    let known = BTreeSet::from(["machine".to_string(), "tag".to_string()]);
+    let (columns, unknown) = list_sessions_columns(None, &known);
+    assert_eq!(columns, LIST_SESSIONS_EXTRA_COLUMNS);
+    assert!(unknown.is_empty());
+
+    let requested = ["tag", "nonsense", "machine", "also_nonsense"].map(String::from);
+    let (columns, unknown) = list_sessions_columns(Some(&requested), &known);
+    assert_eq!(columns, requested);
+    assert_eq!(unknown, ["nonsense", "also_nonsense"]);
+}
+
+#[test]
+fn test_fmt_relative_range() {
+    assert_eq!(fmt_relative_range(&Some(50..=50), 1000, 2), "5.00%");
+    assert_eq!(
+        fmt_relative_range(&Some(5..=20), 1_000_000, 4),
+        "0.0005% .. 0.0020%"
+    );
+    assert_eq!(fmt_relative_range(&None, 1000, 2), "--");
+}
+
+#[test]
+fn test_results_relative() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    db.conn.execute(
+        "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', 0.5, 50)",
+        (session,),
+    )?;
+    let results = |db: &mut BenchDb| {
+        run_to_string(
+            Command::Results {
+                session,
+                relative: true,
+                cov_threshold: RESULTS_COV_THRESHOLD,
+            },
+            db,
+        )
+    };
+
+    // Without a word count, falls back to the absolute index
+    let absolute = results(&mut db);
+    assert!(absolute.lines().next().unwrap().contains("| INDEX |"));
+    assert!(absolute.contains("| 50 "));
+
+    db.set_info(session, "dict_word_count", "1000")?;
+    let relative = results(&mut db);
+    assert!(relative.contains("INDEX (% of dict)"));
+    assert!(relative.contains("| 5.0000% "), "{}", relative);
+    Ok(())
+}
+
+#[test]
+fn test_results_marks_noisy_times() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    for (bench, durations) in [
+        ("noisy = a", [0.1, 1.0, 0.2, 0.9]),
+        ("stable = b", [0.5, 0.51, 0.5, 0.51]),
+    ] {
+        for duration in durations {
+            db.conn.execute(
+                "insert into bench_run (session_id, bench, duration, found_at) values (?, ?, ?, 0)",
+                (session, bench, duration),
+            )?;
+        }
+    }
+    let results = |db: &mut BenchDb, cov_threshold| {
+        run_to_string(
+            Command::Results {
+                session,
+                relative: false,
+                cov_threshold,
+            },
+            db,
+        )
+    };
+
+    let output = results(&mut db, RESULTS_COV_THRESHOLD);
+    let row = |name| output.lines().find(|line| line.starts_with(name)).unwrap();
+    assert!(row("noisy = a").trim_end().ends_with('*'), "{}", output);
+    assert!(!row("stable = b").trim_end().ends_with('*'), "{}", output);
+    assert!(output.contains("* Times vary"), "{}", output);
+
+    let output = results(&mut db, 10.0);
+    assert!(!output.contains('*'), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_aligned_table_footer() {
+    let mut table = AlignedTable::new_cloned(["BENCH", "SCORE"], " | ");
+    table.add_row(vec!["a = b".to_string(), "0.5".to_string()]);
+    table.add_row(vec!["cc = dd".to_string(), "12.25".to_string()]);
+    let without_footer = table.to_string();
+    assert_eq!(
+        without_footer,
+        "BENCH   | SCORE\na = b   | 0.5  \ncc = dd | 12.25"
+    );
+
+    table.set_footer(vec!["AVERAGE".to_string(), "6.375".to_string()]);
+    assert_eq!(
+        table.to_string(),
+        "BENCH   | SCORE\n\
+         a = b   | 0.5  \n\
+         cc = dd | 12.25\n\
+         ---------------\n\
+         AVERAGE | 6.375"
+    );
+}
+
+#[test]
+fn test_stats_by_accent() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    for (bench, found_at) in [
+        ("ação = x", None),
+        ("acao = x", Some(0)),
+        ("pé = y", Some(0)),
+        ("pe = y", Some(2)),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at) values (?, ?, 0.5, ?)",
+            (session, bench, found_at),
+        )?;
+    }
+    let output = run_to_string(
+        Command::Stats {
+            session,
+            by_accent: true,
+            by_section: false,
+        },
+        &mut db,
+    );
+    let table: Vec<&str> = output
+        .lines()
+        .skip_while(|line| !line.starts_with("START WORD"))
+        .collect();
+    assert_eq!(table.len(), 3, "{}", output);
+    assert!(
+        table[1].starts_with("accented   | 1 / 2 (50.0%)  | 0.5000"),
+        "{}",
+        output
+    );
+    assert!(
+        table[2].starts_with("unaccented | 2 / 2 (100.0%) | 0.6250"),
+        "{}",
+        output
+    );
+
+    let output = run_to_string(
+        Command::Stats {
+            session,
+            by_accent: false,
+            by_section: false,
+        },
+        &mut db,
+    );
+    assert!(!output.contains("START WORD"), "{}", output);
+    Ok(())
+}
+
+#[test]
+fn test_stats_by_section() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    for (bench, found_at) in [
+        ("a = x", Some(0)),
+        ("b = x", None),
+        ("c = y", Some(0)),
+        ("d = y", Some(1)),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at) values (?, ?, 0.5, ?)",
+            (session, bench, found_at),
+        )?;
+    }
+    db.set_section(session, "a = x", "nouns")?;
+    db.set_section(session, "b = x", "nouns")?;
+    db.set_section(session, "c = y", "verbs")?;
+    let output = run_to_string(
+        Command::Stats {
+            session,
+            by_accent: false,
+            by_section: true,
+        },
+        &mut db,
+    );
+    let table: Vec<&str> = output
+        .lines()
+        .skip_while(|line| !line.starts_with("SECTION"))
+        .collect();
+    assert_eq!(table.len(), 4, "{}", output);
+    assert!(
+        table[1].starts_with("(none)  | 1 / 1 (100.0%) | 0.6250"),
+        "{}",
+        output
+    );
+    assert!(
+        table[2].starts_with("nouns   | 1 / 2 (50.0%)  | 0.5000"),
+        "{}",
+        output
+    );
+    assert!(
+        table[3].starts_with("verbs   | 1 / 1 (100.0%) | 0.5000"),
+        "{}",
+        output
+    );
+    Ok(())
+}
+
+#[test]
+fn test_never_found() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for (session, bench, found_at) in [
+        (1, "a = b", None),
+        (2, "a = b", Some(0)),
+        (1, "c = d", None),
+        (2, "c = d", None),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at) values (?, ?, 0.5, ?)",
+            (session, bench, found_at),
+        )?;
+    }
+    assert_eq!(
+        run_to_string(Command::NeverFound, &mut db),
+        "BENCH | SESSIONS\n\
+         c = d | 2       \n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_missing_session_message() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let info_only = db.new_session_id()?;
+    db.set_info(info_only, "machine", "laptop")?;
+    let populated: BenchSessionId = "1".parse().unwrap();
+    db.conn.execute(
+        "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', 0.5, 0)",
+        (populated,),
+    )?;
+    let missing: BenchSessionId = "2".parse().unwrap();
+    assert_eq!(
+        missing_session_message(&mut db, missing, "Session has no results")?,
+        "Session not found"
+    );
+    assert_eq!(
+        missing_session_message(&mut db, info_only, "Session has no results")?,
+        "Session has no results"
+    );
+    assert_eq!(
+        run_to_string(
+            Command::Results {
+                session: info_only,
+                relative: false,
+                cov_threshold: RESULTS_COV_THRESHOLD
+            },
+            &mut db
+        ),
+        ""
+    );
+    assert!(run_to_string(
+        Command::Results {
+            session: populated,
+            relative: false,
+            cov_threshold: RESULTS_COV_THRESHOLD
+        },
+        &mut db
+    )
+    .starts_with("BENCH"));
+    Ok(())
+}
+
+#[test]
+fn test_check_input_hash() {
+    assert_eq!(check_input_hash("abc", "abc"), InputCheck::Matches);
+    assert_eq!(check_input_hash("abc", "abd"), InputCheck::Differs);
+    assert_eq!(check_input_hash("", "abc"), InputCheck::NotRecorded);
+}
+
+#[test]
+fn test_check_bench_hashes() {
+    let hashes = vec!["aa".to_string(), "bb".to_string()];
+    assert_eq!(
+        check_bench_hashes(r#"["aa","bb"]"#, &hashes),
+        InputCheck::Matches
+    );
+    assert_eq!(
+        check_bench_hashes(r#"["bb","aa"]"#, &hashes),
+        InputCheck::Differs
+    );
+    assert_eq!(
+        check_bench_hashes(r#"["aa"]"#, &hashes),
+        InputCheck::Differs
+    );
+    assert_eq!(check_bench_hashes("", &hashes), InputCheck::NotRecorded);
+}
+
+#[test]
+fn test_verify_inputs() -> Result<(), Box<dyn Error>> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    let rules = "a = b\n";
+    db.set_info(session, "search_rules_hash", &sha256_hex(rules.as_bytes()))?;
+    db.set_info(session, "search_dict_hash", &sha256_hex(b"old\n"))?;
+    let rules_path =
+        std::env::temp_dir().join(format!("benchdb-test-rules-{}.txt", std::process::id()));
+    let dict_path =
+        std::env::temp_dir().join(format!("benchdb-test-dict-{}.txt", std::process::id()));
+    std::fs::write(&rules_path, rules)?;
+    std::fs::write(&dict_path, "new\n")?;
+    let verify = |rules: Option<&Path>, dict: Option<&Path>| Command::VerifyInputs {
+        session,
+        rules: rules.map(Path::to_path_buf),
+        dict: dict.map(Path::to_path_buf),
+        benches: vec![],
+    };
+    let mut out = Vec::new();
+    let matching = verify(Some(&rules_path), None).run(&mut db, &mut out, 4)?;
+    let mismatched = verify(Some(&rules_path), Some(&dict_path)).run(&mut db, &mut out, 4)?;
+    std::fs::remove_file(&rules_path)?;
+    std::fs::remove_file(&dict_path)?;
+    assert_eq!(matching, ExitCode::SUCCESS);
+    assert_eq!(mismatched, ExitCode::FAILURE);
+    Ok(())
+}
+
+#[test]
+fn test_option_tallies() {
+    let options: BTreeSet<String> = ["casa*", "lar", "moradia"]
+        .into_iter()
+        .map(String::from)
+        .collect();
+    let found_words = [
+        (Some("lar".to_string()), 5),
+        (Some("casinha".to_string()), 1),
+        (Some("casas".to_string()), 2),
+        (Some("casa".to_string()), 3),
+        (None, 4),
+    ];
+    assert_eq!(
+        option_tallies(&options, &found_words, false),
+        (
+            vec![
+                ("casa*".to_string(), 5),
+                ("lar".to_string(), 5),
+                ("moradia".to_string(), 0)
+            ],
+            5
+        )
+    );
+}
+
+#[test]
+fn test_option_tallies_case_insensitive() {
+    let options: BTreeSet<String> = ["casa*", "lar"].into_iter().map(String::from).collect();
+    let found_words = [(Some("Casas".to_string()), 2), (Some("LAR".to_string()), 1)];
+    assert_eq!(
+        option_tallies(&options, &found_words, false),
+        (vec![("casa*".to_string(), 0), ("lar".to_string(), 0)], 3)
+    );
+    assert_eq!(
+        option_tallies(&options, &found_words, true),
+        (vec![("casa*".to_string(), 2), ("lar".to_string(), 1)], 0)
+    );
+}
+
+#[test]
+fn test_option_stats() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for (found_at, found_word) in [
+        (Some(0), Some("b")),
+        (Some(1), Some("c")),
+        (Some(0), Some("b")),
+        (Some(2), Some("b")),
+        (None, None),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at, found_word) values (1, 'a = b | c', 0.5, ?, ?)",
+            (found_at, found_word),
+        )?;
+    }
+    assert_eq!(
+        db.get_found_word_counts("1".parse().unwrap(), "a = b | c")?,
+        vec![(Some("b".to_string()), 3), (Some("c".to_string()), 1)]
+    );
+    assert_eq!(
+        run_to_string(
+            Command::OptionStats {
+                session: "1".parse().unwrap(),
+                bench: "a = b | c".into()
+            },
+            &mut db
+        ),
+        "OPTION | WINS | SHARE\n\
+         b      | 3    | 75.0%\n\
+         c      | 1    | 25.0%\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_errors_by_kind() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for (bench, err, err_kind) in [
+        ("a = b", "bad rule 1", Some("BadRule")),
+        ("c = d", "bad rule 2", Some("BadRule")),
+        ("e = f", "oops", None),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, err, err_kind) values (1, ?, 0.5, ?, ?)",
+            (bench, err, err_kind),
+        )?;
+    }
+    let session = "1".parse().unwrap();
+    assert_eq!(
+        run_to_string(
+            Command::Errors {
+                session,
+                by_kind: true
+            },
+            &mut db
+        ),
+        "KIND      | COUNT | EXAMPLE ERROR | EXAMPLE BENCH\n\
+         BadRule   | 2     | bad rule 1    | a = b        \n\
+         (unknown) | 1     | oops          | e = f        \n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_trend_arrows() {
+    let secs = |secs: f64| Some(Duration::from_secs_f64(secs));
+    assert_eq!(trend_arrows(&[], 0.1), Vec::<&str>::new());
+    assert_eq!(trend_arrows(&[secs(1.0)], 0.1), vec![""]);
+    assert_eq!(
+        trend_arrows(
+            &[
+                secs(1.0),
+                secs(1.5),
+                secs(1.55),
+                secs(1.0),
+                None,
+                None,
+                secs(2.0)
+            ],
+            0.1
+        ),
+        vec!["", "↑", "=", "↓", "↑", "=", "↓"]
+    );
+}
+
+#[test]
+fn test_trend() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for (session, duration) in [(1, 0.5), (2, 1.0), (3, 1.5), (4, 2.0)] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', ?, 0)",
+            (session, duration),
+        )?;
+    }
+    let output = run_to_string(
+        Command::Trend {
+            bench: "a = b".into(),
+            last: 3,
+        },
+        &mut db,
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 5, "{}", output);
+    assert!(lines[1].starts_with("2 "), "{}", output);
+    assert!(lines[1].trim_end().ends_with("1.0000 |"), "{}", output);
+    assert!(lines[2].trim_end().ends_with("1.5000 | ↑"), "{}", output);
+    assert!(lines[3].trim_end().ends_with("2.0000 | ↑"), "{}", output);
+    assert_eq!(
+        lines[4],
+        "\u{26a0} score got worse in each of the last 2 sessions"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_explain() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    for (duration, found_at) in [
+        (0.5, Some(0)),
+        (0.25, Some(0)),
+        (0.75, Some(1)),
+        (2.0, None),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at) values (?, 'a = b', ?, ?)",
+            (session, duration, found_at),
+        )?;
+    }
+    let output = run_to_string(
+        Command::Explain {
+            session,
+            bench: "a = b".into(),
+        },
+        &mut db,
+    );
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 8, "{}", output);
+    assert!(lines[0].starts_with("Compiler: "));
+    assert!(lines[2].starts_with("0.2500 | 0 "), "{}", output);
+    assert!(lines[2].trim_end().ends_with("| kept"), "{}", output);
+    assert!(lines[5].starts_with("--     | not found"), "{}", output);
+    assert!(lines[5].trim_end().ends_with("| kept"), "{}", output);
+    assert_eq!(lines[6], "Dropped the best 0 and worst 0 of 4 results");
+    assert_eq!(lines[7], "Score: mean of the 4 kept = -- sec");
+    Ok(())
+}
+
+#[test]
+fn test_bench_detail() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    for (duration, found_at, err) in [
+        (0.5, Some(0), None),
+        (0.25, Some(0), None),
+        (0.75, Some(1), None),
+        (2.0, None, None),
+        (0.125, None, Some("oops")),
+        (0.375, Some(0), None),
+        (0.625, Some(0), None),
+        (1.0, Some(2), None),
+    ] {
+        db.conn.execute(
+            "insert into bench_run (session_id, bench, duration, found_at, err) values (?, 'a = b', ?, ?, ?)",
+            (session, duration, found_at, err),
+        )?;
+    }
+    let output = run_to_string(
+        Command::BenchDetail {
+            session,
+            bench: "a = b".into(),
+        },
+        &mut db,
+    );
+    let rows: Vec<Vec<&str>> = output
+        .lines()
+        .skip(1)
+        .map(|line| line.split('|').map(str::trim).collect())
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            ["5", "0.1250", "", "oops", "dropped (worst)"],
+            ["2", "0.2500", "0", "", "dropped (best)"],
+            ["6", "0.3750", "0", "", "kept"],
+            ["1", "0.5000", "0", "", "kept"],
+            ["7", "0.6250", "0", "", "kept"],
+            ["3", "0.7500", "1", "", "kept"],
+            ["8", "1.0000", "2", "", "kept"],
+            ["4", "2.0000", "not found", "", "kept"],
+        ],
+        "{}",
+        output
+    );
+    Ok(())
+}
+
+#[test]
+fn test_dump_load_archive() -> Result<(), Box<dyn Error>> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = "1000".parse::<BenchSessionId>()?;
+    let other = "2000".parse::<BenchSessionId>()?;
+    let key = |name| BenchKey::from_name(name).unwrap();
+    db.set_info(session, "machine", "laptop")?;
+    db.set_info(session, "search_rules", "a > b\nc > d\n")?;
+    db.set_info(other, "tag", "empty")?;
+    db.set_weight(session, "a = b | c", 2.0)?;
+    let results = [
+        (
+            "a = b | c",
+            BenchResult::success(3, Duration::from_millis(500)),
+        ),
+        ("a = b | c", BenchResult::not_found(Duration::from_secs(2))),
+        (
+            "d = e",
+            BenchResult::not_found(Duration::from_secs(1))
+                .with_stopped_by(Some(SearchLimit::Timeout)),
+        ),
+        (
+            "d = e",
+            BenchResult::error("oops", Duration::from_millis(1)),
+        ),
+    ];
+    for (bench, result) in results.clone() {
+        db.add_result(session, &key(bench), result)?;
+    }
+    let traces = vec![vec!["b".to_string(), "c".to_string()], vec![]];
+    for (run, words) in traces.iter().enumerate() {
+        db.add_trace(session, "a", run, words)?;
+    }
+
+    let mut archive = Vec::new();
+    assert_eq!(dump_archive(&mut db, &mut archive)?, 10);
+    let mut loaded = BenchDb::new(Connection::open_in_memory()?)?;
+    assert_eq!(load_archive(&mut loaded, archive.as_slice())?, 10);
+    assert_eq!(loaded.get_traces(session, "a")?, traces);
+
+    for id in [session, other] {
+        assert_eq!(loaded.get_all_info(id)?, db.get_all_info(id)?);
+        assert_eq!(loaded.get_weights(id)?, db.get_weights(id)?);
+        assert_eq!(loaded.get_bench_keys(id)?, db.get_bench_keys(id)?);
+    }
+    for bench in ["a = b | c", "d = e"] {
+        assert_eq!(
+            loaded.get_results(session, bench)?,
+            db.get_results(session, bench)?
+        );
+    }
+    assert_eq!(
+        loaded.get_results(session, "d = e")?,
+        results[2..]
+            .iter()
+            .map(|(_, result)| result.clone())
+            .collect::<Vec<_>>()
+    );
+
+    assert!(load_archive(&mut loaded, archive.as_slice()).is_err());
+    assert_eq!(loaded.get_results(session, "d = e")?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_load_archive_results_by_run() -> Result<(), Box<dyn Error>> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = "1000".parse::<BenchSessionId>()?;
+    let key = BenchKey::from_name("a = b").unwrap();
+    db.add_run(
+        session,
+        &key,
+        0,
+        BenchResult::success(3, Duration::from_millis(500)),
+    )?;
+    db.add_run(
+        session,
+        &key,
+        2,
+        BenchResult::not_found(Duration::from_secs(2)),
+    )?;
+    let mut archive = Vec::new();
+    dump_archive(&mut db, &mut archive)?;
+    let archive = String::from_utf8(archive)?;
+    assert!(archive.contains(r#""run_idx":2"#), "{}", archive);
+
+    // The same results twice are the same runs, added once
+    let mut loaded = BenchDb::new(Connection::open_in_memory()?)?;
+    load_archive(&mut loaded, format!("{}{}", archive, archive).as_bytes())?;
+    assert_eq!(
+        loaded.get_runs(session, "a = b")?,
+        db.get_runs(session, "a = b")?
+    );
+
+    // Archives from before run indices add their results as new runs
+    let old_archive = archive
+        .replace(r#""run_idx":0,"#, "")
+        .replace(r#""run_idx":2,"#, "");
+    let mut loaded = BenchDb::new(Connection::open_in_memory()?)?;
+    load_archive(&mut loaded, old_archive.as_bytes())?;
+    let run_indices: Vec<usize> = loaded
+        .get_runs(session, "a = b")?
+        .into_iter()
+        .map(|(run_idx, _)| run_idx)
+        .collect();
+    assert_eq!(run_indices, vec![0, 1]);
+    Ok(())
+}
+
+#[test]
+fn test_format_benchfile_in_place() -> Result<(), Box<dyn Error>> {
+    let path = std::env::temp_dir().join(format!("benchdb-test-bench-{}.txt", std::process::id()));
+    std::fs::write(&path, "b=c\na = d | c\n")?;
+    let changed = format_benchfile_in_place(&path)?;
+    let formatted = std::fs::read_to_string(&path)?;
+    let changed_again = format_benchfile_in_place(&path)?;
+    std::fs::remove_file(&path)?;
+    assert!(changed);
+    assert_eq!(formatted, "a = c | d\nb = c\n");
+    assert!(!changed_again);
+    Ok(())
+}
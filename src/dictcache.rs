@@ -0,0 +1,177 @@
+// Copyright © 2022 Daniel Getz
+// SPDX-License-Identifier: MIT
+
+//! Caches a dictionary's normalized word list, keyed by the dictionary file's path,
+//! modification time and size, so an unchanged dictionary doesn't need reading or hashing.
+//!
+//! Buscaluso's loaded dictionary state isn't serializable,
+//! so this caches the trimmed, non-blank lines of the dictionary
+//! in a compact binary file instead, along with the SHA-256 of the original.
+//!
+//! A file can be rewritten with the same size within its filesystem's modification time
+//! resolution, keeping the same key. So like git's "racy clean" check, a cached dictionary
+//! written within that resolution of the file's modification time is only used
+//! once the file's SHA-256 is checked against it.
+
+#[cfg(test)]
+mod tests;
+
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::{count_dict_words, file_sha256_hex, sha256_hex};
+
+/// The coarsest modification time resolution of the usual filesystems, FAT's two seconds.
+const MTIME_RESOLUTION: Duration = Duration::from_secs(2);
+
+pub fn dict_words(dict: &str) -> Vec<String> {
+    dict.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// A dictionary ready to load, with what the session info needs to know about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadedDict {
+    /// The SHA-256 of the dictionary as it was read.
+    pub hash: String,
+    /// The dictionary's words, one per line. From the cache, they're already trimmed.
+    pub text: String,
+}
+
+impl LoadedDict {
+    pub fn new(dict: String) -> LoadedDict {
+        LoadedDict {
+            hash: sha256_hex(dict.as_bytes()),
+            text: dict,
+        }
+    }
+
+    pub fn word_count(&self) -> usize {
+        count_dict_words(&self.text)
+    }
+
+    pub fn words(&self) -> Vec<String> {
+        dict_words(&self.text)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DictCache {
+    dir: PathBuf,
+}
+
+impl DictCache {
+    pub fn new(dir: impl Into<PathBuf>) -> DictCache {
+        DictCache { dir: dir.into() }
+    }
+
+    /// The cache key for the dictionary file at `path`, which changes whenever the file
+    /// is replaced or written to.
+    pub fn key(path: &Path) -> io::Result<String> {
+        let path = fs::canonicalize(path)?;
+        let metadata = fs::metadata(&path)?;
+        let modified = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+        let id = format!("{}\n{}\n{}", path.display(), modified, metadata.len());
+        Ok(sha256_hex(id.as_bytes()))
+    }
+
+    pub fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", key))
+    }
+
+    /// Loads the cached dictionary for a key, or `None` if it isn't cached.
+    pub fn load(&self, key: &str) -> io::Result<Option<LoadedDict>> {
+        match File::open(self.path(key)) {
+            Ok(file) => read_dict(&mut BufReader::new(file)).map(Some),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes to a temporary file named for this process first, and then renames it,
+    /// so other runs never see a half-written cache file.
+    pub fn store(&self, key: &str, dict: &LoadedDict) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(key);
+        let temp_path = self.dir.join(format!("{}.{}.tmp", key, std::process::id()));
+        let mut out = BufWriter::new(File::create(&temp_path)?);
+        write_dict(&mut out, dict)?;
+        out.into_inner()?.sync_all()?;
+        fs::rename(temp_path, path)
+    }
+
+    /// Whether the file at `path` was modified so close to when its cache entry for `key`
+    /// was written that a change since might not have changed its modification time.
+    fn is_racy(&self, path: &Path, key: &str) -> io::Result<bool> {
+        let modified = fs::metadata(path)?.modified()?;
+        let cached = fs::metadata(self.path(key))?.modified()?;
+        Ok(cached
+            .duration_since(modified)
+            .map_or(true, |since| since < MTIME_RESOLUTION))
+    }
+
+    /// Gets the cached dictionary for the file at `path`, or reads it with `read` and caches it
+    /// if it's missing, unreadable, or the file has changed since.
+    /// If the cache entry is racy, the file is hashed to check that it hasn't changed.
+    pub fn get_or_store(
+        &self,
+        path: &Path,
+        read: impl FnOnce() -> io::Result<String>,
+    ) -> io::Result<LoadedDict> {
+        let key = DictCache::key(path)?;
+        if let Ok(Some(dict)) = self.load(&key) {
+            if !self.is_racy(path, &key)? || file_sha256_hex(path)? == dict.hash {
+                return Ok(dict);
+            }
+        }
+        let dict = LoadedDict::new(read()?);
+        let cached = LoadedDict {
+            hash: dict.hash.clone(),
+            text: dict.words().join("\n"),
+        };
+        self.store(&key, &cached)?;
+        Ok(cached)
+    }
+}
+
+fn write_len(out: &mut impl Write, len: usize) -> io::Result<()> {
+    let len = u32::try_from(len).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    out.write_all(&len.to_le_bytes())
+}
+
+fn read_len(input: &mut impl Read) -> io::Result<usize> {
+    let mut bytes = [0; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes) as usize)
+}
+
+fn write_str(out: &mut impl Write, s: &str) -> io::Result<()> {
+    write_len(out, s.len())?;
+    out.write_all(s.as_bytes())
+}
+
+fn read_str(input: &mut impl Read) -> io::Result<String> {
+    let mut bytes = vec![0; read_len(input)?];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+fn write_dict(out: &mut impl Write, dict: &LoadedDict) -> io::Result<()> {
+    write_str(out, &dict.hash)?;
+    write_str(out, &dict.text)
+}
+
+fn read_dict(input: &mut impl Read) -> io::Result<LoadedDict> {
+    Ok(LoadedDict {
+        hash: read_str(input)?,
+        text: read_str(input)?,
+    })
+}
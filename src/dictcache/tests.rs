@@ -0,0 +1,118 @@
+// Copyright © 2022 Daniel Getz
+// SPDX-License-Identifier: MIT
+
+use super::*;
+
+use quickcheck_macros::quickcheck;
+
+fn temp_cache(name: &str) -> DictCache {
+    let dir = std::env::temp_dir().join(format!(
+        "buscaluso-bench-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    DictCache::new(dir)
+}
+
+#[test]
+fn test_dict_words() {
+    assert_eq!(
+        dict_words(" um\n\ndois \r\n  \ntrês"),
+        vec!["um", "dois", "três"]
+    );
+}
+
+#[quickcheck]
+fn test_write_read_dict(hash: String, text: String) -> io::Result<bool> {
+    let dict = LoadedDict { hash, text };
+    let mut buffer = Vec::new();
+    write_dict(&mut buffer, &dict)?;
+    Ok(read_dict(&mut buffer.as_slice())? == dict)
+}
+
+#[test]
+fn test_loaded_dict() {
+    let dict = LoadedDict::new(" um\n\ndois \n".to_string());
+    assert_eq!(dict.hash, sha256_hex(b" um\n\ndois \n"));
+    assert_eq!(dict.word_count(), 2);
+    assert_eq!(dict.words(), vec!["um", "dois"]);
+}
+
+#[test]
+fn test_cache_miss_then_hit() -> io::Result<()> {
+    let cache = temp_cache("dict-cache-hit");
+    fs::create_dir_all(&cache.dir)?;
+    let dict_path = cache.dir.join("dict.txt");
+    fs::write(&dict_path, " um\ndois\n")?;
+    assert_eq!(cache.load(&DictCache::key(&dict_path)?)?, None);
+
+    let read = || fs::read_to_string(&dict_path);
+    let dict = cache.get_or_store(&dict_path, read)?;
+    assert_eq!(dict.text, "um\ndois");
+    assert_eq!(dict.hash, sha256_hex(b" um\ndois\n"));
+    // a hit doesn't read the dictionary again
+    assert_eq!(
+        cache.get_or_store(&dict_path, || panic!("read a cached dictionary"))?,
+        dict
+    );
+
+    // changing the file changes the key
+    fs::write(&dict_path, "outro\n")?;
+    let changed = cache.get_or_store(&dict_path, read)?;
+    assert_eq!(changed.words(), vec!["outro"]);
+    assert_ne!(changed.hash, dict.hash);
+    fs::remove_dir_all(cache.dir)
+}
+
+#[test]
+fn test_cache_racy_rewrite_reloads() -> io::Result<()> {
+    let cache = temp_cache("dict-cache-racy");
+    fs::create_dir_all(&cache.dir)?;
+    let dict_path = cache.dir.join("dict.txt");
+    fs::write(&dict_path, "um\n")?;
+    let modified = fs::metadata(&dict_path)?.modified()?;
+    let read = || fs::read_to_string(&dict_path);
+    assert_eq!(cache.get_or_store(&dict_path, read)?.words(), vec!["um"]);
+
+    // The same size and modification time, as on a filesystem with a coarse one
+    fs::write(&dict_path, "de\n")?;
+    File::options()
+        .write(true)
+        .open(&dict_path)?
+        .set_modified(modified)?;
+    assert_eq!(
+        cache.get_or_store(&dict_path, read)?.words(),
+        vec!["de"],
+        "a racy cache entry is checked against the file"
+    );
+    fs::remove_dir_all(cache.dir)
+}
+
+#[test]
+fn test_cache_store_leaves_no_temp_file() -> io::Result<()> {
+    let cache = temp_cache("dict-cache-temp");
+    let dict = LoadedDict::new("um\n".to_string());
+    cache.store("abc", &dict)?;
+    cache.store("abc", &dict)?;
+    let names: Vec<_> = fs::read_dir(&cache.dir)?
+        .map(|entry| entry.map(|entry| entry.file_name()))
+        .collect::<io::Result<_>>()?;
+    assert_eq!(names, vec![std::ffi::OsString::from("abc.bin")]);
+    fs::remove_dir_all(cache.dir)
+}
+
+#[test]
+fn test_cache_corrupt_file_reloads() -> io::Result<()> {
+    let cache = temp_cache("dict-cache-corrupt");
+    fs::create_dir_all(&cache.dir)?;
+    let dict_path = cache.dir.join("dict.txt");
+    fs::write(&dict_path, "um\n")?;
+    let key = DictCache::key(&dict_path)?;
+    fs::write(cache.path(&key), [9, 0, 0, 0, 1])?;
+    assert!(cache.load(&key).is_err());
+    let dict = cache.get_or_store(&dict_path, || Ok("um\n".to_string()))?;
+    assert_eq!(dict.words(), vec!["um"]);
+    assert_eq!(cache.load(&key)?, Some(dict));
+    fs::remove_dir_all(cache.dir)
+}
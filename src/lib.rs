@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: MIT
 
 mod benchfile;
+pub mod dictcache;
 pub mod sqlite;
 
 shadow_rs::shadow!(build);
@@ -9,20 +10,29 @@ shadow_rs::shadow!(build);
 #[cfg(test)]
 mod tests;
 
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::cmp::{max, min, Ordering};
 use std::collections::{BTreeMap, BTreeSet};
+use std::ffi::OsString;
+use std::fmt::{Debug, Display};
 use std::fs::File;
-use std::io::{BufRead, Read};
-use std::num::NonZeroUsize;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::num::{NonZeroU8, NonZeroUsize};
 use std::ops::RangeInclusive;
 use std::path::{Path, PathBuf};
 use std::result::Result;
+use std::sync::mpsc::Receiver;
 use std::time::{Duration, Instant};
 
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+use log::{debug, warn, LevelFilter};
 use nom::Finish;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use rand::{thread_rng, Rng, SeedableRng};
+use regex::Regex;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use sha2::{Digest, Sha256};
 use shadow_rs::formatcp;
 use thiserror::Error;
@@ -31,6 +41,9 @@ use unicode_normalization::UnicodeNormalization;
 
 use buscaluso::BuscaCfg;
 
+use dictcache::LoadedDict;
+use sqlite::{BenchDb, BenchSessionId};
+
 #[derive(Error, Debug)]
 pub enum BenchError {
     #[error("IO error {source:?}")]
@@ -41,27 +54,367 @@ pub enum BenchError {
 
     #[error("Parsing error on line {line_no}: {text:?}")]
     ParseErr { line_no: usize, text: String },
+
+    #[error("benchfile contains no benches")]
+    NoBenches,
+
+    #[error("no failed benches from session {session} to retry")]
+    NoFailedBenches { session: BenchSessionId },
 }
 
 use BenchError::*;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct BenchResult {
-    found_index: Result<Option<usize>, String>,
+    found_index: Result<Option<usize>, SearchError>,
     elapsed: Duration,
+    /// For a search that didn't find its target, which limit stopped it,
+    /// or `None` if it ran out of words.
+    stopped_by: Option<SearchLimit>,
+    /// How many bytes the process's resident memory grew by during the search, at its peak,
+    /// if `measure_memory` was on and the platform supports it.
+    peak_rss: Option<u64>,
+    /// For a search that found its target, the word that matched it, if that was recorded.
+    found_word: Option<String>,
+    /// How long it took to set up the search before it started finding words, if recorded.
+    setup: Option<Duration>,
+    /// How long the search took to find its first word of any kind, if recorded
+    /// and it found any.
+    first_result: Option<Duration>,
+}
+
+/// An error that stopped a search: its message, and what kind of error it was,
+/// so that errors can be grouped even when their messages differ.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SearchError {
+    /// What kind of error it was, usually the name of a `SearchErrorKind`,
+    /// or `None` if it wasn't recorded.
+    pub kind: Option<String>,
+    pub message: String,
+}
+
+impl SearchError {
+    /// Records an error's message, and its kind from what the message is about.
+    ///
+    /// ```
+    /// # use buscaluso_bench::SearchError;
+    /// let err = buscaluso::BuscaCfg::new()
+    ///     .load_dictionary(&b"\xff\n"[..])
+    ///     .unwrap_err();
+    /// let err = SearchError::from_error(&err);
+    /// assert_eq!(err.kind.as_deref(), Some("encoding"));
+    /// assert_eq!(err.message, "stream did not contain valid UTF-8");
+    /// ```
+    pub fn from_error(err: &impl Display) -> SearchError {
+        let message = err.to_string();
+        SearchError {
+            kind: Some(SearchErrorKind::classify(&message).name().to_string()),
+            message,
+        }
+    }
+}
+
+/// The kinds of search errors, to group them by.
+///
+/// Buscaluso's `BuscaError` only has a message, so the kind is worked out from that.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SearchErrorKind {
+    /// Reading some input failed.
+    Io,
+    /// Some text wasn't valid UTF-8 or couldn't be normalized.
+    Encoding,
+    /// Something was wrong with the rules.
+    Rules,
+    /// Something was wrong with the dictionary.
+    Dictionary,
+    /// Anything else.
+    Other,
+}
+
+impl SearchErrorKind {
+    /// The kind of error with this message, going by the words in it.
+    pub fn classify(message: &str) -> SearchErrorKind {
+        let message = message.to_lowercase();
+        let mentions = |words: &[&str]| words.iter().any(|word| message.contains(word));
+        if mentions(&["utf-8", "utf8", "unicode", "encoding", "normaliz"]) {
+            SearchErrorKind::Encoding
+        } else if mentions(&["os error", "i/o", "io error"]) {
+            SearchErrorKind::Io
+        } else if mentions(&["rule"]) {
+            SearchErrorKind::Rules
+        } else if mentions(&["dictionary", "dict "]) {
+            SearchErrorKind::Dictionary
+        } else {
+            SearchErrorKind::Other
+        }
+    }
+
+    /// The name this kind is stored with.
+    pub fn name(self) -> &'static str {
+        match self {
+            SearchErrorKind::Io => "io",
+            SearchErrorKind::Encoding => "encoding",
+            SearchErrorKind::Rules => "rules",
+            SearchErrorKind::Dictionary => "dictionary",
+            SearchErrorKind::Other => "other",
+        }
+    }
+}
+
+/// An error with only a message, and no kind.
+impl From<String> for SearchError {
+    fn from(message: String) -> SearchError {
+        SearchError {
+            kind: None,
+            message,
+        }
+    }
+}
+
+impl From<&str> for SearchError {
+    fn from(message: &str) -> SearchError {
+        SearchError::from(message.to_string())
+    }
+}
+
+/// Something benches can be run on, that finds words from a start word, like a [`BuscaCfg`].
+/// Tests can fake one to script the words a search finds.
+pub trait WordSearch {
+    /// Sets up a search from `start_word`, giving the words it finds in order,
+    /// with `None` for each step that didn't find one.
+    fn search<'s>(
+        &'s self,
+        start_word: &str,
+    ) -> Result<Box<dyn Iterator<Item = Option<&'s str>> + 's>, SearchError>;
+}
+
+impl WordSearch for BuscaCfg {
+    fn search<'s>(
+        &'s self,
+        start_word: &str,
+    ) -> Result<Box<dyn Iterator<Item = Option<&'s str>> + 's>, SearchError> {
+        let mut searcher =
+            BuscaCfg::search(self, start_word).map_err(|err| SearchError::from_error(&err))?;
+        Ok(Box::new(
+            searcher.iter().map(|word| word.map(|(word, _)| word)),
+        ))
+    }
+}
+
+/// Where running benches gets the time from, so that tests can control it.
+pub trait Clock {
+    fn now(&self) -> Instant;
+
+    /// How long it's been since `start`, by this clock.
+    fn elapsed_since(&self, start: Instant) -> Duration {
+        self.now().saturating_duration_since(start)
+    }
+}
+
+/// The real time, from [`Instant::now`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A limit that can stop a search before it finds its targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchLimit {
+    /// The search took longer than the timeout.
+    Timeout,
+    /// The search went through more words than the maximum index.
+    MaxIndex,
 }
 
 impl BenchResult {
-    fn success(found_index: usize, elapsed: Duration) -> BenchResult {
+    /// A result with the index its target was found at, `None` if it wasn't found,
+    /// or the error that stopped the search.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// let result = BenchResult::new(Ok(Some(3)), Duration::from_millis(20));
+    /// assert_eq!(result.found_index(), Ok(Some(3)));
+    /// ```
+    pub fn new(found_index: Result<Option<usize>, SearchError>, elapsed: Duration) -> BenchResult {
         BenchResult {
-            found_index: Ok(Some(found_index)),
+            found_index,
             elapsed,
+            stopped_by: None,
+            peak_rss: None,
+            found_word: None,
+            setup: None,
+            first_result: None,
+        }
+    }
+
+    /// A result that found its target at `found_index`.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// let result = BenchResult::success(0, Duration::from_millis(20));
+    /// assert!(result.is_found());
+    /// ```
+    pub fn success(found_index: usize, elapsed: Duration) -> BenchResult {
+        BenchResult::new(Ok(Some(found_index)), elapsed)
+    }
+
+    /// A result that searched without finding its target.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// let result = BenchResult::not_found(Duration::from_secs(1));
+    /// assert_eq!(result.found_index(), Ok(None));
+    /// assert!(!result.is_found());
+    /// ```
+    pub fn not_found(elapsed: Duration) -> BenchResult {
+        BenchResult::new(Ok(None), elapsed)
+    }
+
+    /// A result where the search failed with an error.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// let result = BenchResult::error("oops", Duration::ZERO);
+    /// assert_eq!(result.found_index(), Err("oops"));
+    /// assert!(!result.is_found());
+    /// ```
+    pub fn error(err: impl Into<SearchError>, elapsed: Duration) -> BenchResult {
+        BenchResult::new(Err(err.into()), elapsed)
+    }
+
+    /// The index the target was found at, `None` if it wasn't found, or the search's error.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// assert_eq!(BenchResult::success(2, Duration::ZERO).found_index(), Ok(Some(2)));
+    /// assert_eq!(BenchResult::not_found(Duration::ZERO).found_index(), Ok(None));
+    /// assert_eq!(BenchResult::error("oops", Duration::ZERO).found_index(), Err("oops"));
+    /// ```
+    pub fn found_index(&self) -> Result<Option<usize>, &str> {
+        self.found_index
+            .as_ref()
+            .copied()
+            .map_err(|err| err.message.as_str())
+    }
+
+    /// For a search that gave an error, what kind of error it was, if that was recorded.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::{BenchResult, SearchError};
+    /// let err = SearchError {
+    ///     kind: Some("BadRule".into()),
+    ///     message: "oops".into(),
+    /// };
+    /// assert_eq!(BenchResult::error(err, Duration::ZERO).error_kind(), Some("BadRule"));
+    /// assert_eq!(BenchResult::error("oops", Duration::ZERO).error_kind(), None);
+    /// ```
+    pub fn error_kind(&self) -> Option<&str> {
+        self.found_index.as_ref().err()?.kind.as_deref()
+    }
+
+    /// The same result, but recording which limit stopped the search.
+    pub fn with_stopped_by(self, stopped_by: Option<SearchLimit>) -> BenchResult {
+        BenchResult { stopped_by, ..self }
+    }
+
+    /// For a search that didn't find its target, which limit stopped it,
+    /// or `None` if it ran out of words.
+    pub fn stopped_by(&self) -> Option<SearchLimit> {
+        self.stopped_by
+    }
+
+    /// The same result, but recording how much the memory use grew during the search.
+    pub fn with_peak_rss(self, peak_rss: Option<u64>) -> BenchResult {
+        BenchResult { peak_rss, ..self }
+    }
+
+    /// How many bytes the resident memory grew by during the search, if measured.
+    pub fn peak_rss(&self) -> Option<u64> {
+        self.peak_rss
+    }
+
+    /// The same result, but recording which word matched the target.
+    pub fn with_found_word(self, found_word: Option<String>) -> BenchResult {
+        BenchResult { found_word, ..self }
+    }
+
+    /// For a search that found its target, the word that matched it, if recorded.
+    /// With a target of several options, this tells which one it found.
+    pub fn found_word(&self) -> Option<&str> {
+        self.found_word.as_deref()
+    }
+
+    /// The same result, but recording how long the search took to set up.
+    pub fn with_setup(self, setup: Option<Duration>) -> BenchResult {
+        BenchResult { setup, ..self }
+    }
+
+    /// How long the search took to set up before it started finding words, if recorded.
+    /// With `exclude_setup`, this isn't part of `elapsed`.
+    pub fn setup(&self) -> Option<Duration> {
+        self.setup
+    }
+
+    /// The same result, but recording when the search found its first word.
+    pub fn with_first_result(self, first_result: Option<Duration>) -> BenchResult {
+        BenchResult {
+            first_result,
+            ..self
         }
     }
 
-    fn is_found(&self) -> bool {
+    /// How long the search took to find its first word, whether or not it was a target,
+    /// on the same clock as `elapsed`. `None` if it found no words, or that wasn't recorded.
+    pub fn first_result(&self) -> Option<Duration> {
+        self.first_result
+    }
+
+    /// How long the search took.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// let result = BenchResult::not_found(Duration::from_millis(250));
+    /// assert_eq!(result.elapsed(), Duration::from_millis(250));
+    /// ```
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Whether the search found its target, without an error.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// assert!(BenchResult::success(0, Duration::ZERO).is_found());
+    /// assert!(!BenchResult::not_found(Duration::ZERO).is_found());
+    /// ```
+    pub fn is_found(&self) -> bool {
         matches!(self.found_index, Ok(Some(_)))
     }
+
+    /// Whether the search gave an error, as opposed to finding its target or not.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use buscaluso_bench::BenchResult;
+    /// assert!(BenchResult::error("oops", Duration::ZERO).is_error());
+    /// assert!(!BenchResult::not_found(Duration::ZERO).is_error());
+    /// ```
+    pub fn is_error(&self) -> bool {
+        self.found_index.is_err()
+    }
 }
 
 impl PartialOrd for BenchResult {
@@ -70,29 +423,143 @@ impl PartialOrd for BenchResult {
     }
 }
 
+/// Orders results from best to worst: first the ones that found their target, by index and
+/// then time, then the ones that didn't, by time, and last the errors, by time.
+/// Error messages are only compared to break ties, so that the order agrees with equality.
 impl Ord for BenchResult {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        match (&self.found_index, &other.found_index) {
-            (Ok(_), Err(_)) | (Ok(None), Ok(Some(_))) => return Ordering::Greater,
-            (Err(_), Ok(_)) | (Ok(Some(_)), Ok(None)) => return Ordering::Less,
-            _ => {}
-        }
-        (&self.found_index, self.elapsed).cmp(&(&other.found_index, other.elapsed))
+        let rank = |result: &BenchResult| match result.found_index {
+            Ok(Some(index)) => (0, index),
+            Ok(None) => (1, 0),
+            Err(_) => (2, 0),
+        };
+        rank(self)
+            .cmp(&rank(other))
+            .then(self.elapsed.cmp(&other.elapsed))
+            .then(self.stopped_by.cmp(&other.stopped_by))
+            .then(self.peak_rss.cmp(&other.peak_rss))
+            .then_with(|| self.found_word.cmp(&other.found_word))
+            .then(self.setup.cmp(&other.setup))
+            .then(self.first_result.cmp(&other.first_result))
+            .then_with(|| match (&self.found_index, &other.found_index) {
+                (Err(ours), Err(theirs)) => ours.cmp(theirs),
+                _ => Ordering::Equal,
+            })
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct BenchResultCompiler {
     index_equivalent: Duration,
-    drop_fraction: f64,
+    trim: TrimSpec,
+    index_cost: IndexCost,
+    /// How many times to resample the scores for the score's confidence interval,
+    /// or 0 not to compute one, which is the default.
+    bootstrap_resamples: usize,
+    /// Seeds the resampling, so the same results always get the same interval.
+    bootstrap_seed: u64,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// How many resamples to use for confidence intervals, when they're wanted.
+pub const DEFAULT_BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// The seed `BenchResultCompiler` resamples with, unless told otherwise.
+pub const DEFAULT_BOOTSTRAP_SEED: u64 = 0x6275_7363_616c_7573;
+
+/// How much of the resampled scores' distribution the confidence interval covers.
+pub const BOOTSTRAP_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// How the cost of a found result grows with its index, in units of the index equivalent.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum IndexCost {
+    /// Each index costs one index equivalent more than the one before.
+    #[default]
+    Linear,
+    /// Index `i` costs `log_base(i + 1)` index equivalents.
+    Log { base: f64 },
+}
+
+impl IndexCost {
+    fn cost(&self, index: usize) -> f64 {
+        match *self {
+            IndexCost::Linear => index as f64,
+            IndexCost::Log { base } => (index as f64 + 1.0).ln() / base.ln(),
+        }
+    }
+}
+
+/// Fractions of the results to drop from the low and high ends before compiling them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimSpec {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl TrimSpec {
+    pub fn new(low: f64, high: f64) -> TrimSpec {
+        assert!((0.0..0.5).contains(&low));
+        assert!((0.0..0.5).contains(&high));
+        TrimSpec { low, high }
+    }
+
+    /// Drops `drop_fraction / 2` from each end.
+    pub fn symmetric(drop_fraction: f64) -> TrimSpec {
+        TrimSpec::new(drop_fraction / 2.0, drop_fraction / 2.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CompiledBenchResult {
+    #[serde(
+        default,
+        serialize_with = "option_duration_serialize_seconds",
+        deserialize_with = "option_duration_deserialize_seconds"
+    )]
     pub score: Option<Duration>,
+
     pub errors: Vec<String>,
+
+    #[serde(
+        default,
+        serialize_with = "option_range_serialize",
+        deserialize_with = "option_range_deserialize"
+    )]
     pub found_index: Option<RangeInclusive<usize>>,
+
+    #[serde(
+        default,
+        serialize_with = "option_duration_range_serialize_seconds",
+        deserialize_with = "option_duration_range_deserialize_seconds"
+    )]
     pub elapsed: Option<RangeInclusive<Duration>>,
+
+    /// The scores of the kept results that found their target, lowest first.
+    #[serde(
+        default,
+        skip_serializing_if = "Vec::is_empty",
+        serialize_with = "durations_serialize_seconds",
+        deserialize_with = "durations_deserialize_seconds"
+    )]
+    pub kept_scores: Vec<Duration>,
+
+    /// How many of the kept results didn't find their target, and so have no score.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub kept_failures: usize,
+
+    /// How much the time taken by the kept results that found their target varies:
+    /// the standard deviation as a fraction of the mean. Needs at least two of them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elapsed_cov: Option<f64>,
+
+    /// A bootstrap confidence interval for the score, if the compiler computed one
+    /// and it's bounded: resamples with too many failures have no score.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_duration_range_serialize_seconds",
+        deserialize_with = "option_duration_range_deserialize_seconds"
+    )]
+    pub score_ci: Option<RangeInclusive<Duration>>,
 }
 
 impl CompiledBenchResult {
@@ -112,6 +579,102 @@ impl CompiledBenchResult {
             (None, None) => 0.0,
         }
     }
+
+    /// Whether there are kept results to test the significance of differences with.
+    /// Summaries stored by older versions don't have them.
+    pub fn has_samples(&self) -> bool {
+        !self.kept_scores.is_empty() || self.kept_failures > 0
+    }
+
+    /// Whether our kept results are better than `other`'s, by a one-sided Mann-Whitney U test at
+    /// significance level `alpha`, counting results that didn't find their target as worse than
+    /// any that did. Always false if either has no samples.
+    pub fn is_significantly_better_than(&self, other: &CompiledBenchResult, alpha: f64) -> bool {
+        if !self.has_samples() || !other.has_samples() {
+            return false;
+        }
+        mann_whitney_p_value(&self.sample_scores(), &other.sample_scores()) < alpha
+    }
+
+    fn sample_scores(&self) -> Vec<f64> {
+        self.kept_scores
+            .iter()
+            .map(Duration::as_secs_f64)
+            .chain(std::iter::repeat_n(f64::INFINITY, self.kept_failures))
+            .collect()
+    }
+}
+
+/// One-sided p-value for the values in `xs` tending to be lower than the ones in `ys`,
+/// by the normal approximation to the Mann-Whitney U test, with tie and continuity corrections.
+/// Both must be non-empty.
+fn mann_whitney_p_value(xs: &[f64], ys: &[f64]) -> f64 {
+    let n1 = xs.len() as f64;
+    let n2 = ys.len() as f64;
+    let u: f64 = xs
+        .iter()
+        .flat_map(|x| {
+            ys.iter()
+                .map(move |y| (x < y) as u8 as f64 + (x == y) as u8 as f64 / 2.0)
+        })
+        .sum();
+
+    let mut all: Vec<f64> = xs.iter().chain(ys).copied().collect();
+    all.sort_by(|x, y| x.partial_cmp(y).unwrap());
+    let ties: f64 = all
+        .chunk_by(|x, y| x == y)
+        .map(|group| (group.len() as f64).powi(3) - group.len() as f64)
+        .sum();
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        // Everything is tied.
+        return 1.0;
+    }
+    let z = (u - n1 * n2 / 2.0 - 0.5) / variance.sqrt();
+    1.0 - standard_normal_cdf(z)
+}
+
+/// The standard normal distribution function, using the approximation to `erf`
+/// from Abramowitz and Stegun 7.1.26, good to about 1e-7.
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+/// The sample standard deviation of some values, or `None` if there are fewer than two.
+pub fn sample_std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt())
+}
+
+/// The sample standard deviation of some values, divided by their mean,
+/// or `None` if there are fewer than two or their mean isn't positive.
+fn coefficient_of_variation(values: &[f64]) -> Option<f64> {
+    let std_dev = sample_std_dev(values)?;
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    if mean <= 0.0 {
+        return None;
+    }
+    Some(std_dev / mean)
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
 }
 
 pub fn extend_range<T: Ord + Copy>(range: RangeInclusive<T>, value: T) -> RangeInclusive<T> {
@@ -137,40 +700,164 @@ pub fn get_range<T: Ord + Copy>(values: impl IntoIterator<Item = T>) -> Option<R
         .map(|first| iter.fold(first..=first, extend_range))
 }
 
+/// Builds up the range covering a series of values and ranges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeAccumulator<T> {
+    range: Option<RangeInclusive<T>>,
+}
+
+impl<T: Ord + Copy> RangeAccumulator<T> {
+    pub fn new() -> RangeAccumulator<T> {
+        RangeAccumulator { range: None }
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.range = Some(match self.range.take() {
+            Some(range) => extend_range(range, value),
+            None => value..=value,
+        });
+    }
+
+    pub fn push_range(&mut self, range: &RangeInclusive<T>) {
+        self.range = Some(match self.range.take() {
+            Some(existing) => combine_ranges(&existing, range),
+            None => range.clone(),
+        });
+    }
+
+    pub fn finish(self) -> Option<RangeInclusive<T>> {
+        self.range
+    }
+}
+
+impl<T: Ord + Copy> Default for RangeAccumulator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl BenchResultCompiler {
     pub fn new(index_equivalent: Duration, drop_fraction: f64) -> BenchResultCompiler {
         assert!(drop_fraction >= 0.0);
         assert!(drop_fraction < 1.0);
+        BenchResultCompiler::with_trim(index_equivalent, TrimSpec::symmetric(drop_fraction))
+    }
+
+    pub fn with_trim(index_equivalent: Duration, trim: TrimSpec) -> BenchResultCompiler {
         BenchResultCompiler {
             index_equivalent,
-            drop_fraction,
+            trim,
+            index_cost: IndexCost::Linear,
+            bootstrap_resamples: 0,
+            bootstrap_seed: DEFAULT_BOOTSTRAP_SEED,
+        }
+    }
+
+    /// Describes the compiler's parameters, to tell whether results it compiled
+    /// would still be the same with another compiler.
+    /// The bootstrap settings are left out: they only change `score_ci`,
+    /// so a saved summary is still good with or without a confidence interval.
+    pub fn describe(&self) -> String {
+        format!(
+            "BenchResultCompiler {{ index_equivalent: {:?}, trim: {:?}, index_cost: {:?} }}",
+            self.index_equivalent, self.trim, self.index_cost
+        )
+    }
+
+    pub fn with_index_cost(mut self, index_cost: IndexCost) -> BenchResultCompiler {
+        if let IndexCost::Log { base } = index_cost {
+            assert!(base > 1.0);
+        }
+        self.index_cost = index_cost;
+        self
+    }
+
+    /// Resamples `resamples` times, with the given seed, for the score's confidence interval.
+    /// With 0 resamples, there is no confidence interval.
+    pub fn with_bootstrap(mut self, resamples: usize, seed: u64) -> BenchResultCompiler {
+        self.bootstrap_resamples = resamples;
+        self.bootstrap_seed = seed;
+        self
+    }
+
+    /// How many of the lowest and highest of `len` scores get dropped.
+    fn trim_counts(&self, len: usize) -> (usize, usize) {
+        let drop_low = (self.trim.low * len as f64).floor() as usize;
+        let drop_high = (self.trim.high * len as f64).floor() as usize;
+        (drop_low, drop_high)
+    }
+
+    /// The mean of some scores, sorted lowest first, after trimming them.
+    fn trimmed_mean(&self, sorted_scores: &[f64]) -> f64 {
+        let (drop_low, drop_high) = self.trim_counts(sorted_scores.len());
+        let kept = &sorted_scores[drop_low..sorted_scores.len() - drop_high];
+        kept.iter().sum::<f64>() / kept.len() as f64
+    }
+
+    /// A confidence interval for the trimmed mean of some scores, from resampling them
+    /// with replacement and taking the middle [`BOOTSTRAP_CONFIDENCE_LEVEL`] of the resamples'
+    /// trimmed means. `None` if there's nothing to resample, or either end isn't a finite score.
+    pub fn bootstrap_ci(&self, scores: &[f64]) -> Option<RangeInclusive<Duration>> {
+        if self.bootstrap_resamples == 0 || scores.is_empty() {
+            return None;
         }
+        let mut rng = StdRng::seed_from_u64(self.bootstrap_seed);
+        let mut resample = vec![0.0; scores.len()];
+        let mut means: Vec<f64> = (0..self.bootstrap_resamples)
+            .map(|_| {
+                for score in resample.iter_mut() {
+                    *score = *scores.choose(&mut rng).unwrap();
+                }
+                resample.sort_by(|x, y| x.partial_cmp(y).unwrap());
+                self.trimmed_mean(&resample)
+            })
+            .collect();
+        means.sort_by(|x, y| x.partial_cmp(y).unwrap());
+        let tail = (1.0 - BOOTSTRAP_CONFIDENCE_LEVEL) / 2.0;
+        let percentile = |p: f64| means[(p * (means.len() - 1) as f64).round() as usize];
+        let (lo, hi) = (percentile(tail), percentile(1.0 - tail));
+        (lo.is_finite() && hi.is_finite())
+            .then(|| Duration::from_secs_f64(lo)..=Duration::from_secs_f64(hi))
     }
 
     pub fn score(&self, result: &BenchResult) -> f64 {
         match result.found_index {
             Ok(Some(i)) => {
-                i as f64 * self.index_equivalent.as_secs_f64() + result.elapsed.as_secs_f64()
+                self.index_cost.cost(i) * self.index_equivalent.as_secs_f64()
+                    + result.elapsed.as_secs_f64()
             }
             _ => f64::INFINITY,
         }
     }
 
     pub fn compile(&self, results: impl IntoIterator<Item = BenchResult>) -> CompiledBenchResult {
-        let mut results: Vec<(f64, BenchResult)> =
-            results.into_iter().map(|r| (self.score(&r), r)).collect();
-        assert!(!results.is_empty());
-        results.sort_by(|x, y| x.partial_cmp(y).unwrap()); // force sorting of floats
+        let results: Vec<BenchResult> = results.into_iter().collect();
+        self.compile_ref(&results)
+    }
+
+    /// Like `compile`, but leaves the results for the caller to use again.
+    pub fn compile_ref(&self, results: &[BenchResult]) -> CompiledBenchResult {
+        self.compile_verbose(results).compiled
+    }
+
+    /// Like `compile_ref`, but also gives the steps along the way, to explain the score.
+    pub fn compile_verbose<'r>(&self, results: &'r [BenchResult]) -> CompileSteps<'r> {
+        let mut scored: Vec<(f64, &BenchResult)> =
+            results.iter().map(|r| (self.score(r), r)).collect();
+        assert!(!scored.is_empty());
+        // Only sort by score: the results with the same score are interchangeable here.
+        scored.sort_by(|(x, _), (y, _)| x.partial_cmp(y).unwrap()); // force sorting of floats
 
-        let errors: Vec<String> = results
+        let errors: Vec<String> = scored
             .iter()
-            .flat_map(|(_, r)| r.found_index.as_ref().err().cloned())
+            .flat_map(|(_, r)| r.found_index.as_ref().err())
+            .map(|err| err.message.clone())
             .collect();
 
-        let drop_num = (self.drop_fraction / 2.0 * results.len() as f64).floor() as usize;
-        let keep_num = results.len() - 2 * drop_num;
+        let (drop_low, drop_high) = self.trim_counts(scored.len());
+        let keep_num = scored.len() - drop_low - drop_high;
         debug_assert!(keep_num > 0);
-        let results = &results[drop_num..][..keep_num];
+        let results = &scored[drop_low..][..keep_num];
 
         let elapsed = get_range(
             results
@@ -185,10 +872,25 @@ impl BenchResultCompiler {
                 .map(|(_, r)| r.found_index.as_ref().unwrap().unwrap()),
         );
 
+        let kept_scores: Vec<Duration> = results
+            .iter()
+            .filter(|(s, _)| s.is_finite())
+            .map(|&(s, _)| Duration::from_secs_f64(s))
+            .collect();
+        let kept_failures = keep_num - kept_scores.len();
+        let elapsed_cov = coefficient_of_variation(
+            &results
+                .iter()
+                .filter(|(s, _)| s.is_finite())
+                .map(|(_, r)| r.elapsed.as_secs_f64())
+                .collect::<Vec<_>>(),
+        );
+
         let total: f64 = results.iter().map(|(s, _)| s).sum();
         let score = total / keep_num as f64;
+        let score_ci = self.bootstrap_ci(&scored.iter().map(|&(s, _)| s).collect::<Vec<_>>());
 
-        CompiledBenchResult {
+        let compiled = CompiledBenchResult {
             score: if score.is_finite() {
                 Some(Duration::from_secs_f64(score))
             } else {
@@ -197,10 +899,40 @@ impl BenchResultCompiler {
             errors,
             found_index,
             elapsed,
+            kept_scores,
+            kept_failures,
+            elapsed_cov,
+            score_ci,
+        };
+        CompileSteps {
+            scored,
+            drop_low,
+            drop_high,
+            compiled,
         }
     }
 }
 
+/// How `BenchResultCompiler` got to a compiled result.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompileSteps<'r> {
+    /// Every result with its score, best first. Results that didn't find their target score infinity.
+    pub scored: Vec<(f64, &'r BenchResult)>,
+    /// How many of the best-scoring results were dropped.
+    pub drop_low: usize,
+    /// How many of the worst-scoring results were dropped.
+    pub drop_high: usize,
+    /// The final result, whose score is the mean of the kept scores.
+    pub compiled: CompiledBenchResult,
+}
+
+impl<'r> CompileSteps<'r> {
+    /// The results that weren't dropped, with their scores.
+    pub fn kept(&self) -> &[(f64, &'r BenchResult)] {
+        &self.scored[self.drop_low..self.scored.len() - self.drop_high]
+    }
+}
+
 fn duration_serialize_seconds<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
@@ -208,218 +940,1373 @@ where
     serializer.serialize_f64(duration.as_secs_f64())
 }
 
-fn duration_deserialize_seconds<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+fn option_duration_serialize_seconds<S>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
-    D: Deserializer<'de>,
+    S: Serializer,
 {
-    let seconds: f64 = Deserialize::deserialize(deserializer)?;
-    Ok(Duration::from_secs_f64(seconds))
+    duration.map(|d| d.as_secs_f64()).serialize(serializer)
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub struct BenchRunCfg {
-    pub machine: Option<String>,
-    pub repeat: u8,
-    pub repeat_failed: u8,
+fn durations_serialize_seconds<S>(durations: &[Duration], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.collect_seq(durations.iter().map(Duration::as_secs_f64))
+}
 
-    #[serde(
-        serialize_with = "duration_serialize_seconds",
-        deserialize_with = "duration_deserialize_seconds"
-    )]
-    pub timeout: Duration,
+fn durations_deserialize_seconds<'de, D>(deserializer: D) -> Result<Vec<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let seconds: Vec<f64> = Deserialize::deserialize(deserializer)?;
+    Ok(seconds.into_iter().map(Duration::from_secs_f64).collect())
+}
 
-    #[serde(default)]
-    pub verbose: u8,
+fn option_duration_deserialize_seconds<'de, D>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let seconds: Option<f64> = Deserialize::deserialize(deserializer)?;
+    Ok(seconds.map(Duration::from_secs_f64))
+}
 
-    pub rules_file: Option<PathBuf>,
-    pub dict_file: Option<PathBuf>,
-    pub bench_file: Option<PathBuf>,
+/// A duration in a config file: a number of seconds, or a string with a unit, like `"500ms"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ConfigDuration {
+    Seconds(f64),
+    WithUnit(String),
+}
 
-    #[serde(default = "default_out_db")]
-    pub out_db: PathBuf,
+impl ConfigDuration {
+    fn to_duration(&self) -> Result<Duration, String> {
+        match self {
+            ConfigDuration::Seconds(seconds) => {
+                Duration::try_from_secs_f64(*seconds).map_err(|err| err.to_string())
+            }
+            ConfigDuration::WithUnit(text) => parse_duration(text),
+        }
+    }
 }
 
-fn default_out_db() -> PathBuf {
-    "bench.sqlite3".into()
+fn duration_deserialize_with_unit<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ConfigDuration::deserialize(deserializer)?
+        .to_duration()
+        .map_err(de::Error::custom)
 }
 
-#[derive(Debug, Clone)]
-pub struct Bencher {
-    benches: BTreeMap<String, BTreeMap<BTreeSet<String>, Vec<BenchResult>>>,
+fn option_duration_deserialize_with_unit<'de, D>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<ConfigDuration>::deserialize(deserializer)?
+        .map(|duration| duration.to_duration())
+        .transpose()
+        .map_err(de::Error::custom)
 }
 
-impl Bencher {
-    pub fn new() -> Bencher {
-        Bencher {
-            benches: BTreeMap::new(),
-        }
-    }
+fn option_range_serialize<S, T>(
+    range: &Option<RangeInclusive<T>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Serialize + Copy,
+{
+    range
+        .as_ref()
+        .map(|range| [*range.start(), *range.end()])
+        .serialize(serializer)
+}
 
-    pub fn add_bench<'a>(&mut self, start_word: &'a str, targets: &[&'a str]) {
-        self.benches
-            .entry(String::from(start_word))
-            .or_default()
-            .entry(BTreeSet::from_iter(
-                targets.iter().map(|&s| String::from(s)),
-            ))
-            .or_default();
+/// Reads either a list of paths or a single one.
+fn option_paths_deserialize<'de, D>(deserializer: D) -> Result<Option<Vec<PathBuf>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
     }
+    let paths: Option<OneOrMany> = Deserialize::deserialize(deserializer)?;
+    Ok(paths.map(|paths| match paths {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    }))
+}
 
-    pub fn load_benches<R: BufRead>(&mut self, input: R) -> Result<(), BenchError> {
-        let mut unaccented = String::new();
-        for (line_no, line) in input.lines().enumerate() {
-            match benchfile::bench_line(&line?).finish() {
-                Ok((_, Some((start_words, target_list)))) => {
-                    for start_word in start_words {
-                        set_unaccented(start_word, &mut unaccented);
-                        for targets in &target_list {
-                            self.add_bench(start_word, targets);
-                            if unaccented != start_word {
-                                self.add_bench(&unaccented, targets);
-                            }
-                        }
-                    }
-                    Ok(())
-                }
-                Ok((_, None)) => Ok(()),
-                Err(parse_err) => Err(ParseErr {
-                    line_no: line_no + 1,
-                    text: parse_err.input.to_owned(),
+fn option_range_deserialize<'de, D, T>(
+    deserializer: D,
+) -> Result<Option<RangeInclusive<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    let bounds: Option<[T; 2]> = Deserialize::deserialize(deserializer)?;
+    Ok(bounds.map(|[start, end]| start..=end))
+}
+
+fn option_duration_range_serialize_seconds<S>(
+    range: &Option<RangeInclusive<Duration>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    option_range_serialize(
+        &range
+            .as_ref()
+            .map(|range| range.start().as_secs_f64()..=range.end().as_secs_f64()),
+        serializer,
+    )
+}
+
+fn option_duration_range_deserialize_seconds<'de, D>(
+    deserializer: D,
+) -> Result<Option<RangeInclusive<Duration>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let range: Option<RangeInclusive<f64>> = option_range_deserialize(deserializer)?;
+    Ok(range.map(|range| {
+        Duration::from_secs_f64(*range.start())..=Duration::from_secs_f64(*range.end())
+    }))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchRunCfg {
+    pub machine: Option<String>,
+    /// At least 1, since with no measured runs there would be nothing to save.
+    pub repeat: NonZeroU8,
+    /// At least 1, since a bench that hasn't found its target yet is only run again
+    /// while it's been run fewer than this many times.
+    pub repeat_failed: NonZeroU8,
+    /// How many unmeasured passes to run over the benches before the measured ones.
+    #[serde(default = "default_warmup")]
+    pub warmup: u8,
+    /// How many times to run a search again after it gave an error, before keeping the error.
+    #[serde(default)]
+    pub retry_errors: u8,
+
+    #[serde(
+        serialize_with = "duration_serialize_seconds",
+        deserialize_with = "duration_deserialize_with_unit"
+    )]
+    pub timeout: Duration,
+
+    /// Multiplies the timeout, so a slow machine can be given more time to get the same verdicts
+    /// as a fast one. Best set from a calibration run on each machine.
+    #[serde(default = "default_timeout_scale")]
+    pub timeout_scale: f64,
+
+    /// How many words a search goes through between checks of the timeout.
+    #[serde(default = "default_timeout_check_interval")]
+    pub timeout_check_interval: u32,
+
+    /// Give up on a search after it goes through this many words past the first,
+    /// however fast it is.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_index: Option<usize>,
+
+    /// Like `max_index`, but as a fraction of the dictionary's words, so the cap scales with it.
+    /// When both are set, the smaller cap wins.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_index_fraction: Option<f64>,
+
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "option_duration_serialize_seconds",
+        deserialize_with = "option_duration_deserialize_with_unit"
+    )]
+    pub total_budget: Option<Duration>,
+
+    /// Record every word the search finds, but only for the start words in `trace_words`.
+    #[serde(default)]
+    pub trace: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trace_words: Vec<String>,
+
+    #[serde(default)]
+    pub verbose: u8,
+
+    #[serde(default = "default_add_unaccented")]
+    pub add_unaccented: bool,
+
+    #[serde(default)]
+    pub normalization: Normalization,
+
+    /// Record how much the resident memory grows during each search. Only supported on Linux.
+    #[serde(default)]
+    pub measure_memory: bool,
+
+    /// Start each search's clock, and its timeout, once the search is set up,
+    /// so that the times only measure finding words.
+    #[serde(default)]
+    pub exclude_setup: bool,
+
+    /// Match the words searches find to targets regardless of case,
+    /// so that a capitalized word like `Lisboa` finds the target `lisboa`.
+    #[serde(default)]
+    pub case_insensitive: bool,
+
+    /// Keep at most this many results per bench, as a uniform random sample of all its runs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_samples_per_bench: Option<usize>,
+
+    pub rules_file: Option<PathBuf>,
+    pub dict_file: Option<PathBuf>,
+    /// All the bench files to load benches from. Older configs give a single `bench_file`.
+    #[serde(
+        default,
+        alias = "bench_file",
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "option_paths_deserialize"
+    )]
+    pub bench_files: Option<Vec<PathBuf>>,
+
+    #[serde(default = "default_out_db")]
+    pub out_db: PathBuf,
+}
+
+fn default_out_db() -> PathBuf {
+    "bench.sqlite3".into()
+}
+
+fn default_warmup() -> u8 {
+    1
+}
+
+fn default_timeout_scale() -> f64 {
+    1.0
+}
+
+fn default_timeout_check_interval() -> u32 {
+    64
+}
+
+fn default_add_unaccented() -> bool {
+    true
+}
+
+/// Unicode normalization form to put the bench file's words in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    None,
+    #[default]
+    Nfc,
+    Nfd,
+}
+
+impl Normalization {
+    pub fn apply(self, text: &str) -> String {
+        match self {
+            Normalization::None => text.to_string(),
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfd => text.nfd().collect(),
+        }
+    }
+}
+
+impl BenchRunCfg {
+    /// Replaces the repeat and timeout settings with any given overrides.
+    pub fn apply_overrides(
+        &mut self,
+        repeat: Option<NonZeroU8>,
+        repeat_failed: Option<NonZeroU8>,
+        timeout: Option<Duration>,
+    ) {
+        if let Some(repeat) = repeat {
+            self.repeat = repeat;
+        }
+        if let Some(repeat_failed) = repeat_failed {
+            self.repeat_failed = repeat_failed;
+        }
+        if let Some(timeout) = timeout {
+            self.timeout = timeout;
+        }
+    }
+
+    /// The timeout each search actually gets: `timeout` times `timeout_scale`.
+    pub fn effective_timeout(&self) -> Duration {
+        self.timeout.mul_f64(self.timeout_scale)
+    }
+
+    /// The cap on how many words a search goes through, from `max_index` and
+    /// `max_index_fraction` of a dictionary of `dict_word_count` words, whichever is smaller.
+    pub fn effective_max_index(&self, dict_word_count: usize) -> Option<usize> {
+        let from_fraction = self
+            .max_index_fraction
+            .map(|fraction| (fraction * dict_word_count as f64).floor() as usize);
+        match (self.max_index, from_fraction) {
+            (Some(max_index), Some(from_fraction)) => Some(max_index.min(from_fraction)),
+            (max_index, from_fraction) => max_index.or(from_fraction),
+        }
+    }
+
+    /// Only tries each bench once, with no warmup, for smoke tests that don't need good timings.
+    pub fn apply_quick_mode(&mut self) {
+        self.warmup = 0;
+        self.repeat = NonZeroU8::MIN;
+        self.repeat_failed = NonZeroU8::MIN;
+    }
+
+    /// Whether to trace searches from `start_word`, which is normalized like the benches,
+    /// so `trace_words` are normalized the same way before comparing.
+    pub fn traces(&self, start_word: &str) -> bool {
+        self.trace
+            && self
+                .trace_words
+                .iter()
+                .any(|word| self.normalization.apply(word) == start_word)
+    }
+}
+
+/// Parses a positive number of seconds into a timeout.
+pub fn parse_timeout_secs(s: &str) -> Result<Duration, String> {
+    let seconds: f64 = s.parse().map_err(|err| format!("{}", err))?;
+    if seconds > 0.0 && seconds.is_finite() {
+        Ok(Duration::from_secs_f64(seconds))
+    } else {
+        Err("timeout must be a positive number of seconds".into())
+    }
+}
+
+/// Parses a duration from a number with a unit: `ms` for milliseconds, `s` for seconds
+/// or `m` for minutes, like `"500ms"` or `"2m"`. A number without a unit is in seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit_secs) = if let Some(number) = s.strip_suffix("ms") {
+        (number, 0.001)
+    } else if let Some(number) = s.strip_suffix('s') {
+        (number, 1.0)
+    } else if let Some(number) = s.strip_suffix('m') {
+        (number, 60.0)
+    } else {
+        (s, 1.0)
+    };
+    let number: f64 = number
+        .trim_end()
+        .parse()
+        .map_err(|_| format!("invalid duration {:?}: expected a number and ms, s or m", s))?;
+    Duration::try_from_secs_f64(number * unit_secs)
+        .map_err(|err| format!("invalid duration {:?}: {}", s, err))
+}
+
+/// Adds `item` to a reservoir sample of at most `max_len` items, which has already been
+/// offered `num_seen` items, so that every item offered has the same chance of being kept.
+/// With no `max_len`, keeps everything.
+pub fn reservoir_push<T, R: Rng + ?Sized>(
+    reservoir: &mut Vec<T>,
+    num_seen: usize,
+    max_len: Option<usize>,
+    item: T,
+    rng: &mut R,
+) {
+    match max_len {
+        Some(max_len) if reservoir.len() >= max_len => {
+            let i = rng.gen_range(0..=num_seen);
+            if i < max_len {
+                reservoir[i] = item;
+            }
+        }
+        _ => reservoir.push(item),
+    }
+}
+
+/// The results kept for a bench, which may only be a sample of all its runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BenchSamples {
+    results: Vec<BenchResult>,
+    /// How many results have been offered since the last clear, whether or not they were kept.
+    num_runs: usize,
+    /// How many of `num_runs` were failures kept from warmup, rather than from measured passes.
+    warmup_runs: usize,
+    /// Disabled benches are kept track of, but not run.
+    disabled: bool,
+}
+
+impl BenchSamples {
+    fn push(&mut self, result: BenchResult, max_samples: Option<usize>, rng: &mut impl Rng) {
+        reservoir_push(&mut self.results, self.num_runs, max_samples, result, rng);
+        self.num_runs += 1;
+    }
+
+    fn clear(&mut self) {
+        self.results.clear();
+        self.num_runs = 0;
+        self.warmup_runs = 0;
+    }
+
+    /// Whether any measured pass has run this bench, not counting what's left from warmup.
+    fn is_measured(&self) -> bool {
+        self.num_runs > self.warmup_runs
+    }
+
+    /// Whether the bench still needs running in a pass: either it's found its target before,
+    /// or it hasn't been run `repeat_failed` times yet.
+    fn needs_run(&self, repeat_failed: NonZeroU8) -> bool {
+        self.num_runs < repeat_failed.get() as usize
+            || self.results.iter().any(BenchResult::is_found)
+    }
+
+    fn count_errors(&self) -> usize {
+        self.results.iter().filter(|r| r.is_error()).count()
+    }
+
+    /// Takes out one of the errors, as if that run never happened, if there are any.
+    ///
+    /// This leaves room for the retry that takes the error's place, and doesn't skew the sampling:
+    /// the reservoir picks runs by their position, not their result, so the error's run was
+    /// already chosen to be kept, and its retry is kept in its place. Taking the run out of
+    /// `num_runs` means that once the retry is pushed, it counts as the same run,
+    /// so later runs are kept with the same chances as if the error had never happened.
+    fn take_error(&mut self) -> Option<BenchResult> {
+        let i = self.results.iter().position(BenchResult::is_error)?;
+        self.num_runs -= 1;
+        Some(self.results.remove(i))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Bencher {
+    benches: BTreeMap<String, BTreeMap<BTreeSet<String>, BenchSamples>>,
+    weights: BTreeMap<String, u32>,
+    sections: BTreeMap<String, String>,
+    traces: Vec<(String, Vec<String>)>,
+    found_all_at: BTreeMap<String, Vec<usize>>,
+}
+
+impl Bencher {
+    pub fn new() -> Bencher {
+        Bencher {
+            benches: BTreeMap::new(),
+            weights: BTreeMap::new(),
+            sections: BTreeMap::new(),
+            traces: Vec::new(),
+            found_all_at: BTreeMap::new(),
+        }
+    }
+
+    pub fn add_bench<'a>(&mut self, start_word: &'a str, targets: &[&'a str]) {
+        self.add_weighted_bench(start_word, targets, 1);
+    }
+
+    pub fn add_weighted_bench<'a>(
+        &mut self,
+        start_word: &'a str,
+        targets: &[&'a str],
+        weight: u32,
+    ) {
+        self.insert_bench(start_word, targets, weight, false, None);
+    }
+
+    /// Adds a bench that won't be run, like one commented out with `;;` in a bench file.
+    /// A bench stays disabled even if it's added again without being disabled.
+    pub fn add_disabled_bench<'a>(
+        &mut self,
+        start_word: &'a str,
+        targets: &[&'a str],
+        weight: u32,
+    ) {
+        self.insert_bench(start_word, targets, weight, true, None);
+    }
+
+    fn insert_bench(
+        &mut self,
+        start_word: &str,
+        targets: &[&str],
+        weight: u32,
+        disabled: bool,
+        section: Option<&str>,
+    ) {
+        let targets = BTreeSet::from_iter(targets.iter().map(|&s| String::from(s)));
+        let mut bench_name = String::new();
+        set_bench_name(
+            &mut bench_name,
+            start_word,
+            &targets,
+            &BenchNameStyle::DEFAULT,
+        );
+        match section {
+            Some(section) => self.sections.insert(bench_name.clone(), section.to_owned()),
+            None => self.sections.remove(&bench_name),
+        };
+        if weight == 1 {
+            self.weights.remove(&bench_name);
+        } else {
+            self.weights.insert(bench_name, weight);
+        }
+        self.benches
+            .entry(String::from(start_word))
+            .or_default()
+            .entry(targets)
+            .or_default()
+            .disabled |= disabled;
+    }
+
+    /// Weights of the benches whose weight isn't the default of 1, by bench name.
+    pub fn get_weights(&self) -> &BTreeMap<String, u32> {
+        &self.weights
+    }
+
+    /// Sections of the benches that come after a `[section]` line in their bench file, by bench name.
+    pub fn get_sections(&self) -> &BTreeMap<String, String> {
+        &self.sections
+    }
+
+    /// Loads benches from a bench file, with all its words put in the given normalization form.
+    /// If `add_unaccented` is set, start words with accents also get benches for their
+    /// unaccented versions.
+    pub fn load_benches<R: BufRead>(
+        &mut self,
+        input: R,
+        add_unaccented: bool,
+        normalization: Normalization,
+    ) -> Result<(), BenchError> {
+        let mut unaccented = String::new();
+        let mut section = None;
+        for (line_no, line) in input.lines().enumerate() {
+            let line = line?;
+            let mut line = line.strip_suffix('\r').unwrap_or(&line);
+            if line_no == 0 {
+                line = line.strip_prefix('\u{feff}').unwrap_or(line);
+            }
+            let line = normalization.apply(line);
+            if let Ok((_, name)) = benchfile::section_header(&line) {
+                section = Some(name.to_owned());
+                continue;
+            }
+            match benchfile::bench_line(&line).finish() {
+                Ok((_, Some(bench))) => {
+                    let reversed = bench.reversed();
+                    for bench in [Some(bench), reversed].into_iter().flatten() {
+                        for start_word in bench.start_words {
+                            set_unaccented(start_word, &mut unaccented);
+                            for targets in &bench.targets {
+                                let disabled = !bench.enabled;
+                                let section = section.as_deref();
+                                self.insert_bench(
+                                    start_word,
+                                    targets,
+                                    bench.weight,
+                                    disabled,
+                                    section,
+                                );
+                                if add_unaccented && unaccented != start_word {
+                                    self.insert_bench(
+                                        &unaccented,
+                                        targets,
+                                        bench.weight,
+                                        disabled,
+                                        section,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                Ok((_, None)) => Ok(()),
+                Err(parse_err) => Err(ParseErr {
+                    line_no: line_no + 1,
+                    text: parse_err.input.to_owned(),
                 }),
             }?;
         }
         Ok(())
     }
 
-    pub fn clear_results(&mut self) {
-        for bench_map in self.benches.values_mut() {
-            bench_map.values_mut().for_each(Vec::clear);
-        }
+    /// Loads benches from the text of a bench file, with the default settings:
+    /// unaccented start words added, and NFC normalization.
+    pub fn load_benches_str(&mut self, s: &str) -> Result<(), BenchError> {
+        self.load_benches(s.as_bytes(), true, Normalization::default())
+    }
+
+    /// Keeps only the benches whose names pass `keep`, dropping any start words left with none.
+    pub fn retain_benches(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        let mut bench_name = String::new();
+        for (start_word, bench_map) in self.benches.iter_mut() {
+            bench_map.retain(|targets, _| {
+                set_bench_name(
+                    &mut bench_name,
+                    start_word,
+                    targets,
+                    &BenchNameStyle::DEFAULT,
+                );
+                keep(&bench_name)
+            });
+        }
+        self.benches.retain(|_, bench_map| !bench_map.is_empty());
+        self.weights.retain(|bench_name, _| keep(bench_name));
+        self.sections.retain(|bench_name, _| keep(bench_name));
+    }
+
+    pub fn clear_results(&mut self) {
+        for bench_map in self.benches.values_mut() {
+            bench_map.values_mut().for_each(BenchSamples::clear);
+        }
+    }
+
+    pub fn clear_successes(&mut self) {
+        for bench_map in self.benches.values_mut() {
+            for samples in bench_map.values_mut() {
+                let bench_vec = &mut samples.results;
+                let mut i = 0;
+                while i < bench_vec.len() {
+                    if bench_vec[i].is_found() {
+                        bench_vec.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+                samples.num_runs = bench_vec.len();
+            }
+        }
+    }
+
+    /// Drops the results that were errors, keeping the ones that found their target or didn't,
+    /// so the errors can be run again, such as after fixing what caused them.
+    pub fn clear_errors(&mut self) {
+        for bench_map in self.benches.values_mut() {
+            for samples in bench_map.values_mut() {
+                let bench_vec = &mut samples.results;
+                let mut i = 0;
+                while i < bench_vec.len() {
+                    if bench_vec[i].is_error() {
+                        bench_vec.remove(i);
+                    } else {
+                        i += 1;
+                    }
+                }
+                samples.num_runs = bench_vec.len();
+            }
+        }
+    }
+
+    /// Runs the warmup passes and then all the measured runs, stopping early between words
+    /// if `run_cfg.total_budget` is used up.
+    pub fn run_benches(
+        &mut self,
+        search_cfg: &impl WordSearch,
+        run_cfg: &BenchRunCfg,
+    ) -> RunSummary {
+        self.run_benches_observed(search_cfg, run_cfg, &mut LogProgress::default())
+    }
+
+    /// Like [`Bencher::run_benches`], but tells `observer` how far along the measured runs are.
+    pub fn run_benches_observed(
+        &mut self,
+        search_cfg: &impl WordSearch,
+        run_cfg: &BenchRunCfg,
+        observer: &mut dyn RunObserver,
+    ) -> RunSummary {
+        self.run_benches_timed(search_cfg, run_cfg, observer, &SystemClock)
+    }
+
+    /// Like [`Bencher::run_benches_observed`], but timing everything by `clock`.
+    pub fn run_benches_timed(
+        &mut self,
+        search_cfg: &impl WordSearch,
+        run_cfg: &BenchRunCfg,
+        observer: &mut dyn RunObserver,
+        clock: &impl Clock,
+    ) -> RunSummary {
+        let mut rng = thread_rng();
+        let mut start_words: Vec<String> = self.benches.keys().cloned().collect();
+        let num_to_do = start_words.len() as u32 * (run_cfg.repeat.get() as u32);
+        let mut num_complete: u32 = 0;
+        let run_start = clock.now();
+        let over_budget = || is_over_budget(run_cfg.total_budget, clock.elapsed_since(run_start));
+
+        'warmup: for pass in 0..run_cfg.warmup {
+            debug!("warmup run {}", pass + 1);
+            start_words.shuffle(&mut rng);
+            for word in &start_words {
+                if over_budget() {
+                    break 'warmup;
+                }
+                self.run_benches_for_word(
+                    search_cfg,
+                    run_cfg,
+                    clock,
+                    word,
+                    &mut rng,
+                    |_, samples| samples.needs_run(run_cfg.repeat_failed),
+                );
+            }
+        }
+        self.clear_successes();
+        for samples in self.benches.values_mut().flat_map(BTreeMap::values_mut) {
+            samples.warmup_runs = samples.num_runs;
+        }
+
+        let mut budget_exceeded = false;
+        observer.start(num_to_do);
+        'repeats: for _ in 0..run_cfg.repeat.get() {
+            start_words.shuffle(&mut rng);
+            for word in &start_words {
+                if over_budget() {
+                    budget_exceeded = true;
+                    break 'repeats;
+                }
+                self.run_benches_for_word(
+                    search_cfg,
+                    run_cfg,
+                    clock,
+                    word,
+                    &mut rng,
+                    |_, samples| samples.needs_run(run_cfg.repeat_failed),
+                );
+                num_complete += 1;
+                observer.word_done(num_complete);
+            }
+        }
+        observer.finish();
+
+        'retries: for retry in 0..run_cfg.retry_errors {
+            let start_words = self.start_words_with_errors();
+            if start_words.is_empty() {
+                break;
+            }
+            debug!(
+                "retry {} of errors from {} start words",
+                retry + 1,
+                start_words.len()
+            );
+            for word in &start_words {
+                if !self.retry_errors_for_word(
+                    search_cfg,
+                    run_cfg,
+                    clock,
+                    word,
+                    &mut rng,
+                    &over_budget,
+                ) {
+                    budget_exceeded = true;
+                    break 'retries;
+                }
+            }
+        }
+
+        if budget_exceeded {
+            warn!(
+                "Total budget exceeded after {}/{} runs",
+                num_complete, num_to_do
+            );
+        }
+        RunSummary {
+            budget_exceeded,
+            benches_measured: self.count_measured_benches(),
+        }
+    }
+
+    /// Counts the benches that will be run, not including disabled ones.
+    pub fn num_benches(&self) -> usize {
+        self.benches
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter(|samples| !samples.disabled)
+            .count()
+    }
+
+    /// Keeps only the benches that failed in `session`, given their names in `failed`,
+    /// and fails with `NoFailedBenches` if that leaves none to run.
+    pub fn retain_failed(
+        &mut self,
+        failed: &BTreeSet<String>,
+        session: BenchSessionId,
+    ) -> Result<(), BenchError> {
+        self.retain_benches(|bench| failed.contains(bench));
+        self.check_not_empty()
+            .map_err(|_| NoFailedBenches { session })
+    }
+
+    /// Fails with `NoBenches` if there are no benches to run, such as from an empty benchfile.
+    pub fn check_not_empty(&self) -> Result<(), BenchError> {
+        if self.num_benches() == 0 {
+            Err(NoBenches)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Counts the benches that are disabled, and so won't be run.
+    pub fn disabled_count(&self) -> usize {
+        self.benches
+            .values()
+            .flat_map(BTreeMap::values)
+            .filter(|samples| samples.disabled)
+            .count()
+    }
+
+    /// Returns the names of the benches that can never succeed, because none of their targets
+    /// are in, or match a word in, the dictionary.
+    /// With `case_insensitive`, they're compared regardless of case, like the searches do.
+    pub fn check_targets(
+        &self,
+        dict_words: &BTreeSet<String>,
+        case_insensitive: bool,
+    ) -> Vec<String> {
+        let folded_words: BTreeSet<String>;
+        let dict_words = if case_insensitive {
+            folded_words = dict_words
+                .iter()
+                .map(|word| fold_case(word).into_owned())
+                .collect();
+            &folded_words
+        } else {
+            dict_words
+        };
+        let reachable = |target: &String| {
+            let target = if case_insensitive {
+                fold_case(target)
+            } else {
+                Cow::Borrowed(target.as_str())
+            };
+            match TargetPattern::parse(&target) {
+                Some(pattern) => dict_words.iter().any(|word| pattern.matches(word)),
+                None => dict_words.contains(target.as_ref()),
+            }
+        };
+        let mut unreachable = Vec::new();
+        for (start_word, bench_map) in &self.benches {
+            for (targets, samples) in bench_map {
+                if !samples.disabled && !targets.iter().any(reachable) {
+                    unreachable.push(BenchKey::new(start_word, targets).name());
+                }
+            }
+        }
+        unreachable
+    }
+
+    /// Counts the benches that measured passes have run, not counting failures kept from warmup.
+    pub fn count_measured_benches(&self) -> usize {
+        self.benches
+            .values()
+            .flat_map(|bench_map| bench_map.values())
+            .filter(|samples| samples.is_measured())
+            .count()
+    }
+
+    /// The start words with benches whose results include errors, to run again.
+    /// Searches that just didn't find their targets don't count.
+    fn start_words_with_errors(&self) -> Vec<String> {
+        self.benches
+            .iter()
+            .filter(|(_, bench_map)| {
+                bench_map
+                    .values()
+                    .any(|samples| !samples.disabled && samples.count_errors() > 0)
+            })
+            .map(|(start_word, _)| start_word.clone())
+            .collect()
+    }
+
+    /// Runs the start word's search again once for each error in its benches' results,
+    /// replacing the error with the new result, which may be another error.
+    /// Returns false if it stopped early because the run is over budget.
+    fn retry_errors_for_word(
+        &mut self,
+        cfg: &impl WordSearch,
+        run_cfg: &BenchRunCfg,
+        clock: &impl Clock,
+        start_word: &str,
+        rng: &mut impl Rng,
+        over_budget: &impl Fn() -> bool,
+    ) -> bool {
+        let mut to_retry: BTreeMap<BTreeSet<String>, usize> = self.benches[start_word]
+            .iter()
+            .filter(|(_, samples)| !samples.disabled)
+            .map(|(targets, samples)| (targets.clone(), samples.count_errors()))
+            .filter(|&(_, num_errors)| num_errors > 0)
+            .collect();
+        while !to_retry.is_empty() {
+            if over_budget() {
+                return false;
+            }
+            let bench_map = self.benches.get_mut(start_word).unwrap();
+            for targets in to_retry.keys() {
+                bench_map.get_mut(targets).unwrap().take_error();
+            }
+            self.run_benches_for_word(cfg, run_cfg, clock, start_word, rng, |targets, _| {
+                to_retry.contains_key(targets)
+            });
+            to_retry.retain(|_, num_errors| {
+                *num_errors -= 1;
+                *num_errors > 0
+            });
+        }
+        true
+    }
+
+    /// Runs the start word's search for its benches that pass `select`.
+    fn run_benches_for_word(
+        &mut self,
+        cfg: &impl WordSearch,
+        run_cfg: &BenchRunCfg,
+        clock: &impl Clock,
+        start_word: &str,
+        rng: &mut impl Rng,
+        select: impl Fn(&BTreeSet<String>, &BenchSamples) -> bool,
+    ) {
+        let benches = self.benches.get_mut(start_word).unwrap();
+        let max_samples = run_cfg.max_samples_per_bench;
+        let mut runner = BenchRunner::new().with_case_insensitive(run_cfg.case_insensitive);
+        for (targets, samples) in benches.iter() {
+            if !samples.disabled && select(targets, samples) {
+                runner.add_targets(targets);
+            }
+        }
+        if runner.is_done() {
+            debug!("Skipping {}", start_word);
+            return;
+        }
+        runner.shuffle(rng);
+
+        let mut trace = run_cfg.traces(start_word).then(Vec::new);
+        let rss = if run_cfg.measure_memory {
+            RssSampler::new()
+        } else {
+            None
+        };
+        let search_start = clock.now();
+        match cfg.search(start_word) {
+            Ok(words) => {
+                let setup_end = clock.now();
+                let setup = Some(setup_end - search_start);
+                let start_time = timing_start(run_cfg, search_start, setup_end);
+                let mut limits = SearchLimits::new(run_cfg, start_time);
+                // All the targets found by the same word get the same time
+                let mut last_hit: Option<(usize, Duration)> = None;
+                // In a `Cell`, since both the word and target hit callbacks use it
+                let first_result: Cell<Option<Duration>> = Cell::new(None);
+                let (end, num_words) = run_search(
+                    &mut runner,
+                    words,
+                    &mut limits,
+                    || {
+                        if let Some(rss) = &rss {
+                            rss.sample();
+                        }
+                        clock.now()
+                    },
+                    |word| {
+                        if first_result.get().is_none() {
+                            first_result.set(Some(clock.elapsed_since(start_time)));
+                        }
+                        if let Some(trace) = &mut trace {
+                            trace.push(word.to_string());
+                        }
+                    },
+                    |target, word_idx, word| {
+                        let elapsed = match last_hit {
+                            Some((idx, elapsed)) if idx == word_idx => elapsed,
+                            _ => clock.elapsed_since(start_time),
+                        };
+                        last_hit = Some((word_idx, elapsed));
+                        benches.get_mut(target).unwrap().push(
+                            BenchResult::success(word_idx, elapsed)
+                                .with_peak_rss(rss.as_ref().map(RssSampler::growth))
+                                .with_found_word(Some(word.to_string()))
+                                .with_setup(setup)
+                                .with_first_result(first_result.get()),
+                            max_samples,
+                            rng,
+                        )
+                    },
+                );
+                let stopped_by = match end {
+                    SearchEnd::Completed => {
+                        debug!(
+                            "Found all targets of {} after {} words",
+                            start_word, num_words
+                        );
+                        self.found_all_at
+                            .entry(start_word.to_string())
+                            .or_default()
+                            .push(num_words);
+                        None
+                    }
+                    SearchEnd::Stopped(limit) => Some(limit),
+                    SearchEnd::Exhausted => None,
+                };
+
+                let elapsed = clock.elapsed_since(start_time);
+                if let Some(rss) = &rss {
+                    rss.sample();
+                }
+                for target in &runner.remaining_targets {
+                    benches.get_mut(target).unwrap().push(
+                        BenchResult {
+                            elapsed,
+                            found_index: Ok(None),
+                            stopped_by,
+                            peak_rss: rss.as_ref().map(RssSampler::growth),
+                            found_word: None,
+                            setup,
+                            first_result: first_result.get(),
+                        },
+                        max_samples,
+                        rng,
+                    );
+                }
+            }
+            Err(err) => {
+                let elapsed = clock.elapsed_since(search_start);
+                for target in &runner.remaining_targets {
+                    benches.get_mut(target).unwrap().push(
+                        BenchResult {
+                            elapsed,
+                            found_index: Err(err.clone()),
+                            stopped_by: None,
+                            peak_rss: None,
+                            found_word: None,
+                            setup: None,
+                            first_result: None,
+                        },
+                        max_samples,
+                        rng,
+                    );
+                }
+            }
+        }
+        if let Some(trace) = trace {
+            self.traces.push((start_word.to_string(), trace));
+        }
+    }
+
+    /// For each start word, how many words each of its searches went through before finding
+    /// all its targets. Searches that timed out or ran out of words aren't included.
+    pub fn get_found_all_at(&self) -> &BTreeMap<String, Vec<usize>> {
+        &self.found_all_at
+    }
+
+    /// The traced runs, in the order they ran, as each start word and the words its search found.
+    pub fn get_traces(&self) -> &[(String, Vec<String>)] {
+        &self.traces
+    }
+
+    pub fn get_results(&self) -> Vec<(BenchKey, BenchResult)> {
+        let mut results = Vec::new();
+        for (start_word, benches) in &self.benches {
+            for (targets, samples) in benches {
+                let key = BenchKey::new(start_word, targets);
+                for result in &samples.results {
+                    results.push((key.clone(), result.clone()));
+                }
+            }
+        }
+        results
+    }
+
+    /// Adds the results, bench sections and traces of the benches that have been run to a session.
+    pub fn store_results(
+        &self,
+        db: &mut BenchDb,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<()> {
+        let mut result_runs: BTreeMap<String, usize> = BTreeMap::new();
+        for (bench, result) in self.get_results() {
+            let run = result_runs.entry(bench.name()).or_default();
+            db.add_run(session_id, &bench, *run, result)?;
+            *run += 1;
+        }
+        for (bench, section) in &self.sections {
+            db.set_section(session_id, bench, section)?;
+        }
+        let mut trace_runs: BTreeMap<&str, usize> = BTreeMap::new();
+        for (start_word, words) in &self.traces {
+            let run = trace_runs.entry(start_word).or_default();
+            db.add_trace(session_id, start_word, *run, words)?;
+            *run += 1;
+        }
+        if !self.found_all_at.is_empty() {
+            db.set_info(
+                session_id,
+                FOUND_ALL_AT_INFO,
+                &serde_json::to_string(&self.found_all_at).expect("Error serializing found_all_at"),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Says when it's time to do something that only needs doing every so many steps.
+#[derive(Debug, Clone)]
+pub struct PeriodicCheck {
+    interval: u32,
+    count: u32,
+}
+
+impl PeriodicCheck {
+    /// Will be due every `interval` steps. An interval of 0 is treated as 1.
+    pub fn new(interval: u32) -> PeriodicCheck {
+        PeriodicCheck {
+            interval: interval.max(1),
+            count: 0,
+        }
+    }
+
+    /// Counts a step, and returns whether a check is due.
+    pub fn is_due(&mut self) -> bool {
+        self.count += 1;
+        if self.count >= self.interval {
+            self.count = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// When a search's clock starts: when it started being set up, or with `exclude_setup`,
+/// when it was set up and ready to find words.
+fn timing_start(run_cfg: &BenchRunCfg, search_start: Instant, setup_end: Instant) -> Instant {
+    if run_cfg.exclude_setup {
+        setup_end
+    } else {
+        search_start
+    }
+}
+
+/// Checks whether a search has hit one of the limits in a [`BenchRunCfg`].
+#[derive(Debug, Clone)]
+pub struct SearchLimits {
+    /// When the timeout runs out, or `None` if that's too far off to represent.
+    deadline: Option<Instant>,
+    timeout_check: PeriodicCheck,
+    max_index: Option<usize>,
+}
+
+impl SearchLimits {
+    /// Limits for a search that started at `start_time`.
+    pub fn new(run_cfg: &BenchRunCfg, start_time: Instant) -> SearchLimits {
+        SearchLimits {
+            deadline: start_time.checked_add(run_cfg.effective_timeout()),
+            timeout_check: PeriodicCheck::new(run_cfg.timeout_check_interval),
+            max_index: run_cfg.max_index,
+        }
+    }
+
+    /// Called after each step of a search, having gone through `num_words` words.
+    /// Only calls `now` every so often, when it's time to check the timeout.
+    pub fn check(
+        &mut self,
+        num_words: usize,
+        now: impl FnOnce() -> Instant,
+    ) -> Option<SearchLimit> {
+        if self
+            .max_index
+            .is_some_and(|max_index| num_words > max_index)
+        {
+            Some(SearchLimit::MaxIndex)
+        } else if self.timeout_check.is_due()
+            && self.deadline.is_some_and(|deadline| now() >= deadline)
+        {
+            Some(SearchLimit::Timeout)
+        } else {
+            None
+        }
+    }
+}
+
+/// Why a search stopped going through its words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchEnd {
+    /// All the targets were found.
+    Completed,
+    /// One of the limits stopped it first.
+    Stopped(SearchLimit),
+    /// The search ran out of words first.
+    Exhausted,
+}
+
+/// Goes through a search's words until the runner has found all its targets, the words run out,
+/// or one of the limits stops it. Calls `on_word` with each word, and `on_target_hit` with each
+/// set of targets as it's found, along with the index of the word that found it.
+/// Returns why it stopped, and how many words it went through.
+fn run_search<'w>(
+    runner: &mut BenchRunner,
+    mut words: impl Iterator<Item = Option<&'w str>>,
+    limits: &mut SearchLimits,
+    mut now: impl FnMut() -> Instant,
+    mut on_word: impl FnMut(&str),
+    mut on_target_hit: impl FnMut(&BTreeSet<String>, usize, &str),
+) -> (SearchEnd, usize) {
+    let mut word_idx = 0;
+    loop {
+        if runner.is_done() {
+            return (SearchEnd::Completed, word_idx);
+        }
+        match words.next() {
+            Some(Some(word)) => {
+                on_word(word);
+                runner.on_word_found(word, |target| on_target_hit(target, word_idx, word));
+                word_idx += 1;
+            }
+            Some(None) => {}
+            None => return (SearchEnd::Exhausted, word_idx),
+        }
+        if !runner.is_done() {
+            if let Some(limit) = limits.check(word_idx, &mut now) {
+                return (SearchEnd::Stopped(limit), word_idx);
+            }
+        }
+    }
+}
+
+/// The process's resident memory in bytes, on platforms where that's easy to read.
+pub fn current_rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        let kib: u64 = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse()
+            .ok()?;
+        Some(kib * 1024)
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Keeps track of the highest resident memory seen since it started.
+#[derive(Debug, Clone)]
+struct RssSampler {
+    start: u64,
+    /// In a `Cell`, so that a search can sample it while its results read it.
+    peak: Cell<u64>,
+}
+
+impl RssSampler {
+    /// Starts from the current memory use, or gives `None` if it can't be read.
+    fn new() -> Option<RssSampler> {
+        current_rss_bytes().map(|start| RssSampler {
+            start,
+            peak: Cell::new(start),
+        })
+    }
+
+    fn sample(&self) {
+        if let Some(rss) = current_rss_bytes() {
+            self.peak.set(max(self.peak.get(), rss));
+        }
+    }
+
+    /// How much the memory grew from the start to the peak.
+    fn growth(&self) -> u64 {
+        self.peak.get() - self.start
+    }
+}
+
+/// Gets told how far along the measured runs of [`Bencher::run_benches_observed`] are,
+/// such as to show a progress bar.
+pub trait RunObserver {
+    /// Called before the measured runs, with how many start words they'll go through in all.
+    fn start(&mut self, num_to_do: u32);
+
+    /// Called after each start word's measured run, with how many are done so far.
+    fn word_done(&mut self, num_complete: u32);
+
+    /// Called after the measured runs, even if the total budget stopped them early.
+    fn finish(&mut self) {}
+}
+
+/// Logs how far along the runs are as `(done/total)` lines, at debug level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogProgress {
+    num_to_do: u32,
+}
+
+impl RunObserver for LogProgress {
+    fn start(&mut self, num_to_do: u32) {
+        self.num_to_do = num_to_do;
+        debug!("(0/{})", num_to_do);
     }
 
-    pub fn clear_successes(&mut self) {
-        for bench_map in self.benches.values_mut() {
-            for bench_vec in bench_map.values_mut() {
-                let mut i = 0;
-                while i < bench_vec.len() {
-                    if bench_vec[i].is_found() {
-                        bench_vec.remove(i);
-                    } else {
-                        i += 1;
-                    }
-                }
-            }
-        }
+    fn word_done(&mut self, num_complete: u32) {
+        debug!("({}/{})", num_complete, self.num_to_do);
     }
+}
 
-    pub fn run_benches(&mut self, search_cfg: &BuscaCfg, run_cfg: &BenchRunCfg) {
-        let mut rng = thread_rng();
-        let mut start_words: Vec<String> = self.benches.keys().cloned().collect();
-        let num_to_do = start_words.len() as u32 * (run_cfg.repeat as u32);
-        let mut num_complete: u32 = 0;
+/// The progress bar's layout, in `indicatif`'s template format.
+const PROGRESS_TEMPLATE: &str = "{wide_bar} {pos}/{len} words, {elapsed} elapsed, ETA {eta}";
 
-        if run_cfg.verbose > 1 {
-            eprintln!("warmup run");
-        }
-        start_words.shuffle(&mut rng);
-        for word in &start_words {
-            self.run_benches_for_word(search_cfg, run_cfg, word);
-        }
-        self.clear_successes();
+/// Shows how far along the measured runs are as a progress bar, on standard error by default.
+#[derive(Debug, Default)]
+pub struct ProgressBarObserver {
+    bar: Option<ProgressBar>,
+    /// Where the bar gets drawn when the runs start, if not standard error.
+    draw_target: Option<ProgressDrawTarget>,
+}
 
-        if run_cfg.verbose > 1 {
-            eprintln!("(0/{})", num_to_do);
-        }
-        for _ in 0..run_cfg.repeat {
-            start_words.shuffle(&mut rng);
-            for word in &start_words {
-                self.run_benches_for_word(search_cfg, run_cfg, word);
-                num_complete += 1;
-                if run_cfg.verbose > 1 {
-                    eprintln!("({}/{})", num_complete, num_to_do);
-                }
-            }
+impl ProgressBarObserver {
+    pub fn with_draw_target(draw_target: ProgressDrawTarget) -> ProgressBarObserver {
+        ProgressBarObserver {
+            bar: None,
+            draw_target: Some(draw_target),
         }
     }
+}
 
-    fn run_benches_for_word(&mut self, cfg: &BuscaCfg, run_cfg: &BenchRunCfg, start_word: &str) {
-        let benches = self.benches.get_mut(start_word).unwrap();
-        let mut runner = BenchRunner::new();
-        for (targets, results) in benches.iter() {
-            if results.len() < run_cfg.repeat_failed as usize
-                || results.iter().any(BenchResult::is_found)
-            {
-                runner.add_targets(targets);
-            }
-        }
-        if runner.is_done() {
-            if run_cfg.verbose > 1 {
-                eprintln!("Skipping {}", start_word);
-            }
-            return;
-        }
-
-        let start_time = Instant::now();
-        match cfg.search(start_word) {
-            Ok(mut iter) => {
-                let mut iter = iter.iter();
-                let mut word_idx = 0;
-                while !runner.is_done() {
-                    match iter.next() {
-                        Some(Some((word, _))) => {
-                            let elapsed = start_time.elapsed();
-                            runner.on_word_found(word, |target| {
-                                benches
-                                    .get_mut(target)
-                                    .unwrap()
-                                    .push(BenchResult::success(word_idx, elapsed))
-                            });
-                            word_idx += 1;
-                        }
-                        Some(None) => {}
-                        None => break,
-                    }
-
-                    if start_time.elapsed() >= run_cfg.timeout {
-                        break;
-                    }
-                }
+impl RunObserver for ProgressBarObserver {
+    fn start(&mut self, num_to_do: u32) {
+        let style = ProgressStyle::with_template(PROGRESS_TEMPLATE)
+            .expect("Error in progress bar template");
+        let draw_target = self
+            .draw_target
+            .take()
+            .unwrap_or_else(ProgressDrawTarget::stderr);
+        self.bar = Some(
+            ProgressBar::with_draw_target(Some(num_to_do.into()), draw_target).with_style(style),
+        );
+    }
 
-                let elapsed = start_time.elapsed();
-                for target in &runner.remaining_targets {
-                    benches.get_mut(target).unwrap().push(BenchResult {
-                        elapsed,
-                        found_index: Ok(None),
-                    });
-                }
-            }
-            Err(err) => {
-                let elapsed = start_time.elapsed();
-                for result_vec in benches.values_mut() {
-                    result_vec.push(BenchResult {
-                        elapsed,
-                        found_index: Err(err.to_string()),
-                    });
-                }
-            }
+    fn word_done(&mut self, num_complete: u32) {
+        if let Some(bar) = &self.bar {
+            bar.set_position(num_complete.into());
         }
     }
 
-    pub fn get_results(&self) -> Vec<(String, BenchResult)> {
-        let mut results = Vec::new();
-        let mut bench_name = String::new();
-        for (start_word, benches) in &self.benches {
-            for (targets, run_results) in benches {
-                set_bench_name(&mut bench_name, start_word, targets);
-                for result in run_results {
-                    results.push((bench_name.clone(), result.clone()));
-                }
-            }
+    fn finish(&mut self) {
+        if let Some(bar) = self.bar.take() {
+            bar.finish();
         }
-        results
     }
 }
 
+/// What happened during [`Bencher::run_benches`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunSummary {
+    pub budget_exceeded: bool,
+    pub benches_measured: usize,
+}
+
+/// Whether a run that has taken `elapsed` so far has used up its total budget, if any.
+pub fn is_over_budget(budget: Option<Duration>, elapsed: Duration) -> bool {
+    budget.is_some_and(|budget| elapsed >= budget)
+}
+
 impl Default for Bencher {
     fn default() -> Self {
         Self::new()
@@ -428,8 +2315,12 @@ impl Default for Bencher {
 
 #[derive(Debug, Clone)]
 struct BenchRunner {
+    /// The targets as written, even when matching regardless of case.
     remaining_targets: Vec<BTreeSet<String>>,
+    /// With `case_insensitive`, these are lowercased, like the words they're checked against.
     all_target_words: BTreeSet<String>,
+    all_target_patterns: Vec<TargetPattern>,
+    case_insensitive: bool,
 }
 
 impl BenchRunner {
@@ -437,13 +2328,37 @@ impl BenchRunner {
         BenchRunner {
             remaining_targets: Vec::new(),
             all_target_words: BTreeSet::new(),
+            all_target_patterns: Vec::new(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Matches words to targets regardless of case. Has to be set before adding targets.
+    fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        debug_assert!(self.remaining_targets.is_empty());
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// The word as it's compared to targets: lowercased if matching regardless of case.
+    fn fold<'w>(&self, word: &'w str) -> Cow<'w, str> {
+        if self.case_insensitive {
+            fold_case(word)
+        } else {
+            Cow::Borrowed(word)
         }
     }
 
     fn add_targets(&mut self, targets: &BTreeSet<String>) {
         self.remaining_targets.push(targets.clone());
         for word in targets {
-            self.all_target_words.insert(word.clone());
+            let word = self.fold(word);
+            match TargetPattern::parse(&word) {
+                Some(pattern) => self.all_target_patterns.push(pattern),
+                None => {
+                    self.all_target_words.insert(word.into_owned());
+                }
+            }
         }
     }
 
@@ -451,11 +2366,31 @@ impl BenchRunner {
         self.remaining_targets.is_empty()
     }
 
+    /// Puts the remaining targets in a random order, so that no bench is always checked first.
+    fn shuffle(&mut self, rng: &mut impl Rng) {
+        self.remaining_targets.shuffle(rng);
+    }
+
+    /// Exact target words are checked with a single lookup,
+    /// but every pattern has to be checked against every word found,
+    /// so benches with patterns slow down the search loop for their start word.
     fn on_word_found(&mut self, word: &str, mut on_target_hit: impl FnMut(&BTreeSet<String>)) {
-        if self.all_target_words.contains(word) {
+        let word = self.fold(word);
+        let word = word.as_ref();
+        if self.all_target_words.contains(word)
+            || self.all_target_patterns.iter().any(|p| p.matches(word))
+        {
             let mut target_idx = 0;
             while target_idx < self.remaining_targets.len() {
-                if self.remaining_targets[target_idx].contains(word) {
+                let targets = &self.remaining_targets[target_idx];
+                let is_hit = if self.case_insensitive {
+                    targets
+                        .iter()
+                        .any(|target| target_option_matches(&fold_case(target), word))
+                } else {
+                    targets_match(targets, word)
+                };
+                if is_hit {
                     let target = self.remaining_targets.swap_remove(target_idx);
                     on_target_hit(&target);
                 } else {
@@ -466,14 +2401,151 @@ impl BenchRunner {
     }
 }
 
-fn set_bench_name<S: AsRef<str>>(bench_name: &mut String, start_word: &str, targets: &BTreeSet<S>) {
+/// A target that matches any word with a given prefix (`casa*`) or suffix (`*inho`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TargetPattern {
+    Prefix(String),
+    Suffix(String),
+}
+
+impl TargetPattern {
+    fn parse(target: &str) -> Option<TargetPattern> {
+        if let Some(suffix) = target.strip_prefix('*') {
+            Some(TargetPattern::Suffix(suffix.to_string()))
+        } else {
+            target
+                .strip_suffix('*')
+                .map(|prefix| TargetPattern::Prefix(prefix.to_string()))
+        }
+    }
+
+    fn matches(&self, word: &str) -> bool {
+        match self {
+            TargetPattern::Prefix(prefix) => word.starts_with(prefix.as_str()),
+            TargetPattern::Suffix(suffix) => word.ends_with(suffix.as_str()),
+        }
+    }
+}
+
+fn targets_match(targets: &BTreeSet<String>, word: &str) -> bool {
+    targets.contains(word)
+        || targets
+            .iter()
+            .filter_map(|target| TargetPattern::parse(target))
+            .any(|pattern| pattern.matches(word))
+}
+
+/// Lowercases a word to compare it regardless of case, without copying it if it's already lowercase.
+fn fold_case(word: &str) -> Cow<'_, str> {
+    if word.chars().any(|c| !c.to_lowercase().eq([c])) {
+        Cow::Owned(word.to_lowercase())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// Whether a found word matches one of a bench's target options:
+/// the same word, or a word with the prefix or suffix of a pattern like `casa*` or `*inho`.
+pub fn target_option_matches(option: &str, word: &str) -> bool {
+    match TargetPattern::parse(option) {
+        Some(pattern) => pattern.matches(word),
+        None => option == word,
+    }
+}
+
+/// Like [`target_option_matches`], but regardless of case, for sessions run with `case_insensitive`.
+pub fn target_option_matches_any_case(option: &str, word: &str) -> bool {
+    target_option_matches(&fold_case(option), &fold_case(word))
+}
+
+/// Identifies a bench by its start word and set of targets.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BenchKey {
+    pub start_word: String,
+    pub targets: BTreeSet<String>,
+}
+
+impl BenchKey {
+    pub fn new(start_word: &str, targets: &BTreeSet<String>) -> BenchKey {
+        BenchKey {
+            start_word: start_word.to_string(),
+            targets: targets.clone(),
+        }
+    }
+
+    /// The bench's display name, like `"start = target1 | target2"`.
+    pub fn name(&self) -> String {
+        self.name_with_style(&BenchNameStyle::DEFAULT)
+    }
+
+    /// The bench's name, with the separators from `style`.
+    pub fn name_with_style(&self, style: &BenchNameStyle) -> String {
+        let mut name = String::new();
+        set_bench_name(&mut name, &self.start_word, &self.targets, style);
+        name
+    }
+
+    /// Reverses [`BenchKey::name`]. Only reliable if none of the words contain `" = "` or `" | "`.
+    pub fn from_name(name: &str) -> Option<BenchKey> {
+        BenchKey::from_name_with_style(name, &BenchNameStyle::DEFAULT)
+    }
+
+    /// Reverses [`BenchKey::name_with_style`].
+    /// Only reliable if none of the words contain the style's separators.
+    pub fn from_name_with_style(name: &str, style: &BenchNameStyle) -> Option<BenchKey> {
+        let (start_word, targets) = name.split_once(style.assign)?;
+        if start_word.is_empty() || targets.is_empty() {
+            return None;
+        }
+        Some(BenchKey {
+            start_word: start_word.to_string(),
+            targets: targets.split(style.sep).map(String::from).collect(),
+        })
+    }
+}
+
+/// The separators that go in a bench's name:
+/// `assign` between the start word and the targets, and `sep` between targets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchNameStyle<'a> {
+    pub assign: &'a str,
+    pub sep: &'a str,
+}
+
+impl BenchNameStyle<'static> {
+    /// The style used everywhere bench names are stored, like `"start = target1 | target2"`.
+    pub const DEFAULT: BenchNameStyle<'static> = BenchNameStyle {
+        assign: " = ",
+        sep: " | ",
+    };
+
+    /// A style without spaces or `|`, for names that get used as paths,
+    /// like `"start/target1+target2"`, which Criterion reads as a group and a function.
+    pub const PATH: BenchNameStyle<'static> = BenchNameStyle {
+        assign: "/",
+        sep: "+",
+    };
+}
+
+impl Default for BenchNameStyle<'static> {
+    fn default() -> Self {
+        BenchNameStyle::DEFAULT
+    }
+}
+
+fn set_bench_name<S: AsRef<str>>(
+    bench_name: &mut String,
+    start_word: &str,
+    targets: &BTreeSet<S>,
+    style: &BenchNameStyle,
+) {
     bench_name.clear();
     bench_name.push_str(start_word);
-    bench_name.push_str(" = ");
+    bench_name.push_str(style.assign);
     let mut target_iter = targets.iter();
     bench_name.push_str(target_iter.next().unwrap().as_ref());
     for target in target_iter {
-        bench_name.push_str(" | ");
+        bench_name.push_str(style.sep);
         bench_name.push_str(target.as_ref());
     }
 }
@@ -483,6 +2555,80 @@ fn set_unaccented(accented: &str, unaccented: &mut String) {
     unaccented.extend(accented.nfd().filter(|&c| !is_combining_mark(c)));
 }
 
+/// Whether the word has any accents, i.e. combining marks once decomposed.
+pub fn has_accents(word: &str) -> bool {
+    word.nfd().any(is_combining_mark)
+}
+
+/// Rewrites a bench file in a canonical form, so that diffs of it only show real changes.
+/// Each run of bench lines, between blank lines, comments and section headers, is sorted by
+/// start words, with the `=`s lined up. Within a bench, the start words and targets are sorted
+/// and duplicates dropped. Everything else, including comments after benches, is kept as it is.
+pub fn format_bench_file(input: &str) -> Result<String, BenchError> {
+    let mut formatted = String::new();
+    let mut block: Vec<(String, String, Option<&str>)> = Vec::new();
+    let flush = |block: &mut Vec<(String, String, Option<&str>)>, formatted: &mut String| {
+        block.sort_by(|(start_a, rest_a, _), (start_b, rest_b, _)| {
+            (start_a.trim_start_matches(";; "), rest_a)
+                .cmp(&(start_b.trim_start_matches(";; "), rest_b))
+        });
+        let width = block
+            .iter()
+            .map(|(start, _, _)| start.chars().count())
+            .max()
+            .unwrap_or(0);
+        for (start, rest, comment) in block.drain(..) {
+            let padding = width - start.chars().count();
+            let line = format!("{}{} {}", start, " ".repeat(padding), rest);
+            formatted.push_str(&line);
+            if let Some(comment) = comment {
+                formatted.push_str(" ;");
+                formatted.push_str(comment.trim_end());
+            }
+            formatted.push('\n');
+        }
+    };
+    for (line_no, line) in input.lines().enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        let line = if line_no == 0 {
+            if let Some(rest) = line.strip_prefix('\u{feff}') {
+                formatted.push('\u{feff}');
+                rest
+            } else {
+                line
+            }
+        } else {
+            line
+        };
+        if benchfile::section_header(line).is_err() {
+            match benchfile::bench_line_detailed(line).finish() {
+                Ok((
+                    _,
+                    benchfile::BenchLine {
+                        bench: Some(bench),
+                        comment,
+                    },
+                )) => {
+                    block.push((bench.canonical_start(), bench.canonical_rest(), comment));
+                    continue;
+                }
+                Ok((_, benchfile::BenchLine { bench: None, .. })) => {}
+                Err(parse_err) => {
+                    return Err(ParseErr {
+                        line_no: line_no + 1,
+                        text: parse_err.input.to_owned(),
+                    })
+                }
+            }
+        }
+        flush(&mut block, &mut formatted);
+        formatted.push_str(line);
+        formatted.push('\n');
+    }
+    flush(&mut block, &mut formatted);
+    Ok(formatted)
+}
+
 pub fn get_build_info() -> BTreeMap<&'static str, &'static str> {
     let mut map = BTreeMap::new();
     map.insert("version_bench", build::GIT_DESCRIBE);
@@ -495,6 +2641,576 @@ pub fn get_build_info() -> BTreeMap<&'static str, &'static str> {
     map
 }
 
+pub fn set_session_info(
+    db: &mut BenchDb,
+    session_id: BenchSessionId,
+    run_cfg: &BenchRunCfg,
+    rules: &str,
+    dict: &LoadedDict,
+    bench_files: &[(&Path, &str)],
+    cli_args: &[String],
+) -> rusqlite::Result<()> {
+    db.set_info(session_id, "machine", run_cfg.machine.as_ref().unwrap())?;
+    db.set_info(
+        session_id,
+        "cli_args",
+        &serde_json::to_string(cli_args).expect("Error serializing cli args"),
+    )?;
+    for (key, value) in get_build_info() {
+        db.set_info(session_id, key, value)?;
+    }
+    db.set_info(session_id, "search_rules", rules)?;
+    db.set_info(
+        session_id,
+        "search_rules_hash",
+        &sha256_hex(rules.as_bytes()),
+    )?;
+    db.set_info(session_id, "search_dict_hash", &dict.hash)?;
+    db.set_info(
+        session_id,
+        "dict_word_count",
+        &dict.word_count().to_string(),
+    )?;
+    let (paths, hashes): (Vec<&Path>, Vec<String>) = bench_files
+        .iter()
+        .map(|(path, contents)| (*path, sha256_hex(contents.as_bytes())))
+        .unzip();
+    db.set_info(
+        session_id,
+        "bench_files",
+        &serde_json::to_string(&paths).expect("Error serializing bench file paths"),
+    )?;
+    db.set_info(
+        session_id,
+        "bench_files_hash",
+        &serde_json::to_string(&hashes).expect("Error serializing bench file hashes"),
+    )?;
+    db.set_info(
+        session_id,
+        "timeout_secs",
+        &run_cfg.timeout.as_secs_f64().to_string(),
+    )?;
+    db.set_info(
+        session_id,
+        "timeout_scale",
+        &run_cfg.timeout_scale.to_string(),
+    )?;
+    db.set_info(
+        session_id,
+        "effective_timeout_secs",
+        &run_cfg.effective_timeout().as_secs_f64().to_string(),
+    )?;
+    db.set_info(
+        session_id,
+        CASE_INSENSITIVE_INFO,
+        &run_cfg.case_insensitive.to_string(),
+    )?;
+    db.set_info(
+        session_id,
+        "bench_config",
+        &toml::to_string(&run_cfg).expect("Error serializing run config"),
+    )?;
+    Ok(())
+}
+
+/// The log level for a verbosity count, where 0 only shows warnings.
+/// `quiet` overrides any verbosity.
+pub fn log_level(verbose: u8, quiet: bool) -> LevelFilter {
+    match (quiet, verbose) {
+        (true, _) | (false, 0) => LevelFilter::Warn,
+        (false, 1) => LevelFilter::Info,
+        (false, 2) => LevelFilter::Debug,
+        (false, _) => LevelFilter::Trace,
+    }
+}
+
+pub fn count_dict_words(dict: &str) -> usize {
+    dict.lines().filter(|line| !line.trim().is_empty()).count()
+}
+
+/// Which words of a dictionary to keep, for benching against part of it without editing it.
+/// A word has to pass every filter that's set.
+#[derive(Debug, Clone, Default)]
+pub struct DictFilter {
+    /// Keeps words with a match of this anywhere in them.
+    pub pattern: Option<Regex>,
+    /// Keeps words in this set.
+    pub subset: Option<BTreeSet<String>>,
+}
+
+impl DictFilter {
+    pub fn is_empty(&self) -> bool {
+        self.pattern.is_none() && self.subset.is_none()
+    }
+
+    pub fn keeps(&self, word: &str) -> bool {
+        self.pattern
+            .as_ref()
+            .is_none_or(|pattern| pattern.is_match(word))
+            && self
+                .subset
+                .as_ref()
+                .is_none_or(|subset| subset.contains(word))
+    }
+
+    /// Reads a dictionary, one word per line, keeping only the lines with words it keeps.
+    pub fn filter(&self, dict: impl BufRead) -> io::Result<String> {
+        let mut filtered = String::new();
+        for line in dict.lines() {
+            let line = line?;
+            let word = line.trim();
+            if !word.is_empty() && self.keeps(word) {
+                filtered.push_str(word);
+                filtered.push('\n');
+            }
+        }
+        Ok(filtered)
+    }
+}
+
+/// Seconds of search time that each position further down the results is worth,
+/// when compiling results to compare them.
+pub const COMPARE_INDEX_EQUIVALENT: f64 = 1.0 / 8.0;
+
+/// Fraction of the results to drop, half from each end, when compiling results to compare them.
+pub const COMPARE_DROP_FRACTION: f64 = 1.0 / 4.0;
+
+/// Smallest difference in score, in seconds, that counts when comparing results.
+pub const COMPARE_MIN_DIFFERENCE: f64 = 1.0 / 32.0;
+
+/// Significance level for a difference in a bench's results to count when comparing sessions.
+pub const COMPARE_ALPHA: f64 = 0.05;
+
+/// How a session's results compare to an earlier session's, over the benches they have in common.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionComparison {
+    pub num_better: usize,
+    pub num_worse: usize,
+    /// Total of the finite score differences, in seconds, where negative is better.
+    pub total_difference: f64,
+}
+
+/// Compiles and compares the results of each bench in `later` that's also in `earlier`,
+/// counting differences of at least `min_difference` seconds.
+pub fn compare_sessions(
+    db: &mut BenchDb,
+    compiler: &BenchResultCompiler,
+    earlier: BenchSessionId,
+    later: BenchSessionId,
+    min_difference: f64,
+) -> rusqlite::Result<SessionComparison> {
+    let mut comparison = SessionComparison::default();
+    for bench in db.get_bench_keys(later)? {
+        let bench = bench.name();
+        let earlier_results = db.get_results(earlier, &bench)?;
+        if earlier_results.is_empty() {
+            continue;
+        }
+        let earlier_result = compiler.compile(earlier_results);
+        let later_result = compiler.compile(db.get_results(later, &bench)?);
+        let difference = later_result.difference(&earlier_result);
+        if difference <= -min_difference {
+            comparison.num_better += 1;
+        } else if difference >= min_difference {
+            comparison.num_worse += 1;
+        }
+        if difference.is_finite() {
+            comparison.total_difference += difference;
+        }
+    }
+    Ok(comparison)
+}
+
+/// Which group a bench goes in when breaking down results by accents:
+/// "accented" or "unaccented" by its start word, or "unknown" if its name can't be split up.
+pub fn accent_label(bench: &str) -> &'static str {
+    match BenchKey::from_name(bench) {
+        Some(key) if has_accents(&key.start_word) => "accented",
+        Some(_) => "unaccented",
+        None => "unknown",
+    }
+}
+
+/// The label of benches that weren't in any section when breaking down results by section.
+pub const NO_SECTION_LABEL: &str = "(none)";
+
+/// Gets a bench's compiled result from the stored summary, if it was compiled the same way,
+/// or else compiles it from the session's results.
+pub fn compile_bench(
+    db: &mut BenchDb,
+    compiler: &BenchResultCompiler,
+    session: BenchSessionId,
+    bench: &str,
+) -> rusqlite::Result<CompiledBenchResult> {
+    match db.get_summary(session, bench, &compiler.describe())? {
+        Some(compiled) => Ok(compiled),
+        None => Ok(compiler.compile(db.get_results(session, bench)?)),
+    }
+}
+
+/// Gets the compiled result of each of a session's benches, by name, like [`compile_bench`],
+/// but fetching the summaries in one query, and the results of the benches without one in another.
+pub fn compile_session(
+    db: &mut BenchDb,
+    compiler: &BenchResultCompiler,
+    session: BenchSessionId,
+) -> rusqlite::Result<BTreeMap<String, CompiledBenchResult>> {
+    let describe = compiler.describe();
+    let mut compiled = db.get_summaries(session, &describe)?;
+    for (bench, results) in db.get_unsummarized_results(session, &describe)? {
+        compiled.insert(bench, compiler.compile(results));
+    }
+    Ok(compiled)
+}
+
+/// Formats a duration in seconds with `precision` decimal places.
+pub fn fmt_duration(duration: &Option<Duration>, precision: usize) -> String {
+    match duration {
+        Some(value) => format!("{:.*}", precision, value.as_secs_f64()),
+        None => "--".into(),
+    }
+}
+
+/// Formats a range of durations in seconds with `precision` decimal places,
+/// as a single value if it starts and ends at the same one.
+pub fn fmt_duration_range(
+    duration_range: &Option<RangeInclusive<Duration>>,
+    precision: usize,
+) -> String {
+    match duration_range {
+        Some(range) => {
+            let start = range.start();
+            let end = range.end();
+            if start == end {
+                format!("{:.*}", precision, start.as_secs_f64())
+            } else {
+                format!(
+                    "{:.*} .. {:.*}",
+                    precision,
+                    start.as_secs_f64(),
+                    precision,
+                    end.as_secs_f64()
+                )
+            }
+        }
+        None => "--".into(),
+    }
+}
+
+/// Averages `(value, weight)` pairs, or returns `None` if the total weight is zero.
+pub fn weighted_average(values: impl IntoIterator<Item = (f64, f64)>) -> Option<f64> {
+    let mut total = 0.0;
+    let mut total_weight = 0.0;
+    for (value, weight) in values {
+        total += value * weight;
+        total_weight += weight;
+    }
+    if total_weight > 0.0 {
+        Some(total / total_weight)
+    } else {
+        None
+    }
+}
+
+/// The geometric mean of some positive values, or `None` if there are none.
+/// Computed as `exp(mean(ln(value)))` so large products don't overflow.
+pub fn geometric_mean(values: impl IntoIterator<Item = f64>) -> Option<f64> {
+    let mut total_ln = 0.0;
+    let mut count = 0;
+    for value in values {
+        total_ln += value.ln();
+        count += 1;
+    }
+    if count > 0 {
+        Some((total_ln / count as f64).exp())
+    } else {
+        None
+    }
+}
+
+/// How many of a group of benches were found, and their total score.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GroupStats {
+    pub num_benches: usize,
+    pub num_found: usize,
+    /// Total of the found benches' scores, in seconds.
+    pub total_score: f64,
+}
+
+impl GroupStats {
+    fn add(&mut self, score: Option<Duration>) {
+        self.num_benches += 1;
+        if let Some(score) = score {
+            self.num_found += 1;
+            self.total_score += score.as_secs_f64();
+        }
+    }
+
+    /// The percentage of the benches that were found.
+    pub fn percent_found(&self) -> f64 {
+        self.num_found as f64 / self.num_benches as f64 * 100.0
+    }
+
+    /// The average score of the benches that were found, if any were.
+    pub fn average_score(&self) -> Option<Duration> {
+        (self.num_found > 0)
+            .then(|| Duration::from_secs_f64(self.total_score / self.num_found as f64))
+    }
+}
+
+/// Quick statistics of a session's results, as shown by `benchdb stats`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    pub all: GroupStats,
+    pub geometric_mean_score: Option<Duration>,
+    /// Only set if some benches have a weight other than 1.
+    pub weighted_average_score: Option<Duration>,
+    pub score_range: Option<RangeInclusive<Duration>>,
+    pub elapsed_range: Option<RangeInclusive<Duration>>,
+    /// The first index each bench was found at, or `None` for the ones that weren't found.
+    pub found_indices: Vec<Option<usize>>,
+    /// The average and largest growth in resident memory per search, in bytes, if it was measured.
+    pub memory_growth: Option<(f64, u64)>,
+    /// The average time results' searches took to find their first word, if it was recorded.
+    pub average_first_result: Option<Duration>,
+    /// The average number of words searches went through to find all their start word's targets,
+    /// from [`FOUND_ALL_AT_INFO`], if any did.
+    pub average_found_all_at: Option<f64>,
+    /// Broken down by [`accent_label`].
+    pub accent_groups: BTreeMap<&'static str, GroupStats>,
+    /// Broken down by bench file section, with [`NO_SECTION_LABEL`] for benches not in one.
+    pub section_groups: BTreeMap<String, GroupStats>,
+}
+
+/// Compiles the results of each of a session's benches, and gathers them into statistics.
+/// A session that isn't in the database has no benches.
+pub fn session_stats(
+    db: &mut BenchDb,
+    compiler: &BenchResultCompiler,
+    session: BenchSessionId,
+) -> rusqlite::Result<SessionStats> {
+    let mut stats = SessionStats::default();
+    let mut score_range = RangeAccumulator::new();
+    let mut elapsed_range = RangeAccumulator::new();
+    let weights = db.get_weights(session)?;
+    let sections = db.get_sections(session)?;
+    let mut weighted_scores = Vec::new();
+    for (bench, compiled) in compile_session(db, compiler, session)? {
+        stats
+            .found_indices
+            .push(compiled.found_index.as_ref().map(|r| *r.start()));
+        let score = match (compiled.score, &compiled.found_index, &compiled.elapsed) {
+            (Some(score), Some(_found_index), Some(elapsed)) => {
+                weighted_scores.push((
+                    score.as_secs_f64(),
+                    weights.get(&bench).copied().unwrap_or(1.0),
+                ));
+                score_range.push(score);
+                elapsed_range.push_range(elapsed);
+                Some(score)
+            }
+            _ => None,
+        };
+        stats.all.add(score);
+        stats
+            .accent_groups
+            .entry(accent_label(&bench))
+            .or_default()
+            .add(score);
+        let section = sections
+            .get(&bench)
+            .map_or(NO_SECTION_LABEL, String::as_str);
+        stats
+            .section_groups
+            .entry(section.to_owned())
+            .or_default()
+            .add(score);
+    }
+    stats.geometric_mean_score = geometric_mean(weighted_scores.iter().map(|&(score, _)| score))
+        .map(Duration::from_secs_f64);
+    if !weights.is_empty() {
+        stats.weighted_average_score =
+            weighted_average(weighted_scores).map(Duration::from_secs_f64);
+    }
+    stats.score_range = score_range.finish();
+    stats.elapsed_range = elapsed_range.finish();
+    stats.memory_growth = db.peak_rss_summary(session)?;
+    stats.average_first_result = db.average_first_result(session)?;
+    stats.average_found_all_at = average_found_all_at(&db.get_info(session, FOUND_ALL_AT_INFO)?);
+    Ok(stats)
+}
+
+/// The average word count in a [`FOUND_ALL_AT_INFO`] value, or `None` if it's empty or unreadable.
+fn average_found_all_at(info: &str) -> Option<f64> {
+    let found_all_at: BTreeMap<String, Vec<usize>> = serde_json::from_str(info).ok()?;
+    let counts: Vec<usize> = found_all_at.into_values().flatten().collect();
+    if counts.is_empty() {
+        None
+    } else {
+        Some(counts.iter().sum::<usize>() as f64 / counts.len() as f64)
+    }
+}
+
+impl SessionStats {
+    /// Writes the overall statistics, one per line, with scores to `precision` decimal places.
+    pub fn write_summary(&self, out: &mut dyn Write, precision: usize) -> io::Result<()> {
+        writeln!(
+            out,
+            "Found {} / {} ({:.1}%)",
+            self.all.num_found,
+            self.all.num_benches,
+            self.all.percent_found()
+        )?;
+        if self.all.num_found > 0 {
+            writeln!(
+                out,
+                "Average score: {} sec",
+                fmt_duration(&self.all.average_score(), precision)
+            )?;
+            writeln!(
+                out,
+                "Geometric mean score: {} sec",
+                fmt_duration(&self.geometric_mean_score, precision)
+            )?;
+            if self.weighted_average_score.is_some() {
+                writeln!(
+                    out,
+                    "Weighted average score: {} sec",
+                    fmt_duration(&self.weighted_average_score, precision)
+                )?;
+            }
+            writeln!(
+                out,
+                "Score range: {}",
+                fmt_duration_range(&self.score_range, precision)
+            )?;
+            writeln!(
+                out,
+                "Seconds to find: {}",
+                fmt_duration_range(&self.elapsed_range, precision)
+            )?;
+        }
+        if self.average_first_result.is_some() {
+            writeln!(
+                out,
+                "Average time to first result: {} sec",
+                fmt_duration(&self.average_first_result, precision)
+            )?;
+        }
+        if let Some(average) = self.average_found_all_at {
+            writeln!(out, "Average words to find all targets: {:.1}", average)?;
+        }
+        if let Some((avg_rss, max_rss)) = self.memory_growth {
+            writeln!(
+                out,
+                "Memory growth per search: {:.0} KiB average, {} KiB max",
+                avg_rss / 1024.0,
+                max_rss / 1024
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Receives and drops everything from `rx` until nothing new comes for `quiet`,
+/// so that a burst of events only gets handled once. Returns how many were dropped.
+pub fn drain_until_quiet<T>(rx: &Receiver<T>, quiet: Duration) -> usize {
+    let mut num_dropped = 0;
+    while rx.recv_timeout(quiet).is_ok() {
+        num_dropped += 1;
+    }
+    num_dropped
+}
+
+/// Session info name for whether a session's run finished: [`SESSION_RUNNING`] until all its
+/// results are stored, and then [`SESSION_COMPLETE`]. Sessions from older versions don't have it.
+pub const SESSION_STATUS_INFO: &str = "status";
+
+/// Session info name for whether a session's searches matched targets regardless of case,
+/// `"true"` or `"false"`. Sessions from older versions don't have it, and were case-sensitive.
+pub const CASE_INSENSITIVE_INFO: &str = "case_insensitive";
+
+/// Session info name for [`Bencher::get_found_all_at`], as a JSON object of start words
+/// to their searches' word counts. Left out if no search found all its targets.
+pub const FOUND_ALL_AT_INFO: &str = "found_all_at";
+pub const SESSION_RUNNING: &str = "running";
+pub const SESSION_COMPLETE: &str = "complete";
+
+/// Environment variable with the config file path, for when `--config` isn't given.
+pub const CONFIG_ENV_VAR: &str = "BUSCALUSO_BENCH_CONFIG";
+
+/// Environment variable with the output database path, for when `--out-db` isn't given.
+pub const DB_ENV_VAR: &str = "BUSCALUSO_BENCH_DB";
+
+/// Machine identifier that asks for one to be made up from the host, with `auto_machine_id`.
+pub const AUTO_MACHINE: &str = "auto";
+
+/// A machine identifier from the host's name and CPU model, like `"mybox (AMD Ryzen 7 5800X)"`,
+/// read from the system so that runs on the same machine get the same one.
+pub fn auto_machine_id() -> String {
+    let hostname = std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok());
+    let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok();
+    machine_id(hostname.as_deref(), cpuinfo.as_deref())
+}
+
+/// Puts together a machine identifier from a host name and the text of `/proc/cpuinfo`,
+/// leaving out whichever aren't known.
+pub fn machine_id(hostname: Option<&str>, cpuinfo: Option<&str>) -> String {
+    let hostname = hostname
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or("unknown-host");
+    let cpu_model = cpuinfo.and_then(|cpuinfo| {
+        cpuinfo.lines().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            (key.trim() == "model name").then(|| value.trim())
+        })
+    });
+    match cpu_model.filter(|model| !model.is_empty()) {
+        Some(model) => format!("{} ({})", hostname, model),
+        None => hostname.to_string(),
+    }
+}
+
+/// Picks a path from the command line if it was given there,
+/// or else from an environment variable's value, if that's set and not empty.
+pub fn path_from_cli_or_env(cli: Option<PathBuf>, env_value: Option<OsString>) -> Option<PathBuf> {
+    cli.or_else(|| {
+        env_value
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+    })
+}
+
+pub const STDIN_PATH: &str = "-";
+
+/// Output database path that keeps the results in memory, and throws them away at the end.
+pub const MEMORY_DB_PATH: &str = ":memory:";
+
+pub fn is_memory_db_path(path: &Path) -> bool {
+    path == Path::new(MEMORY_DB_PATH)
+}
+
+pub fn is_stdin_path(path: &Path) -> bool {
+    path == Path::new(STDIN_PATH)
+}
+
+pub fn open_input(path: &Path) -> std::io::Result<Box<dyn BufRead>> {
+    if is_stdin_path(path) {
+        Ok(Box::new(std::io::stdin().lock()))
+    } else {
+        Ok(Box::new(BufReader::new(File::open(path)?)))
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
 pub fn file_sha256_hex(path: &Path) -> std::io::Result<String> {
     let mut file = File::open(path)?;
     let mut buffer = [0; 4096];
@@ -1,54 +1,133 @@
 // Copyright © 2022 Daniel Getz
 // SPDX-License-Identifier: MIT
 
+use std::collections::BTreeSet;
 use std::io::prelude::*;
 
+use std::env;
+use std::error::Error;
 use std::fs;
-use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
-use std::time::Instant;
+use std::io::{self, Cursor, IsTerminal};
+use std::num::NonZeroU8;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use clap::{CommandFactory, Parser};
+use log::{info, warn, LevelFilter};
+use notify::{RecursiveMode, Watcher};
+use regex::Regex;
 use rusqlite::Connection;
 
 use buscaluso::BuscaCfg;
 
 use buscaluso_bench::build;
-use buscaluso_bench::file_sha256_hex;
+use buscaluso_bench::dictcache::{dict_words, DictCache, LoadedDict};
 use buscaluso_bench::sqlite::{BenchDb, BenchSessionId};
-use buscaluso_bench::{get_build_info, BenchRunCfg, Bencher};
+use buscaluso_bench::{
+    auto_machine_id, compare_sessions, count_dict_words, drain_until_quiet, is_memory_db_path,
+    is_stdin_path, log_level, open_input, parse_timeout_secs, path_from_cli_or_env, session_stats,
+    set_session_info, sha256_hex, BenchResultCompiler, BenchRunCfg, Bencher, DictFilter,
+    ProgressBarObserver, AUTO_MACHINE, COMPARE_DROP_FRACTION, COMPARE_INDEX_EQUIVALENT,
+    COMPARE_MIN_DIFFERENCE, CONFIG_ENV_VAR, DB_ENV_VAR, SESSION_COMPLETE, SESSION_RUNNING,
+    SESSION_STATUS_INFO,
+};
 
 #[derive(Parser)]
 #[clap(author, version = build::GIT_DESCRIBE, long_version = build::CLAP_LONG_VERSION, about, long_about = None)]
 struct Cli {
-    /// Machine identifier
+    /// Machine identifier, or "auto" to make one up from the host name and CPU model,
+    /// which is also what happens if neither this nor the config sets one
     #[arg(short, long)]
     machine: Option<String>,
 
-    /// Config TOML file
+    /// Config TOML file, defaults to $BUSCALUSO_BENCH_CONFIG
     #[arg(short, long)]
-    config: PathBuf,
+    config: Option<PathBuf>,
 
-    /// Rules file
+    /// Rules file, or "-" for stdin
     #[arg(short, long)]
     rules: Option<PathBuf>,
 
-    /// Dictionary file
+    /// Dictionary file, or "-" for stdin
     #[arg(short, long)]
     dict: Option<PathBuf>,
 
-    /// Benchmark file
+    /// Benchmark file, or "-" for stdin; can be given more than once to load several
     #[arg(short, long)]
-    bench: Option<PathBuf>,
+    bench: Vec<PathBuf>,
 
-    /// Output database file, defaults to "bench.sqlite3"
+    /// Only keep the dictionary words with a match of this regular expression
+    #[arg(long)]
+    dict_filter: Option<Regex>,
+
+    /// Only keep the dictionary words that are also in this file, one per line, or "-" for stdin
+    #[arg(long)]
+    dict_subset: Option<PathBuf>,
+
+    /// Directory to cache dictionary word lists in, by the dictionary file's path, size and modification time;
+    /// this saves reading and hashing an unchanged dictionary, but buscaluso still loads its words every run
+    #[arg(long)]
+    dict_cache: Option<PathBuf>,
+
+    /// Output database file, defaults to $BUSCALUSO_BENCH_DB or the config's, or else "bench.sqlite3";
+    /// ":memory:" prints the session's stats instead of keeping them
     #[arg(short, long)]
     out_db: Option<PathBuf>,
 
+    /// Number of times to run each benchmark, overriding the config
+    #[arg(long)]
+    repeat: Option<NonZeroU8>,
+
+    /// Number of times to run failing benchmarks, overriding the config
+    #[arg(long)]
+    repeat_failed: Option<NonZeroU8>,
+
+    /// Timeout in seconds for each search, overriding the config
+    #[arg(long, value_parser = parse_timeout_secs)]
+    timeout: Option<Duration>,
+
+    /// Give up on a search after this many words past the first, overriding the config's max_index
+    #[arg(long)]
+    limit_results: Option<usize>,
+
+    /// Tag to label the session with
+    #[arg(long)]
+    tag: Option<String>,
+
+    /// Load everything and check the benchmarks, but don't run them or store a session
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Only run the benchmarks that never found their target in this earlier session, failing if there are none
+    #[arg(long)]
+    retry_failed_from: Option<BenchSessionId>,
+
+    /// Total time budget in seconds for the whole run, overriding the config
+    #[arg(long, value_parser = parse_timeout_secs)]
+    total_budget: Option<Duration>,
+
+    /// Smoke test: run each benchmark once with no warmup, and mark the session's timings as unreliable
+    #[arg(long, conflicts_with_all = ["repeat", "repeat_failed"])]
+    quick: bool,
+
+    /// Keep watching the rules, dictionary and benchmark files, and run again whenever they change
+    #[arg(long)]
+    watch: bool,
+
+    /// Log progress as lines instead of showing a progress bar
+    #[arg(long)]
+    no_progress: bool,
+
     /// Turn on verbose output
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
+
+    /// Only show warnings and errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
 }
 
 fn copy_required_setting_from_cli<T: Clone>(
@@ -65,107 +144,455 @@ fn copy_required_setting_from_cli<T: Clone>(
     }
 }
 
-fn setting_file_reader(setting: &Option<PathBuf>, verbose: u8) -> impl BufRead {
-    let path = setting.as_ref().unwrap();
-    if verbose > 0 {
-        eprintln!("Loading {:?}", path);
-    }
-    BufReader::new(File::open(path).expect("Error opening file"))
+/// Reads a whole input file, with its path in any error.
+fn read_input_file(path: &Path) -> io::Result<String> {
+    info!("Loading {:?}", path);
+    let mut contents = String::new();
+    open_input(path)
+        .and_then(|mut input| input.read_to_string(&mut contents))
+        .map_err(|err| io::Error::new(err.kind(), format!("Error reading {:?}: {}", path, err)))?;
+    Ok(contents)
 }
 
+fn read_setting_file(setting: &Option<PathBuf>) -> io::Result<String> {
+    read_input_file(setting.as_ref().unwrap())
+}
+
+/// How long the input files have to go without changing before `--watch` runs again.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Decimal places of the scores in the stats printed after a run with an in-memory database.
+const MEMORY_DB_STATS_PRECISION: usize = 4;
+
+/// How often `--watch` checks for Ctrl-C while waiting for changes.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
 fn main() {
     let cli = Cli::parse();
-    let start_time = Instant::now();
-    let mut search_cfg = BuscaCfg::new();
+    let config = path_from_cli_or_env(cli.config.clone(), env::var_os(CONFIG_ENV_VAR))
+        .unwrap_or_else(|| {
+            Cli::command()
+                .error(
+                    clap::error::ErrorKind::MissingRequiredArgument,
+                    format!(
+                        "Missing config file: use --config or set {}",
+                        CONFIG_ENV_VAR
+                    ),
+                )
+                .exit()
+        });
     let mut run_cfg: BenchRunCfg =
-        toml::from_str(&fs::read_to_string(cli.config).expect("Error reading config file"))
+        toml::from_str(&fs::read_to_string(config).expect("Error reading config file"))
             .expect("Error loading config");
+    if !(run_cfg.timeout_scale.is_finite() && run_cfg.timeout_scale > 0.0) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ValueValidation,
+                format!(
+                    "timeout_scale must be a positive number, not {}",
+                    run_cfg.timeout_scale
+                ),
+            )
+            .exit()
+    }
     if cli.verbose != 0 {
         run_cfg.verbose = cli.verbose;
     }
-    copy_required_setting_from_cli(
-        &mut run_cfg.machine,
-        &cli.machine,
-        "Missing machine identifier",
-    );
+    let mut logger = env_logger::Builder::new();
+    logger.filter_level(log_level(run_cfg.verbose, cli.quiet));
+    // `--quiet` has the last word, so RUST_LOG can't bring back what it hides
+    if !cli.quiet {
+        logger.parse_default_env();
+    }
+    logger.init();
+    run_cfg.apply_overrides(cli.repeat, cli.repeat_failed, cli.timeout);
+    if cli.quick {
+        run_cfg.apply_quick_mode();
+    }
+    if cli.total_budget.is_some() {
+        run_cfg.total_budget = cli.total_budget;
+    }
+    if cli.limit_results.is_some() {
+        run_cfg.max_index = cli.limit_results;
+    }
+    if cli.machine.is_some() {
+        run_cfg.machine = cli.machine.clone();
+    }
+    if run_cfg
+        .machine
+        .as_deref()
+        .is_none_or(|machine| machine == AUTO_MACHINE)
+    {
+        let machine = auto_machine_id();
+        info!("Using machine identifier {:?}", machine);
+        run_cfg.machine = Some(machine);
+    }
     copy_required_setting_from_cli(&mut run_cfg.rules_file, &cli.rules, "Missing rules file");
     copy_required_setting_from_cli(&mut run_cfg.dict_file, &cli.dict, "Missing dict file");
-    copy_required_setting_from_cli(&mut run_cfg.bench_file, &cli.bench, "Missing benches file");
-    if let Some(out_db) = cli.out_db {
+    if run_cfg.bench_files.as_ref().is_some_and(Vec::is_empty) {
+        run_cfg.bench_files = None;
+    }
+    copy_required_setting_from_cli(
+        &mut run_cfg.bench_files,
+        &(!cli.bench.is_empty()).then(|| cli.bench.clone()),
+        "Missing benches file",
+    );
+    if let Some(out_db) = path_from_cli_or_env(cli.out_db.clone(), env::var_os(DB_ENV_VAR)) {
         run_cfg.out_db = out_db;
     }
+    if [&run_cfg.rules_file, &run_cfg.dict_file]
+        .into_iter()
+        .map(|setting| setting.as_ref().unwrap())
+        .chain(run_cfg.bench_files.as_ref().unwrap())
+        .chain(&cli.dict_subset)
+        .filter(|path| is_stdin_path(path))
+        .count()
+        > 1
+    {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "Only one input file can be read from stdin",
+            )
+            .exit();
+    }
+
+    let in_memory = is_memory_db_path(&run_cfg.out_db);
+    let conn = if in_memory {
+        Connection::open_in_memory()
+    } else {
+        Connection::open(&run_cfg.out_db)
+    };
+    let mut db = BenchDb::new(conn.expect("Error opening db file")).expect("Error initializing db");
+    if cli.watch {
+        watch(&cli, &run_cfg, &mut db);
+    } else {
+        match run_session(&cli, &run_cfg, &mut db) {
+            Ok(Some(session)) if in_memory => {
+                let compiler = BenchResultCompiler::new(
+                    Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+                    COMPARE_DROP_FRACTION,
+                );
+                session_stats(&mut db, &compiler, session)
+                    .expect("Error computing session stats")
+                    .write_summary(&mut io::stdout(), MEMORY_DB_STATS_PRECISION)
+                    .expect("Error writing session stats");
+            }
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+}
 
-    let mut db = BenchDb::new(Connection::open(&run_cfg.out_db).expect("Error opening db file"))
-        .expect("Error initializing db");
+/// Loads the rules, dictionary and benchmarks, runs the benchmarks, and stores them as a new
+/// session, returning its id. With `--dry-run`, stops before running anything.
+/// Input files that can't be read or used are an error, for the caller to report, so that
+/// `--watch` can wait for them to be fixed.
+fn run_session(
+    cli: &Cli,
+    run_cfg: &BenchRunCfg,
+    db: &mut BenchDb,
+) -> Result<Option<BenchSessionId>, Box<dyn Error>> {
+    let start_time = Instant::now();
+    let mut search_cfg = BuscaCfg::new();
     let mut bencher = Bencher::new();
 
+    let rules = read_setting_file(&run_cfg.rules_file)?;
     search_cfg
-        .load_rules(setting_file_reader(&run_cfg.rules_file, run_cfg.verbose))
-        .expect("Error loading rules file");
+        .load_rules(Cursor::new(rules.as_bytes()))
+        .map_err(|err| format!("Error loading rules file: {}", err))?;
+    let dict_subset = cli
+        .dict_subset
+        .as_deref()
+        .map(read_input_file)
+        .transpose()?;
+    let dict_filter = DictFilter {
+        pattern: cli.dict_filter.clone(),
+        subset: dict_subset
+            .as_deref()
+            .map(|subset| dict_words(subset).into_iter().collect()),
+    };
+    let dict_path = run_cfg.dict_file.as_deref().unwrap();
+    // A filtered dictionary depends on more than the file, so it isn't cached
+    let dict = match &cli.dict_cache {
+        Some(cache_dir) if dict_filter.is_empty() && !is_stdin_path(dict_path) => {
+            DictCache::new(cache_dir)
+                .get_or_store(dict_path, || read_input_file(dict_path))
+                .map_err(|err| format!("Error using dictionary cache: {}", err))?
+        }
+        _ => {
+            let mut dict = read_input_file(dict_path)?;
+            if !dict_filter.is_empty() {
+                dict = dict_filter
+                    .filter(dict.as_bytes())
+                    .map_err(|err| format!("Error filtering dictionary: {}", err))?;
+                info!("Kept {} dictionary words", count_dict_words(&dict));
+            }
+            LoadedDict::new(dict)
+        }
+    };
     search_cfg
-        .load_dictionary(setting_file_reader(&run_cfg.dict_file, run_cfg.verbose))
-        .expect("Error loading dictionary");
-    bencher
-        .load_benches(setting_file_reader(&run_cfg.bench_file, run_cfg.verbose))
-        .expect("Error loading bench file");
-
-    if run_cfg.verbose > 0 {
-        eprintln!("Storing session info into db");
+        .load_dictionary(Cursor::new(dict.text.as_bytes()))
+        .map_err(|err| format!("Error loading dictionary: {}", err))?;
+    // The search's own copy of the config, with the dictionary-size cap resolved
+    let search_run_cfg = BenchRunCfg {
+        max_index: run_cfg.effective_max_index(dict.word_count()),
+        ..run_cfg.clone()
+    };
+    let bench_files: Vec<(&Path, String)> = run_cfg
+        .bench_files
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|path| Ok((path.as_path(), read_input_file(path)?)))
+        .collect::<io::Result<_>>()?;
+    for (path, contents) in &bench_files {
+        bencher
+            .load_benches(
+                contents.as_bytes(),
+                run_cfg.add_unaccented,
+                run_cfg.normalization,
+            )
+            .map_err(|err| format!("Error loading bench file {:?}: {}", path, err))?;
+    }
+    bencher.check_not_empty()?;
+    if bencher.disabled_count() > 0 {
+        info!("Skipping {} disabled benches", bencher.disabled_count());
+    }
+    if let Some(retry_session) = cli.retry_failed_from {
+        let failed: BTreeSet<String> = db
+            .get_failed_benches(retry_session)
+            .expect("Error reading failed benches from db")
+            .into_iter()
+            .collect();
+        info!(
+            "Only running the {} benches that failed in session {}",
+            failed.len(),
+            retry_session
+        );
+        bencher.retain_failed(&failed, retry_session)?;
+    }
+    // Only warn about the benches that are going to run
+    let dict_words: BTreeSet<String> = dict
+        .words()
+        .iter()
+        .map(|word| run_cfg.normalization.apply(word))
+        .collect();
+    for bench in bencher.check_targets(&dict_words, run_cfg.case_insensitive) {
+        warn!("No target of {:?} is in the dictionary", bench);
     }
-    let session_id = db.new_session_id().expect("Error getting session id");
-    set_session_info(&mut db, session_id, &run_cfg).expect("Error adding session info to db");
 
-    if run_cfg.verbose > 0 {
-        eprintln!(
-            "Running all benchmarks {} times with a timeout of {:?} each",
-            run_cfg.repeat, run_cfg.timeout,
+    if cli.dry_run {
+        println!(
+            "Loaded {} benchmarks ({} disabled); not running them because of --dry-run",
+            bencher.num_benches(),
+            bencher.disabled_count()
         );
+        return Ok(None);
     }
-    bencher.run_benches(&search_cfg, &run_cfg);
 
-    if run_cfg.verbose > 0 {
-        eprintln!("Writing results to database");
+    info!("Storing session info into db");
+    let session_id = db.new_session_id().expect("Error getting session id");
+    let cli_args: Vec<String> = std::env::args().collect();
+    let bench_files: Vec<(&Path, &str)> = bench_files
+        .iter()
+        .map(|(path, contents)| (*path, contents.as_str()))
+        .collect();
+    set_session_info(
+        db,
+        session_id,
+        run_cfg,
+        &rules,
+        &dict,
+        &bench_files,
+        &cli_args,
+    )
+    .expect("Error adding session info to db");
+    db.set_info(session_id, SESSION_STATUS_INFO, SESSION_RUNNING)
+        .expect("Error adding session info to db");
+    if let Some(tag) = &cli.tag {
+        db.set_info(session_id, "tag", tag)
+            .expect("Error adding session info to db");
     }
-    for (bench, result) in bencher.get_results() {
-        db.add_result(session_id, &bench, result)
-            .expect("Error adding result to db");
+    if let Some(pattern) = &cli.dict_filter {
+        db.set_info(session_id, "dict_filter", pattern.as_str())
+            .expect("Error adding session info to db");
     }
-
-    if run_cfg.verbose > 0 {
-        let elapsed = start_time.elapsed();
-        eprintln!("Total elapsed time: {:?}", elapsed);
+    if let (Some(path), Some(subset)) = (&cli.dict_subset, &dict_subset) {
+        db.set_info(session_id, "dict_subset", &path.to_string_lossy())
+            .expect("Error adding session info to db");
+        db.set_info(
+            session_id,
+            "dict_subset_hash",
+            &sha256_hex(subset.as_bytes()),
+        )
+        .expect("Error adding session info to db");
     }
-}
-
-fn set_session_info(
-    db: &mut BenchDb,
-    session_id: BenchSessionId,
-    run_cfg: &BenchRunCfg,
-) -> rusqlite::Result<()> {
-    db.set_info(session_id, "machine", run_cfg.machine.as_ref().unwrap())?;
-    for (key, value) in get_build_info() {
-        db.set_info(session_id, key, value)?;
+    if let Some(max_index) = search_run_cfg.max_index {
+        db.set_info(session_id, "effective_max_index", &max_index.to_string())
+            .expect("Error adding session info to db");
     }
+    if cli.quick {
+        db.set_info(session_id, "quick_mode", "true")
+            .expect("Error adding session info to db");
+    }
+    for (bench, &weight) in bencher.get_weights() {
+        db.set_weight(session_id, bench, weight.into())
+            .expect("Error adding bench weight to db");
+    }
+
+    info!(
+        "Running all benchmarks {} times with a timeout of {:?} each",
+        run_cfg.repeat,
+        run_cfg.effective_timeout(),
+    );
+    let summary = if show_progress_bar(cli) {
+        bencher.run_benches_observed(
+            &search_cfg,
+            &search_run_cfg,
+            &mut ProgressBarObserver::default(),
+        )
+    } else {
+        bencher.run_benches(&search_cfg, &search_run_cfg)
+    };
     db.set_info(
         session_id,
-        "search_rules",
-        &std::fs::read_to_string(run_cfg.rules_file.as_ref().unwrap())
-            .expect("Error reading rules file"),
-    )?;
-    db.set_info(
-        session_id,
-        "search_rules_hash",
-        &file_sha256_hex(run_cfg.rules_file.as_ref().unwrap()).expect("Error hashing rules file"),
-    )?;
+        "budget_exceeded",
+        &summary.budget_exceeded.to_string(),
+    )
+    .expect("Error adding session info to db");
     db.set_info(
         session_id,
-        "search_dict_hash",
-        &file_sha256_hex(run_cfg.dict_file.as_ref().unwrap()).expect("Error hashing dict file"),
-    )?;
+        "benches_measured",
+        &summary.benches_measured.to_string(),
+    )
+    .expect("Error adding session info to db");
+
+    info!("Writing results to database");
+    bencher
+        .store_results(db, session_id)
+        .expect("Error adding results to db");
+
+    let elapsed = start_time.elapsed();
     db.set_info(
         session_id,
-        "bench_config",
-        &toml::to_string(&run_cfg).expect("Error serializing run config"),
-    )?;
-    Ok(())
+        "elapsed_secs",
+        &format!("{:.3}", elapsed.as_secs_f64()),
+    )
+    .expect("Error adding session info to db");
+    db.set_info(session_id, SESSION_STATUS_INFO, SESSION_COMPLETE)
+        .expect("Error adding session info to db");
+    info!("Total elapsed time: {:?}", elapsed);
+    Ok(Some(session_id))
+}
+
+/// Whether to show a progress bar during the runs instead of logging progress lines:
+/// only when logging at info level, and standard error is a terminal.
+/// Any more verbose, and the lines logged for each search would break up the bar.
+fn show_progress_bar(cli: &Cli) -> bool {
+    !cli.no_progress && log::max_level() == LevelFilter::Info && io::stderr().is_terminal()
+}
+
+/// Runs a session, and then another each time the input files change,
+/// comparing each to the one before. Stops on Ctrl-C.
+fn watch(cli: &Cli, run_cfg: &BenchRunCfg, db: &mut BenchDb) {
+    let paths: Vec<&PathBuf> = [&run_cfg.rules_file, &run_cfg.dict_file]
+        .into_iter()
+        .map(|setting| setting.as_ref().unwrap())
+        .chain(run_cfg.bench_files.as_ref().unwrap())
+        .chain(&cli.dict_subset)
+        .collect();
+    if paths.iter().any(|path| is_stdin_path(path)) {
+        Cli::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "Can't watch standard input for changes",
+            )
+            .exit();
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let handler_stop = stop.clone();
+    ctrlc::set_handler(move || {
+        if handler_stop.swap(true, Ordering::SeqCst) {
+            // A second Ctrl-C doesn't wait for the current run to finish
+            std::process::exit(130);
+        }
+        eprintln!("Stopping after this run; press Ctrl-C again to stop now");
+    })
+    .expect("Error setting Ctrl-C handler");
+
+    // Editors often replace a file instead of writing to it, so watch the directories
+    // and only pay attention to changes to the files themselves.
+    let files: BTreeSet<PathBuf> = paths
+        .iter()
+        .map(|path| fs::canonicalize(path).expect("Error finding file to watch"))
+        .collect();
+    let dirs: BTreeSet<PathBuf> = files
+        .iter()
+        .filter_map(|file| file.parent().map(PathBuf::from))
+        .collect();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event {
+            if !event.kind.is_access() && event.paths.iter().any(|path| files.contains(path)) {
+                let _ = tx.send(event);
+            }
+        }
+    })
+    .expect("Error creating file watcher");
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .expect("Error watching directory");
+    }
+
+    let compiler = BenchResultCompiler::new(
+        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+        COMPARE_DROP_FRACTION,
+    );
+    let mut previous_session = None;
+    while !stop.load(Ordering::SeqCst) {
+        match run_session(cli, run_cfg, db) {
+            Ok(Some(session)) => {
+                if let Some(previous) = previous_session {
+                    let comparison =
+                        compare_sessions(db, &compiler, previous, session, COMPARE_MIN_DIFFERENCE)
+                            .expect("Error comparing sessions");
+                    println!(
+                        "Session {} vs {}: {} better, {} worse, total difference {:+.4} sec",
+                        session,
+                        previous,
+                        comparison.num_better,
+                        comparison.num_worse,
+                        comparison.total_difference
+                    );
+                } else {
+                    println!("Session {} done", session);
+                }
+                previous_session = Some(session);
+            }
+            Ok(None) => {}
+            // Keep watching, to run again once the input is fixed
+            Err(err) => eprintln!("Error: {}", err),
+        }
+
+        info!("Waiting for changes to {:?}", paths);
+        // Drop any changes from during the run, to only run again for new ones
+        drain_until_quiet(&rx, Duration::ZERO);
+        loop {
+            if stop.load(Ordering::SeqCst) {
+                return;
+            }
+            match rx.recv_timeout(WATCH_POLL_INTERVAL) {
+                Ok(_) => break,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        drain_until_quiet(&rx, WATCH_DEBOUNCE);
+    }
 }
@@ -4,59 +4,369 @@
 #[cfg(test)]
 mod tests;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Display;
+use std::io::{Read, Write};
+use std::num::ParseIntError;
 use std::str::FromStr;
 use std::time::{Duration, SystemTime};
 
-use rusqlite::types::FromSql;
-use rusqlite::{named_params, Connection, OptionalExtension, ToSql};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rusqlite::types::{FromSql, FromSqlError, ToSqlOutput, Type, ValueRef};
+use rusqlite::{named_params, Connection, DatabaseName, OptionalExtension, Row, Rows, ToSql};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-use super::BenchResult;
+use super::{
+    BenchKey, BenchResult, BenchResultCompiler, CompiledBenchResult, SearchError, SearchLimit,
+};
 
 const SCHEMA: &str = r#"
-create table if not exists bench_session_info (
+create table if not exists {prefix}bench_session_info (
   session_id int not null,
   name text not null,
   value text not null,
   primary key (session_id, name));
 
-create index if not exists bench_session_info_name_idx
-  on bench_session_info (name, value);
+create index if not exists {prefix}bench_session_info_name_idx
+  on {prefix}bench_session_info (name, value);
 
-create table if not exists bench_run (
+create table if not exists {prefix}bench_run (
   session_id int not null,
   bench text not null,
   duration real not null,
   found_at int,
-  err text);
+  err text,
+  start_word text,
+  targets text,
+  run_idx int,
+  stopped_by text,
+  peak_rss int,
+  section text,
+  err_kind text,
+  found_word text,
+  setup_secs real,
+  first_result_secs real);
 
-create index if not exists bench_run_bench_idx
-  on bench_run (bench, session_id);
+create index if not exists {prefix}bench_run_bench_idx
+  on {prefix}bench_run (bench, session_id);
 
-create index if not exists bench_run_session_idx
-  on bench_run (session_id);
+create index if not exists {prefix}bench_run_session_idx
+  on {prefix}bench_run (session_id);
+
+create table if not exists {prefix}bench_weight (
+  session_id int not null,
+  bench text not null,
+  weight real not null,
+  primary key (session_id, bench));
+
+create table if not exists {prefix}bench_trace (
+  session_id int not null,
+  start_word text not null,
+  run int not null,
+  word_idx int not null,
+  word text not null,
+  primary key (session_id, start_word, run, word_idx));
+
+create table if not exists {prefix}bench_trace_run (
+  session_id int not null,
+  start_word text not null,
+  run int not null,
+  num_words int not null,
+  primary key (session_id, start_word, run));
+
+create table if not exists {prefix}bench_summary (
+  session_id int not null,
+  bench text not null,
+  score real,
+  found_lo int,
+  found_hi int,
+  elapsed_lo real,
+  elapsed_hi real,
+  errors text not null,
+  compiler text not null,
+  kept_scores text,
+  kept_failures int,
+  elapsed_cov real,
+  score_ci_lo real,
+  score_ci_hi real,
+  primary key (session_id, bench));
 "#;
 
+/// Why a read-only database can't be used, when it was made by an older version.
+pub const NEEDS_UPGRADE_MESSAGE: &str =
+    "database was made by an older version; open it with write access once to upgrade it";
+
+/// Whether the database already has every table in `schema`, with all of their columns,
+/// so it can be read without creating or migrating anything.
+fn has_schema(conn: &Connection, schema: &str) -> rusqlite::Result<bool> {
+    let expected = Connection::open_in_memory()?;
+    expected.execute_batch(schema)?;
+    let columns = |conn: &Connection| -> rusqlite::Result<BTreeSet<(String, String)>> {
+        conn.prepare(
+            r#"
+            select m.name, c.name
+              from sqlite_master as m, pragma_table_info(m.name) as c
+              where m.type = 'table'
+            "#,
+        )?
+        .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+    };
+    Ok(columns(&expected)?.is_subset(&columns(conn)?))
+}
+
 pub struct BenchDb {
     pub conn: Connection,
+    tables: TableNames,
+}
+
+/// The names of a `BenchDb`'s tables, each starting with its prefix.
+struct TableNames {
+    prefix: String,
+    session_info: String,
+    run: String,
+    weight: String,
+    trace: String,
+    trace_run: String,
+    summary: String,
+}
+
+impl TableNames {
+    fn new(prefix: &str) -> TableNames {
+        TableNames {
+            prefix: prefix.to_owned(),
+            session_info: format!("{}bench_session_info", prefix),
+            run: format!("{}bench_run", prefix),
+            weight: format!("{}bench_weight", prefix),
+            trace: format!("{}bench_trace", prefix),
+            trace_run: format!("{}bench_trace_run", prefix),
+            summary: format!("{}bench_summary", prefix),
+        }
+    }
+}
+
+/// Whether `prefix` can go in front of a table name without quoting:
+/// ASCII letters, digits and underscores, not starting with a digit.
+pub fn is_valid_table_prefix(prefix: &str) -> bool {
+    prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !prefix.starts_with(|c: char| c.is_ascii_digit())
 }
 
 impl BenchDb {
     pub fn new(conn: Connection) -> rusqlite::Result<BenchDb> {
-        conn.execute_batch(SCHEMA)?;
-        Ok(BenchDb { conn })
+        BenchDb::new_with_prefix(conn, "")
+    }
+
+    /// Like `new`, but with `prefix` in front of all the table and index names,
+    /// so different kinds of experiments can share one database.
+    /// Panics if the prefix isn't valid according to `is_valid_table_prefix`.
+    pub fn new_with_prefix(conn: Connection, prefix: &str) -> rusqlite::Result<BenchDb> {
+        assert!(
+            is_valid_table_prefix(prefix),
+            "Invalid table prefix {:?}",
+            prefix
+        );
+        let schema = SCHEMA.replace("{prefix}", prefix);
+        let mut db = BenchDb {
+            conn,
+            tables: TableNames::new(prefix),
+        };
+        if db.conn.is_readonly(DatabaseName::Main)? {
+            if !has_schema(&db.conn, &schema)? {
+                return Err(rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_READONLY),
+                    Some(NEEDS_UPGRADE_MESSAGE.to_string()),
+                ));
+            }
+        } else {
+            db.conn.execute_batch(&schema)?;
+            db.migrate()?;
+        }
+        Ok(db)
+    }
+
+    /// Brings a database made by an older version up to date.
+    fn migrate(&mut self) -> rusqlite::Result<()> {
+        let tx = self.conn.transaction()?;
+        let has_column = |table: &str, name: &str| -> rusqlite::Result<bool> {
+            tx.query_row(
+                "select count(*) from pragma_table_info(?) where name = ?",
+                (table, name),
+                |row| row.get(0),
+            )
+        };
+        let run = &self.tables.run;
+        let has_start_word = has_column(run, "start_word")?;
+        let has_run_idx = has_column(run, "run_idx")?;
+        let has_stopped_by = has_column(run, "stopped_by")?;
+        let has_peak_rss = has_column(run, "peak_rss")?;
+        let has_section = has_column(run, "section")?;
+        let has_err_kind = has_column(run, "err_kind")?;
+        let has_found_word = has_column(run, "found_word")?;
+        let has_setup_secs = has_column(run, "setup_secs")?;
+        let has_first_result_secs = has_column(run, "first_result_secs")?;
+        let has_kept_scores = has_column(&self.tables.summary, "kept_scores")?;
+        let has_elapsed_cov = has_column(&self.tables.summary, "elapsed_cov")?;
+        let has_score_ci = has_column(&self.tables.summary, "score_ci_lo")?;
+        if !has_start_word {
+            tx.execute_batch(&format!(
+                r#"
+                alter table {run} add column start_word text;
+                alter table {run} add column targets text;
+                "#,
+                run = self.tables.run
+            ))?;
+            let benches: Vec<String> = tx
+                .prepare(&format!(
+                    "select distinct bench from {run}",
+                    run = self.tables.run
+                ))?
+                .query_map((), |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            let mut update = tx.prepare(&format!(
+                r#"
+                update {run}
+                set start_word = ?, targets = ?
+                where bench = ?
+                "#,
+                run = self.tables.run
+            ))?;
+            for bench in benches {
+                if let Some(key) = BenchKey::from_name(&bench) {
+                    update.execute((&key.start_word, targets_to_json(&key.targets), &bench))?;
+                }
+            }
+        }
+        if !has_run_idx {
+            tx.execute_batch(&format!(
+                r#"
+                alter table {run} add column run_idx int;
+                update {run}
+                  set run_idx = (select count(*)
+                                   from {run} earlier
+                                   where earlier.session_id = {run}.session_id
+                                     and earlier.bench = {run}.bench
+                                     and earlier.rowid < {run}.rowid);
+                "#,
+                run = self.tables.run
+            ))?;
+        }
+        if !has_stopped_by {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column stopped_by text",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_peak_rss {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column peak_rss int",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_section {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column section text",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_err_kind {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column err_kind text",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_found_word {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column found_word text",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_setup_secs {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column setup_secs real",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_first_result_secs {
+            tx.execute(
+                &format!(
+                    "alter table {run} add column first_result_secs real",
+                    run = self.tables.run
+                ),
+                (),
+            )?;
+        }
+        if !has_kept_scores {
+            tx.execute_batch(&format!(
+                r#"
+                alter table {summary} add column kept_scores text;
+                alter table {summary} add column kept_failures int;
+                "#,
+                summary = self.tables.summary
+            ))?;
+        }
+        if !has_elapsed_cov {
+            tx.execute(
+                &format!(
+                    "alter table {summary} add column elapsed_cov real",
+                    summary = self.tables.summary
+                ),
+                (),
+            )?;
+        }
+        if !has_score_ci {
+            tx.execute_batch(&format!(
+                r#"
+                alter table {summary} add column score_ci_lo real;
+                alter table {summary} add column score_ci_hi real;
+                "#,
+                summary = self.tables.summary
+            ))?;
+        }
+        tx.execute_batch(&format!(
+            r#"
+            create unique index if not exists {prefix}bench_run_run_idx
+              on {run} (session_id, bench, run_idx);
+            "#,
+            run = self.tables.run,
+            prefix = self.tables.prefix
+        ))?;
+        tx.commit()
     }
 
     pub fn new_session_id(&mut self) -> rusqlite::Result<BenchSessionId> {
-        let mut query = self.conn.prepare_cached(
+        let mut query = self.conn.prepare_cached(&format!(
             r#"
             select 1
-              from bench_session_info
+              from {session_info}
               where session_id = ?
               limit 1
             "#,
-        )?;
+            session_info = self.tables.session_info
+        ))?;
         let mut session_id = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -67,32 +377,76 @@ impl BenchDb {
         Ok(BenchSessionId(session_id))
     }
 
+    /// Adds a result as the bench's next run in the session.
+    /// This isn't idempotent: adding the same result again adds it as another run.
+    /// To add a run only once, like when copying it from elsewhere, use [`BenchDb::add_run`].
     pub fn add_result(
         &mut self,
         session_id: BenchSessionId,
-        bench: &str,
+        bench: &BenchKey,
         result: BenchResult,
     ) -> rusqlite::Result<()> {
-        let (found_at, err): (Option<usize>, Option<&str>) = match &result.found_index {
+        let run_idx: usize = self
+            .conn
+            .prepare_cached(&format!(
+                r#"
+                select coalesce(max(run_idx) + 1, 0)
+                  from {run}
+                  where session_id = ?
+                    and bench = ?
+                "#,
+                run = self.tables.run
+            ))?
+            .query_row((session_id, bench.name()), |row| row.get(0))?;
+        self.add_run(session_id, bench, run_idx, result)?;
+        Ok(())
+    }
+
+    /// Adds a result as the given run of the bench in the session.
+    /// If that run is already there, leaves it alone and returns false.
+    pub fn add_run(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &BenchKey,
+        run_idx: usize,
+        result: BenchResult,
+    ) -> rusqlite::Result<bool> {
+        let (found_at, err): (Option<usize>, Option<&SearchError>) = match &result.found_index {
             Ok(found_at) => (*found_at, None),
             Err(err) => (None, Some(err)),
         };
-        self.conn
-            .prepare_cached(
+        let num_inserted = self
+            .conn
+            .prepare_cached(&format!(
                 r#"
-                insert into bench_run
-                  (session_id, bench, duration, found_at, err)
-                  values(?, ?, ?, ?, ?)
+                insert into {run}
+                  (session_id, bench, duration, found_at, err, err_kind, start_word, targets,
+                   run_idx, stopped_by, peak_rss, found_word, setup_secs, first_result_secs)
+                  values(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                  on conflict do nothing
                 "#,
-            )?
+                run = self.tables.run
+            ))?
             .execute((
                 session_id,
-                bench,
+                bench.name(),
                 result.elapsed.as_secs_f64(),
                 found_at,
-                err,
+                err.map(|err| &err.message),
+                err.and_then(|err| err.kind.as_ref()),
+                &bench.start_word,
+                targets_to_json(&bench.targets),
+                run_idx,
+                result.stopped_by,
+                result.peak_rss,
+                &result.found_word,
+                result.setup.map(|setup| setup.as_secs_f64()),
+                result.first_result.map(|first| first.as_secs_f64()),
             ))?;
-        Ok(())
+        if num_inserted > 0 {
+            self.delete_summary(session_id, &bench.name())?;
+        }
+        Ok(num_inserted > 0)
     }
 
     pub fn get_results(
@@ -101,26 +455,663 @@ impl BenchDb {
         bench: &str,
     ) -> rusqlite::Result<Vec<BenchResult>> {
         let mut results = Vec::new();
-        let mut stmt = self.conn.prepare_cached(
+        self.for_each_result(session_id, bench, |result| {
+            results.push(result);
+            Ok(())
+        })?;
+        Ok(results)
+    }
+
+    /// Gets a bench's results along with their run indices, in order of those.
+    pub fn get_runs(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+    ) -> rusqlite::Result<Vec<(usize, BenchResult)>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select {columns}, run_idx
+                  from {run}
+                  where session_id = ?
+                    and bench = ?
+                  order by run_idx
+                "#,
+                columns = RESULT_COLUMNS,
+                run = self.tables.run
+            ))?
+            .query_map((session_id, bench), |row| {
+                Ok((row.get(RESULT_COLUMN_COUNT)?, result_from_row(row)?))
+            })?
+            .collect()
+    }
+
+    /// Calls `f` on each of a bench's results in turn, without collecting them all first.
+    pub fn for_each_result(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+        mut f: impl FnMut(BenchResult) -> rusqlite::Result<()>,
+    ) -> rusqlite::Result<()> {
+        let mut stmt = self.conn.prepare_cached(&format!(
             r#"
-            select duration, found_at, err
-              from bench_run
+            select {columns}
+              from {run}
               where session_id = ?
                 and bench = ?
             "#,
-        )?;
+            columns = RESULT_COLUMNS,
+            run = self.tables.run
+        ))?;
         let mut rows = stmt.query((session_id, bench))?;
         while let Some(row) = rows.next()? {
-            let err: Option<String> = row.get(2)?;
-            results.push(BenchResult {
-                found_index: match err {
-                    Some(err) => Err(err),
-                    None => Ok(row.get(1)?),
-                },
-                elapsed: Duration::from_secs_f64(row.get(0)?),
-            })
+            f(result_from_row(row)?)?;
         }
-        Ok(results)
+        Ok(())
+    }
+
+    /// Gets all of a session's results at once, by bench name,
+    /// for going through every bench without a query for each one.
+    pub fn get_all_results(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<BTreeMap<String, Vec<BenchResult>>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            r#"
+            select {columns}, bench
+              from {run}
+              where session_id = ?
+            "#,
+            columns = RESULT_COLUMNS,
+            run = self.tables.run
+        ))?;
+        let rows = stmt.query([session_id])?;
+        results_by_bench(rows)
+    }
+
+    /// Like [`get_all_results`](BenchDb::get_all_results), but leaves out the benches
+    /// with a summary from the given compiler, which don't need their results compiling again.
+    pub fn get_unsummarized_results(
+        &mut self,
+        session_id: BenchSessionId,
+        compiler: &str,
+    ) -> rusqlite::Result<BTreeMap<String, Vec<BenchResult>>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            r#"
+            select {columns}, bench
+              from {run}
+              where session_id = ?
+                and bench not in (
+                  select bench
+                    from {summary}
+                    where session_id = ?
+                      and compiler = ?)
+            "#,
+            columns = RESULT_COLUMNS,
+            run = self.tables.run,
+            summary = self.tables.summary
+        ))?;
+        let rows = stmt.query((session_id, session_id, compiler))?;
+        results_by_bench(rows)
+    }
+
+    /// Adds a traced run of a start word, with the words its search found in order.
+    pub fn add_trace(
+        &mut self,
+        session_id: BenchSessionId,
+        start_word: &str,
+        run: usize,
+        words: &[String],
+    ) -> rusqlite::Result<()> {
+        // A savepoint, so this works inside a transaction that's already going, like `load`'s
+        let tx = self.conn.savepoint()?;
+        {
+            let mut insert = tx.prepare_cached(&format!(
+                r#"
+                insert into {trace} (session_id, start_word, run, word_idx, word)
+                values (?, ?, ?, ?, ?)
+                "#,
+                trace = self.tables.trace
+            ))?;
+            for (word_idx, word) in words.iter().enumerate() {
+                insert.execute((session_id, start_word, run, word_idx, word))?;
+            }
+            // Even a run with no words gets a row here, so it isn't lost
+            tx.prepare_cached(&format!(
+                r#"
+                insert into {trace_run} (session_id, start_word, run, num_words)
+                values (?, ?, ?, ?)
+                "#,
+                trace_run = self.tables.trace_run
+            ))?
+            .execute((session_id, start_word, run, words.len()))?;
+        }
+        tx.commit()
+    }
+
+    /// Lists the start words a session has traced runs of.
+    pub fn get_traced_start_words(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Vec<String>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select start_word from {trace} where session_id = ?1
+                union
+                select start_word from {trace_run} where session_id = ?1
+                order by 1
+                "#,
+                trace = self.tables.trace,
+                trace_run = self.tables.trace_run
+            ))?
+            .query_map([session_id], |row| row.get(0))?
+            .collect()
+    }
+
+    /// Gets all the traced runs for a start word, indexed by run.
+    /// A run whose search found no words comes back empty.
+    pub fn get_traces(
+        &mut self,
+        session_id: BenchSessionId,
+        start_word: &str,
+    ) -> rusqlite::Result<Vec<Vec<String>>> {
+        let num_runs: Option<usize> = self
+            .conn
+            .prepare_cached(&format!(
+                r#"
+                select max(run) + 1
+                from {trace_run}
+                where session_id = ?
+                    and start_word = ?
+                "#,
+                trace_run = self.tables.trace_run
+            ))?
+            .query_row((session_id, start_word), |row| row.get(0))?;
+        let mut traces: Vec<Vec<String>> = Vec::new();
+        traces.resize_with(num_runs.unwrap_or(0), Vec::new);
+        let mut stmt = self.conn.prepare_cached(&format!(
+            r#"
+            select run, word
+            from {trace}
+            where session_id = ?
+                and start_word = ?
+            order by run, word_idx
+            "#,
+            trace = self.tables.trace
+        ))?;
+        let mut rows = stmt.query((session_id, start_word))?;
+        while let Some(row) = rows.next()? {
+            let run: usize = row.get(0)?;
+            if traces.len() <= run {
+                traces.resize_with(run + 1, Vec::new);
+            }
+            traces[run].push(row.get(1)?);
+        }
+        Ok(traces)
+    }
+
+    /// Lists a session's benches by their start words and targets.
+    /// Benches whose names couldn't be split up when migrating an older database are left out.
+    pub fn get_bench_keys(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Vec<BenchKey>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select distinct bench, start_word, targets
+                from {run}
+                where session_id = ?
+                    and start_word is not null
+                order by bench
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| {
+                let targets: String = row.get(2)?;
+                Ok(BenchKey {
+                    start_word: row.get(1)?,
+                    targets: serde_json::from_str(&targets).map_err(|err| {
+                        rusqlite::Error::FromSqlConversionFailure(2, Type::Text, Box::new(err))
+                    })?,
+                })
+            })?
+            .collect()
+    }
+
+    /// Lists the names of all of a session's benches, even ones that couldn't be split up.
+    pub fn get_bench_names(&mut self, session_id: BenchSessionId) -> rusqlite::Result<Vec<String>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select distinct bench
+                from {run}
+                where session_id = ?
+                order by bench
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| row.get(0))?
+            .collect()
+    }
+
+    /// The average and largest growth in resident memory over a session's searches, in bytes,
+    /// or `None` if it wasn't measured.
+    pub fn peak_rss_summary(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Option<(f64, u64)>> {
+        let (avg_rss, max_rss): (Option<f64>, Option<u64>) = self
+            .conn
+            .prepare_cached(&format!(
+                r#"
+                select avg(peak_rss), max(peak_rss)
+                from {run}
+                where session_id = ?
+                "#,
+                run = self.tables.run
+            ))?
+            .query_row([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(avg_rss.zip(max_rss))
+    }
+
+    /// The average time a session's results took to find their first word,
+    /// or `None` if none of them recorded it.
+    pub fn average_first_result(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Option<Duration>> {
+        let avg: Option<f64> = self
+            .conn
+            .prepare_cached(&format!(
+                r#"
+                select avg(first_result_secs)
+                from {run}
+                where session_id = ?
+                "#,
+                run = self.tables.run
+            ))?
+            .query_row([session_id], |row| row.get(0))?;
+        Ok(avg.map(Duration::from_secs_f64))
+    }
+
+    /// Lists the names of a session's benches that never found their target.
+    pub fn get_failed_benches(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Vec<String>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select bench
+                from {run}
+                where session_id = ?
+                group by bench
+                having sum(found_at is not null and err is null) = 0
+                order by bench
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| row.get(0))?
+            .collect()
+    }
+
+    /// Lists the benches that never found their target in any session,
+    /// with how many sessions each was run in.
+    pub fn get_never_found_benches(&mut self) -> rusqlite::Result<Vec<(String, usize)>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select bench, count(distinct session_id)
+                from {run}
+                group by bench
+                having sum(found_at is not null and err is null) = 0
+                order by bench
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+
+    /// Deletes a session's results that found their target, leaving only the failures.
+    /// Returns the number of results deleted.
+    pub fn delete_successful_results(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<usize> {
+        self.conn
+            .prepare_cached(&format!(
+                "delete from {summary} where session_id = ?",
+                summary = self.tables.summary
+            ))?
+            .execute([session_id])?;
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                delete from {run}
+                where session_id = ?
+                    and found_at is not null
+                    and err is null
+                "#,
+                run = self.tables.run
+            ))?
+            .execute([session_id])
+    }
+
+    /// Stores a bench's compiled result, with a description of the compiler that made it.
+    pub fn set_summary(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+        compiler: &str,
+        result: &CompiledBenchResult,
+    ) -> rusqlite::Result<()> {
+        self.conn
+            .prepare_cached(
+                &format!(r#"
+                insert or replace into {summary}
+                  (session_id, bench, score, found_lo, found_hi, elapsed_lo, elapsed_hi, errors, compiler,
+                   kept_scores, kept_failures, elapsed_cov, score_ci_lo, score_ci_hi)
+                  values(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#, summary = self.tables.summary),
+            )?
+            .execute((
+                session_id,
+                bench,
+                result.score.map(|score| score.as_secs_f64()),
+                result.found_index.as_ref().map(|range| *range.start()),
+                result.found_index.as_ref().map(|range| *range.end()),
+                result.elapsed.as_ref().map(|range| range.start().as_secs_f64()),
+                result.elapsed.as_ref().map(|range| range.end().as_secs_f64()),
+                serde_json::to_string(&result.errors).expect("Error serializing errors"),
+                compiler,
+                serde_json::to_string(
+                    &result
+                        .kept_scores
+                        .iter()
+                        .map(Duration::as_secs_f64)
+                        .collect::<Vec<_>>(),
+                )
+                .expect("Error serializing kept scores"),
+                result.kept_failures,
+                result.elapsed_cov,
+                result.score_ci.as_ref().map(|range| range.start().as_secs_f64()),
+                result.score_ci.as_ref().map(|range| range.end().as_secs_f64()),
+            ))?;
+        Ok(())
+    }
+
+    /// Gets a bench's stored compiled result, if there is one from the same compiler.
+    pub fn get_summary(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+        compiler: &str,
+    ) -> rusqlite::Result<Option<CompiledBenchResult>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select {columns}
+                  from {summary}
+                  where session_id = ?
+                    and bench = ?
+                    and compiler = ?
+                "#,
+                columns = SUMMARY_COLUMNS,
+                summary = self.tables.summary
+            ))?
+            .query_row((session_id, bench, compiler), summary_from_row)
+            .optional()
+    }
+
+    /// Gets all of a session's summaries from the given compiler, by bench name.
+    pub fn get_summaries(
+        &mut self,
+        session_id: BenchSessionId,
+        compiler: &str,
+    ) -> rusqlite::Result<BTreeMap<String, CompiledBenchResult>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            r#"
+            select {columns}, bench
+              from {summary}
+              where session_id = ?
+                and compiler = ?
+            "#,
+            columns = SUMMARY_COLUMNS,
+            summary = self.tables.summary
+        ))?;
+        let mut rows = stmt.query((session_id, compiler))?;
+        let mut summaries = BTreeMap::new();
+        while let Some(row) = rows.next()? {
+            summaries.insert(row.get(SUMMARY_COLUMN_COUNT)?, summary_from_row(row)?);
+        }
+        Ok(summaries)
+    }
+
+    fn delete_summary(&mut self, session_id: BenchSessionId, bench: &str) -> rusqlite::Result<()> {
+        self.conn
+            .prepare_cached(&format!(
+                "delete from {summary} where session_id = ? and bench = ?",
+                summary = self.tables.summary
+            ))?
+            .execute((session_id, bench))?;
+        Ok(())
+    }
+
+    /// Lists the sessions that ran a bench, oldest first.
+    pub fn get_bench_sessions(&mut self, bench: &str) -> rusqlite::Result<Vec<BenchSessionId>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select distinct session_id
+                  from {run}
+                  where bench = ?
+                  order by session_id
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([bench], |row| row.get(0))?
+            .collect()
+    }
+
+    /// For each bench that ever found its target, the session with its best compiled score,
+    /// and that score. Ties go to the earlier session.
+    /// This compiles the results of every bench in every session, so it reads the whole database.
+    pub fn best_scores(
+        &mut self,
+        compiler: &BenchResultCompiler,
+    ) -> rusqlite::Result<BTreeMap<String, (BenchSessionId, Duration)>> {
+        let session_benches: Vec<(BenchSessionId, String)> = self
+            .conn
+            .prepare(&format!(
+                r#"
+                select distinct session_id, bench
+                  from {run}
+                  order by session_id
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        let mut best: BTreeMap<String, (BenchSessionId, Duration)> = BTreeMap::new();
+        for (session_id, bench) in session_benches {
+            let Some(score) = compiler
+                .compile(self.get_results(session_id, &bench)?)
+                .score
+            else {
+                continue;
+            };
+            match best.get(&bench) {
+                Some(&(_, best_score)) if best_score <= score => {}
+                _ => {
+                    best.insert(bench, (session_id, score));
+                }
+            }
+        }
+        Ok(best)
+    }
+
+    /// The size of the database in bytes, going by its pages.
+    pub fn size_bytes(&self) -> rusqlite::Result<u64> {
+        self.conn.query_row(
+            "select page_count * page_size from pragma_page_count(), pragma_page_size()",
+            (),
+            |row| row.get(0),
+        )
+    }
+
+    /// Rebuilds the database to free up the space left by deleted data,
+    /// and updates the statistics that SQLite uses to plan queries.
+    pub fn vacuum(&mut self) -> rusqlite::Result<()> {
+        self.conn.execute_batch("vacuum; pragma optimize;")
+    }
+
+    /// Whether there's a session with this ID: one with any info, or any results,
+    /// even if it has nothing else.
+    pub fn session_exists(&mut self, session_id: BenchSessionId) -> rusqlite::Result<bool> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select exists(select 1 from {session_info} where session_id = :session_id)
+                    or exists(select 1 from {run} where session_id = :session_id)
+                "#,
+                session_info = self.tables.session_info,
+                run = self.tables.run
+            ))?
+            .query_row(named_params! {":session_id": session_id}, |row| row.get(0))
+    }
+
+    /// Lists every session with any info or results, oldest first.
+    pub fn all_session_ids(&mut self) -> rusqlite::Result<Vec<BenchSessionId>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select session_id from {session_info}
+                union
+                select session_id from {run}
+                order by 1
+                "#,
+                session_info = self.tables.session_info,
+                run = self.tables.run
+            ))?
+            .query_map((), |row| row.get(0))?
+            .collect()
+    }
+
+    /// Lists sessions, newest first, optionally only those starting at or after `since`.
+    /// Includes sessions with info but no results yet, like a run that crashed before storing them.
+    pub fn list_sessions(
+        &mut self,
+        since: Option<BenchSessionId>,
+    ) -> rusqlite::Result<Vec<SessionSummary>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select s.session_id, count(distinct r.bench)
+                  from (select session_id from {info}
+                        union
+                        select session_id from {run}) as s
+                  left join {run} as r
+                    on r.session_id = s.session_id
+                  where ?1 is null or s.session_id >= ?1
+                  group by s.session_id
+                  order by s.session_id desc
+                "#,
+                info = self.tables.session_info,
+                run = self.tables.run
+            ))?
+            .query_map((since,), |row| {
+                let id: BenchSessionId = row.get(0)?;
+                Ok(SessionSummary {
+                    id,
+                    start_time: id.start_time(),
+                    num_benches: row.get(1)?,
+                })
+            })?
+            .collect()
+    }
+
+    pub fn get_error_counts(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Vec<ErrorCount>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select err, count(*), min(bench)
+                  from {run}
+                  where session_id = ?
+                    and err is not null
+                  group by err
+                  order by 2 desc, 1
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| {
+                Ok(ErrorCount {
+                    err: row.get(0)?,
+                    count: row.get(1)?,
+                    example_bench: row.get(2)?,
+                })
+            })?
+            .collect()
+    }
+
+    /// Counts how many of a bench's successful results found each word,
+    /// most often first, with `None` for the ones that didn't record their word.
+    pub fn get_found_word_counts(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+    ) -> rusqlite::Result<Vec<(Option<String>, usize)>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select found_word, count(*)
+                  from {run}
+                  where session_id = ?
+                    and bench = ?
+                    and found_at is not null
+                    and err is null
+                  group by found_word
+                  order by 2 desc, 1
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map((session_id, bench), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+
+    /// Counts a session's errors by their kind, with an example message and bench for each.
+    /// Errors whose kind wasn't recorded are counted together, with a kind of `None`.
+    /// The example message is from the example bench, which sqlite picks out along with `min`.
+    pub fn get_error_kind_counts(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<Vec<ErrorKindCount>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select err_kind, count(*), err, min(bench)
+                  from {run}
+                  where session_id = ?
+                    and err is not null
+                  group by err_kind
+                  order by 2 desc, 1
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| {
+                Ok(ErrorKindCount {
+                    kind: row.get(0)?,
+                    count: row.get(1)?,
+                    example_err: row.get(2)?,
+                    example_bench: row.get(3)?,
+                })
+            })?
+            .collect()
     }
 
     pub fn set_info(
@@ -131,9 +1122,9 @@ impl BenchDb {
     ) -> rusqlite::Result<()> {
         assert_eq!(
             self.conn
-                .prepare_cached(
+                .prepare_cached(&format!(
                     r#"
-                    insert into bench_session_info
+                    insert into {session_info}
                       (session_id, name, value)
                       values(:session_id, :name, :value)
                       on conflict do update set
@@ -141,11 +1132,12 @@ impl BenchDb {
                         where session_id = :session_id
                           and name = :name
                     "#,
-                )?
+                    session_info = self.tables.session_info
+                ))?
                 .execute(named_params! {
                     ":session_id": session_id,
                     ":name": name,
-                    ":value": value,
+                    ":value": InfoValue(value),
                 })?,
             1
         );
@@ -157,41 +1149,291 @@ impl BenchDb {
         session_id: BenchSessionId,
     ) -> rusqlite::Result<BTreeMap<String, String>> {
         let mut map = BTreeMap::new();
-        let mut stmt = self.conn.prepare_cached(
+        let mut stmt = self.conn.prepare_cached(&format!(
             r#"
             select name, value
-              from bench_session_info
+              from {session_info}
               where session_id = ?
             "#,
-        )?;
+            session_info = self.tables.session_info
+        ))?;
         let mut rows = stmt.query([session_id])?;
         while let Some(row) = rows.next()? {
-            map.insert(row.get(0)?, row.get(1)?);
+            let value: StoredInfoValue = row.get(1)?;
+            map.insert(row.get(0)?, value.0);
         }
         Ok(map)
     }
 
+    /// Lists every info name set for any session.
+    pub fn info_names(&mut self) -> rusqlite::Result<BTreeSet<String>> {
+        self.conn
+            .prepare_cached(&format!(
+                "select distinct name from {session_info}",
+                session_info = self.tables.session_info
+            ))?
+            .query_map([], |row| row.get(0))?
+            .collect()
+    }
+
     pub fn get_info(&mut self, session_id: BenchSessionId, name: &str) -> rusqlite::Result<String> {
-        let value: Option<Option<String>> = self
+        let value: Option<StoredInfoValue> = self
             .conn
-            .prepare_cached(
+            .prepare_cached(&format!(
                 r#"
                 select value
-                from bench_session_info
+                from {session_info}
                 where name = ?
                     and session_id = ?
                 "#,
-            )?
+                session_info = self.tables.session_info
+            ))?
             .query_row((name, session_id), |row| row.get(0))
             .optional()?;
-        Ok(value.flatten().unwrap_or_default())
+        Ok(value.map(|value| value.0).unwrap_or_default())
+    }
+
+    pub fn set_weight(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+        weight: f64,
+    ) -> rusqlite::Result<()> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                insert or replace into {weight}
+                  (session_id, bench, weight)
+                  values(?, ?, ?)
+                "#,
+                weight = self.tables.weight
+            ))?
+            .execute((session_id, bench, weight))?;
+        Ok(())
+    }
+
+    /// Gets the weights set for a session's benches.
+    /// Benches not in the map have the default weight of 1.
+    pub fn get_weights(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<BTreeMap<String, f64>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select bench, weight
+                  from {weight}
+                  where session_id = ?
+                "#,
+                weight = self.tables.weight
+            ))?
+            .query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+
+    /// Puts the runs of a bench in a session into a section, as given by a `[section]` line.
+    pub fn set_section(
+        &mut self,
+        session_id: BenchSessionId,
+        bench: &str,
+        section: &str,
+    ) -> rusqlite::Result<()> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                update {run}
+                  set section = ?
+                  where session_id = ? and bench = ?
+                "#,
+                run = self.tables.run
+            ))?
+            .execute((section, session_id, bench))?;
+        Ok(())
+    }
+
+    /// Gets the sections of a session's benches.
+    /// Benches not in the map weren't in any section.
+    pub fn get_sections(
+        &mut self,
+        session_id: BenchSessionId,
+    ) -> rusqlite::Result<BTreeMap<String, String>> {
+        self.conn
+            .prepare_cached(&format!(
+                r#"
+                select distinct bench, section
+                  from {run}
+                  where session_id = ? and section is not null
+                "#,
+                run = self.tables.run
+            ))?
+            .query_map([session_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionSummary {
+    pub id: BenchSessionId,
+    pub start_time: SystemTime,
+    pub num_benches: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorCount {
+    pub err: String,
+    pub count: usize,
+    pub example_bench: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorKindCount {
+    pub kind: Option<String>,
+    pub count: usize,
+    pub example_err: String,
+    pub example_bench: String,
+}
+
+/// Info values with more than one line, and at least this many bytes, are stored compressed.
+const COMPRESS_INFO_MIN_LEN: usize = 1024;
+
+/// An info value to store, which is gzipped into a blob if it's long and multiline,
+/// like the text of the rules, or stored as text otherwise.
+struct InfoValue<'a>(&'a str);
+
+impl ToSql for InfoValue<'_> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        if self.0.len() >= COMPRESS_INFO_MIN_LEN && self.0.contains('\n') {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(self.0.as_bytes())
+                .and_then(|()| encoder.finish())
+                .map(ToSqlOutput::from)
+                .map_err(|err| rusqlite::Error::ToSqlConversionFailure(Box::new(err)))
+        } else {
+            self.0.to_sql()
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// An info value read back from the database:
+/// text as it is, or a blob that `InfoValue` compressed.
+struct StoredInfoValue(String);
+
+impl FromSql for StoredInfoValue {
+    fn column_result(value: ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value {
+            ValueRef::Blob(compressed) => {
+                let mut text = String::new();
+                GzDecoder::new(compressed)
+                    .read_to_string(&mut text)
+                    .map_err(|err| FromSqlError::Other(Box::new(err)))?;
+                Ok(StoredInfoValue(text))
+            }
+            _ => String::column_result(value).map(StoredInfoValue),
+        }
+    }
+}
+
+/// The columns of the run table that make up a [`BenchResult`], as read by [`result_from_row`].
+const RESULT_COLUMNS: &str = "duration, found_at, err, stopped_by, peak_rss, err_kind, \
+    found_word, setup_secs, first_result_secs";
+
+/// The columns of a stored summary, in the order [`summary_from_row`] reads them.
+const SUMMARY_COLUMNS: &str = "score, found_lo, found_hi, elapsed_lo, elapsed_hi, errors, \
+    kept_scores, kept_failures, elapsed_cov, score_ci_lo, score_ci_hi";
+
+/// How many columns are in [`SUMMARY_COLUMNS`], so that more can be selected after them.
+const SUMMARY_COLUMN_COUNT: usize = 11;
+
+/// How many columns are in [`RESULT_COLUMNS`], so that more can be selected after them.
+const RESULT_COLUMN_COUNT: usize = 9;
+
+/// Reads a result from a row that starts with [`RESULT_COLUMNS`].
+fn result_from_row(row: &Row) -> rusqlite::Result<BenchResult> {
+    let err: Option<String> = row.get(2)?;
+    Ok(BenchResult {
+        found_index: match err {
+            Some(message) => Err(SearchError {
+                kind: row.get(5)?,
+                message,
+            }),
+            None => Ok(row.get(1)?),
+        },
+        elapsed: Duration::from_secs_f64(row.get(0)?),
+        stopped_by: row.get(3)?,
+        peak_rss: row.get(4)?,
+        found_word: row.get(6)?,
+        setup: row.get::<_, Option<f64>>(7)?.map(Duration::from_secs_f64),
+        first_result: row.get::<_, Option<f64>>(8)?.map(Duration::from_secs_f64),
+    })
+}
+
+/// Reads a compiled result from a row that starts with [`SUMMARY_COLUMNS`].
+fn summary_from_row(row: &Row) -> rusqlite::Result<CompiledBenchResult> {
+    let score: Option<f64> = row.get(0)?;
+    let found_lo: Option<usize> = row.get(1)?;
+    let found_hi: Option<usize> = row.get(2)?;
+    let elapsed_lo: Option<f64> = row.get(3)?;
+    let elapsed_hi: Option<f64> = row.get(4)?;
+    let errors: String = row.get(5)?;
+    let score_ci_lo: Option<f64> = row.get(9)?;
+    let score_ci_hi: Option<f64> = row.get(10)?;
+    let kept_scores: Vec<f64> = match row.get::<_, Option<String>>(6)? {
+        Some(kept_scores) => serde_json::from_str(&kept_scores).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(6, Type::Text, Box::new(err))
+        })?,
+        None => vec![],
+    };
+    Ok(CompiledBenchResult {
+        score: score.map(Duration::from_secs_f64),
+        errors: serde_json::from_str(&errors).map_err(|err| {
+            rusqlite::Error::FromSqlConversionFailure(5, Type::Text, Box::new(err))
+        })?,
+        found_index: found_lo.zip(found_hi).map(|(lo, hi)| lo..=hi),
+        elapsed: elapsed_lo
+            .zip(elapsed_hi)
+            .map(|(lo, hi)| Duration::from_secs_f64(lo)..=Duration::from_secs_f64(hi)),
+        kept_scores: kept_scores
+            .into_iter()
+            .map(Duration::from_secs_f64)
+            .collect(),
+        kept_failures: row.get::<_, Option<usize>>(7)?.unwrap_or_default(),
+        elapsed_cov: row.get(8)?,
+        score_ci: score_ci_lo
+            .zip(score_ci_hi)
+            .map(|(lo, hi)| Duration::from_secs_f64(lo)..=Duration::from_secs_f64(hi)),
+    })
+}
+
+/// Groups the results from rows of [`RESULT_COLUMNS`] followed by the bench name.
+fn results_by_bench(mut rows: Rows) -> rusqlite::Result<BTreeMap<String, Vec<BenchResult>>> {
+    let mut results: BTreeMap<String, Vec<BenchResult>> = BTreeMap::new();
+    while let Some(row) = rows.next()? {
+        let bench: String = row.get(RESULT_COLUMN_COUNT)?;
+        results
+            .entry(bench)
+            .or_default()
+            .push(result_from_row(row)?);
+    }
+    Ok(results)
+}
+
+fn targets_to_json(targets: &BTreeSet<String>) -> String {
+    serde_json::to_string(targets).expect("Error serializing targets")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct BenchSessionId(u64);
 
 impl BenchSessionId {
+    /// The id a session started at `when` would have. Times before the epoch give the earliest id.
+    pub fn from_timestamp(when: SystemTime) -> BenchSessionId {
+        BenchSessionId(
+            when.duration_since(SystemTime::UNIX_EPOCH)
+                .map_or(0, |since_epoch| since_epoch.as_secs()),
+        )
+    }
+
     pub fn start_time(&self) -> SystemTime {
         SystemTime::UNIX_EPOCH + Duration::from_secs(self.0)
     }
@@ -209,16 +1451,60 @@ impl FromSql for BenchSessionId {
     }
 }
 
+/// A session ID that couldn't be parsed.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("invalid session ID {text:?}: {source}")]
+pub struct ParseSessionIdError {
+    text: String,
+    source: ParseIntError,
+}
+
+/// Parses the decimal form written by `Display`, which is also how `benchdb` takes session IDs
+/// on its command line. Anything that isn't a `u64`, like an empty, negative or too-large number,
+/// is an error.
 impl FromStr for BenchSessionId {
-    type Err = <u64 as FromStr>::Err;
+    type Err = ParseSessionIdError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        s.parse().map(BenchSessionId)
+        s.parse()
+            .map(BenchSessionId)
+            .map_err(|source| ParseSessionIdError {
+                text: s.to_string(),
+                source,
+            })
     }
 }
 
+/// Writes the ID in decimal.
 impl Display for BenchSessionId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.0.fmt(f)
     }
 }
+
+impl SearchLimit {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SearchLimit::Timeout => "timeout",
+            SearchLimit::MaxIndex => "max_index",
+        }
+    }
+}
+
+impl ToSql for SearchLimit {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        self.as_str().to_sql()
+    }
+}
+
+impl FromSql for SearchLimit {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        match value.as_str()? {
+            "timeout" => Ok(SearchLimit::Timeout),
+            "max_index" => Ok(SearchLimit::MaxIndex),
+            other => Err(rusqlite::types::FromSqlError::Other(
+                format!("Unknown search limit {:?}", other).into(),
+            )),
+        }
+    }
+}
@@ -1,15 +1,24 @@
 // Copyright © 2022 Daniel Getz
 // SPDX-License-Identifier: MIT
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::{Duration, SystemTime};
 
 use quickcheck::QuickCheck;
 use quickcheck_macros::quickcheck;
 use rand::seq::SliceRandom;
 use rand::thread_rng;
-use rusqlite::Connection;
+use rusqlite::{Connection, OpenFlags};
 
-use super::{BenchDb, BenchResult, BenchSessionId};
+use super::{
+    is_valid_table_prefix, BenchDb, BenchKey, BenchResult, BenchSessionId, ErrorCount,
+    ErrorKindCount, SessionSummary, NEEDS_UPGRADE_MESSAGE,
+};
+use crate::{BenchResultCompiler, SearchError};
+
+fn key(name: &str) -> BenchKey {
+    BenchKey::from_name(name).unwrap()
+}
 
 #[test]
 fn test_db_new_idempotent() -> rusqlite::Result<()> {
@@ -20,6 +29,354 @@ fn test_db_new_idempotent() -> rusqlite::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_db_table_prefixes_separate() -> rusqlite::Result<()> {
+    let session = BenchSessionId(1);
+    let mut db_a = BenchDb::new_with_prefix(Connection::open_in_memory()?, "exp_a_")?;
+    db_a.add_result(
+        session,
+        &key("a = b"),
+        BenchResult::success(0, Duration::ZERO),
+    )?;
+    db_a.set_info(session, "tag", "a")?;
+
+    let mut db_b = BenchDb::new_with_prefix(db_a.conn, "exp_b_")?;
+    assert_eq!(db_b.list_sessions(None)?, vec![]);
+    assert_eq!(db_b.get_info(session, "tag")?, "");
+    db_b.add_result(
+        session,
+        &key("c = d"),
+        BenchResult::success(1, Duration::ZERO),
+    )?;
+    db_b.add_result(
+        session,
+        &key("c = d"),
+        BenchResult::success(2, Duration::ZERO),
+    )?;
+    db_b.set_info(session, "tag", "b")?;
+
+    let mut db_a = BenchDb::new_with_prefix(db_b.conn, "exp_a_")?;
+    assert_eq!(db_a.get_bench_keys(session)?, vec![key("a = b")]);
+    assert_eq!(db_a.get_results(session, "a = b")?.len(), 1);
+    assert_eq!(db_a.get_info(session, "tag")?, "a");
+
+    let mut db_b = BenchDb::new_with_prefix(db_a.conn, "exp_b_")?;
+    assert_eq!(db_b.get_bench_keys(session)?, vec![key("c = d")]);
+    assert_eq!(db_b.get_results(session, "c = d")?.len(), 2);
+    assert_eq!(db_b.get_info(session, "tag")?, "b");
+
+    let mut db = BenchDb::new(db_b.conn)?;
+    assert_eq!(db.list_sessions(None)?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_is_valid_table_prefix() {
+    for prefix in ["", "exp_", "A1_b", "_"] {
+        assert!(is_valid_table_prefix(prefix), "{:?}", prefix);
+    }
+    for prefix in ["1a_", "a b", "a-b", "a;drop table x;", "a\"", "ã_"] {
+        assert!(!is_valid_table_prefix(prefix), "{:?}", prefix);
+    }
+}
+
+#[test]
+fn test_db_migrate_structured_bench() -> rusqlite::Result<()> {
+    let conn = Connection::open_in_memory()?;
+    conn.execute_batch(
+        r#"
+        create table bench_run (
+          session_id int not null,
+          bench text not null,
+          duration real not null,
+          found_at int,
+          err text);
+        insert into bench_run values (1, 'a = b | c', 0.5, 2, null);
+        insert into bench_run values (1, 'a = b | c', 0.7, 3, null);
+        insert into bench_run values (1, 'unparseable', 0.1, null, 'oops');
+        "#,
+    )?;
+    let mut db = BenchDb::new(conn)?;
+    assert_eq!(
+        db.get_bench_keys(BenchSessionId(1))?,
+        vec![key("a = b | c")]
+    );
+    assert_eq!(db.get_results(BenchSessionId(1), "a = b | c")?.len(), 2);
+    let run_idxs: Vec<usize> = db
+        .conn
+        .prepare("select run_idx from bench_run where bench = 'a = b | c' order by rowid")?
+        .query_map((), |row| row.get(0))?
+        .collect::<rusqlite::Result<_>>()?;
+    assert_eq!(run_idxs, vec![0, 1]);
+    db.add_result(
+        BenchSessionId(1),
+        &key("a = b | c"),
+        BenchResult::success(0, Duration::ZERO),
+    )?;
+    assert_eq!(db.get_results(BenchSessionId(1), "a = b | c")?.len(), 3);
+    let db = BenchDb::new(db.conn)?;
+    db.conn.close().unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_add_result_structured_bench() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let bench = BenchKey {
+        start_word: "ação".into(),
+        targets: BTreeSet::from(["acção".into(), "*ão".into(), "a\"b".into()]),
+    };
+    let result = BenchResult::success(0, Duration::ZERO);
+    db.add_result(sid, &bench, result.clone())?;
+    db.add_result(sid, &bench, result.clone())?;
+    db.add_result(sid, &key("x = y"), result.clone())?;
+    assert_eq!(db.get_bench_keys(sid)?, vec![bench.clone(), key("x = y")]);
+    assert_eq!(
+        db.get_results(sid, &bench.name())?,
+        vec![result.clone(), result]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_add_run_idempotent() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let bench = key("a = b");
+    let result = BenchResult::success(1, Duration::from_millis(500));
+    assert!(db.add_run(sid, &bench, 0, result.clone())?);
+    assert!(!db.add_run(sid, &bench, 0, result.clone())?);
+    assert_eq!(db.get_results(sid, "a = b")?, vec![result.clone()]);
+    assert!(db.add_run(sid, &bench, 1, result.clone())?);
+    db.add_result(sid, &bench, result.clone())?;
+    assert_eq!(db.get_results(sid, "a = b")?.len(), 3);
+    assert!(!db.add_run(sid, &bench, 2, result)?);
+    Ok(())
+}
+
+#[test]
+fn test_peak_rss_round_trip() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let measured = BenchResult::success(2, Duration::from_millis(100)).with_peak_rss(Some(4096));
+    let unmeasured = BenchResult::success(2, Duration::from_millis(100));
+    db.add_result(sid, &key("a = b"), measured.clone())?;
+    db.add_result(sid, &key("a = b"), unmeasured.clone())?;
+    let results = db.get_results(sid, "a = b")?;
+    assert_eq!(results.len(), 2);
+    assert!(results.contains(&measured));
+    assert!(results.contains(&unmeasured));
+    Ok(())
+}
+
+#[test]
+fn test_summary_matches_compiled() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let compiler = BenchResultCompiler::new(Duration::from_millis(125), 0.25);
+    for (found, millis) in [(Some(1), 500), (Some(3), 250), (None, 1000), (Some(0), 750)] {
+        let result = match found {
+            Some(index) => BenchResult::success(index, Duration::from_millis(millis)),
+            None => BenchResult {
+                found_index: Ok(None),
+                elapsed: Duration::from_millis(millis),
+                stopped_by: None,
+                peak_rss: None,
+                found_word: None,
+                setup: None,
+                first_result: None,
+            },
+        };
+        db.add_result(sid, &key("a = b"), result)?;
+    }
+    db.add_result(
+        sid,
+        &key("c = d"),
+        BenchResult {
+            found_index: Err("oops".into()),
+            elapsed: Duration::ZERO,
+            stopped_by: None,
+            peak_rss: None,
+            found_word: None,
+            setup: None,
+            first_result: None,
+        },
+    )?;
+
+    for bench in ["a = b", "c = d"] {
+        let compiled = compiler.compile(db.get_results(sid, bench)?);
+        assert_eq!(db.get_summary(sid, bench, &compiler.describe())?, None);
+        db.set_summary(sid, bench, &compiler.describe(), &compiled)?;
+        assert_eq!(
+            db.get_summary(sid, bench, &compiler.describe())?,
+            Some(compiled)
+        );
+    }
+
+    let other_compiler = BenchResultCompiler::new(Duration::from_millis(125), 0.5);
+    assert_eq!(
+        db.get_summary(sid, "a = b", &other_compiler.describe())?,
+        None
+    );
+
+    db.add_result(sid, &key("a = b"), BenchResult::success(0, Duration::ZERO))?;
+    assert_eq!(db.get_summary(sid, "a = b", &compiler.describe())?, None);
+    assert!(db
+        .get_summary(sid, "c = d", &compiler.describe())?
+        .is_some());
+    db.delete_successful_results(sid)?;
+    assert_eq!(db.get_summary(sid, "c = d", &compiler.describe())?, None);
+    Ok(())
+}
+
+#[test]
+fn test_read_only_old_schema() -> rusqlite::Result<()> {
+    let path = std::env::temp_dir().join(format!(
+        "buscaluso-bench-test-read-only-{}.sqlite3",
+        std::process::id()
+    ));
+    // The schema of the first version, before any migrations
+    let conn = Connection::open(&path)?;
+    conn.execute_batch(
+        r#"
+        create table bench_session_info (
+          session_id int not null,
+          name text not null,
+          value text not null,
+          primary key (session_id, name));
+        create table bench_run (
+          session_id int not null,
+          bench text not null,
+          duration real not null,
+          found_at int,
+          err text);
+        insert into bench_run values (1, 'a = b', 0.5, 3, null);
+        "#,
+    )?;
+    conn.close().unwrap();
+    let read_only = || Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY);
+
+    let err = BenchDb::new(read_only()?).err().unwrap();
+    assert!(err.to_string().contains(NEEDS_UPGRADE_MESSAGE), "{}", err);
+
+    BenchDb::new(Connection::open(&path)?)?
+        .conn
+        .close()
+        .unwrap();
+    let mut db = BenchDb::new(read_only()?)?;
+    assert_eq!(
+        db.get_results(BenchSessionId(1), "a = b")?,
+        vec![BenchResult::success(3, Duration::from_millis(500))]
+    );
+    assert_eq!(db.get_bench_keys(BenchSessionId(1))?, vec![key("a = b")]);
+    db.conn.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_vacuum() -> rusqlite::Result<()> {
+    let path = std::env::temp_dir().join(format!(
+        "buscaluso-bench-test-vacuum-{}.sqlite3",
+        std::process::id()
+    ));
+    let mut db = BenchDb::new(Connection::open(&path)?)?;
+    let session = BenchSessionId(1);
+    for i in 0..2000 {
+        db.add_result(
+            session,
+            &key(&format!("word{} = target{}", i, i)),
+            BenchResult::success(i, Duration::ZERO),
+        )?;
+    }
+    db.delete_successful_results(session)?;
+    let size_before = db.size_bytes()?;
+    db.vacuum()?;
+    assert!(db.size_bytes()? < size_before);
+
+    db.add_result(
+        session,
+        &key("a = b"),
+        BenchResult::not_found(Duration::ZERO),
+    )?;
+    assert_eq!(db.get_failed_benches(session)?, vec!["a = b".to_string()]);
+    db.conn.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    Ok(())
+}
+
+#[test]
+fn test_get_bench_sessions() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for session in [3, 1, 2] {
+        db.add_result(
+            BenchSessionId(session),
+            &key("a = b"),
+            BenchResult::success(0, Duration::ZERO),
+        )?;
+    }
+    db.add_result(
+        BenchSessionId(1),
+        &key("a = b"),
+        BenchResult::success(1, Duration::ZERO),
+    )?;
+    db.add_result(
+        BenchSessionId(4),
+        &key("c = d"),
+        BenchResult::success(0, Duration::ZERO),
+    )?;
+    assert_eq!(
+        db.get_bench_sessions("a = b")?,
+        vec![BenchSessionId(1), BenchSessionId(2), BenchSessionId(3)]
+    );
+    assert_eq!(db.get_bench_sessions("x = y")?, vec![]);
+    Ok(())
+}
+
+#[test]
+fn test_best_scores() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let found = |index, millis| BenchResult::success(index, Duration::from_millis(millis));
+    let not_found = BenchResult {
+        found_index: Ok(None),
+        elapsed: Duration::from_secs(1),
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    db.add_result(BenchSessionId(1), &key("a = b"), found(0, 500))?;
+    db.add_result(BenchSessionId(1), &key("c = d"), found(1, 250))?;
+    db.add_result(BenchSessionId(1), &key("e = f"), not_found.clone())?;
+    db.add_result(BenchSessionId(1), &key("g = h"), found(0, 250))?;
+    db.add_result(BenchSessionId(2), &key("a = b"), found(0, 750))?;
+    db.add_result(BenchSessionId(2), &key("c = d"), found(0, 250))?;
+    db.add_result(BenchSessionId(2), &key("e = f"), not_found)?;
+    db.add_result(BenchSessionId(2), &key("g = h"), found(0, 250))?;
+
+    let compiler = BenchResultCompiler::new(Duration::from_millis(125), 0.0);
+    assert_eq!(
+        db.best_scores(&compiler)?,
+        BTreeMap::from([
+            (
+                "a = b".to_string(),
+                (BenchSessionId(1), Duration::from_millis(500))
+            ),
+            (
+                "c = d".to_string(),
+                (BenchSessionId(2), Duration::from_millis(250))
+            ),
+            (
+                "g = h".to_string(),
+                (BenchSessionId(1), Duration::from_millis(250))
+            ),
+        ])
+    );
+    Ok(())
+}
+
 #[test]
 fn test_new_session_id_different() -> rusqlite::Result<()> {
     let mut db = BenchDb::new(Connection::open_in_memory()?)?;
@@ -42,6 +399,64 @@ fn test_set_and_get_info(name: String, value: String) -> rusqlite::Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_set_info_overwrites_tag() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    db.set_info(sid, "tag", "baseline")?;
+    assert_eq!(db.get_info(sid, "tag")?, "baseline");
+    db.set_info(sid, "tag", "new-heuristic-v2")?;
+    assert_eq!(db.get_info(sid, "tag")?, "new-heuristic-v2");
+    assert_eq!(
+        db.get_all_info(sid)?,
+        BTreeMap::from([("tag".to_string(), "new-heuristic-v2".to_string())])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_info_compressed() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let rules: String = (0..200).map(|i| format!("ss{} > ç{}\n", i, i)).collect();
+    db.set_info(sid, "search_rules", &rules)?;
+    db.set_info(sid, "machine", "laptop")?;
+    let value_type = |db: &BenchDb, name: &str| -> rusqlite::Result<String> {
+        db.conn.query_row(
+            "select typeof(value) from bench_session_info where name = ?",
+            [name],
+            |row| row.get(0),
+        )
+    };
+    assert_eq!(value_type(&db, "search_rules")?, "blob");
+    assert_eq!(value_type(&db, "machine")?, "text");
+    assert_eq!(db.get_info(sid, "search_rules")?, rules);
+    assert_eq!(
+        db.get_all_info(sid)?,
+        BTreeMap::from([
+            ("machine".to_string(), "laptop".to_string()),
+            ("search_rules".to_string(), rules),
+        ])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_info_uncompressed_old_row() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let rules: String = (0..200).map(|i| format!("ss{} > ç{}\n", i, i)).collect();
+    db.conn.execute(
+        "insert into bench_session_info values (1, 'search_rules', ?)",
+        [&rules],
+    )?;
+    assert_eq!(db.get_info(BenchSessionId(1), "search_rules")?, rules);
+    assert_eq!(
+        db.get_all_info(BenchSessionId(1))?,
+        BTreeMap::from([("search_rules".to_string(), rules)])
+    );
+    Ok(())
+}
+
 #[quickcheck]
 fn test_get_all_info(values: BTreeMap<String, String>) -> rusqlite::Result<()> {
     let mut db = BenchDb::new(Connection::open_in_memory()?)?;
@@ -59,18 +474,22 @@ fn test_add_get_results() {
         let mut rng = thread_rng();
         let mut db = BenchDb::new(Connection::open_in_memory()?)?;
         let sid = db.new_session_id()?;
-        let mut all_results: Vec<(&str, BenchResult)> = Vec::new();
-        for (bench, results) in &bench_results {
+        let mut all_results: Vec<(BenchKey, BenchResult)> = Vec::new();
+        for (start_word, results) in &bench_results {
+            let bench = BenchKey {
+                start_word: start_word.clone(),
+                targets: BTreeSet::from(["target".to_string()]),
+            };
             for result in results {
-                all_results.push((bench, result.clone()));
+                all_results.push((bench.clone(), result.clone()));
             }
         }
         all_results.shuffle(&mut rng);
         for (bench, result) in all_results {
-            db.add_result(sid, bench, result)?;
+            db.add_result(sid, &bench, result)?;
         }
-        for (bench, mut expected) in bench_results {
-            let mut from_db = db.get_results(sid, &bench)?;
+        for (start_word, mut expected) in bench_results {
+            let mut from_db = db.get_results(sid, &format!("{} = target", start_word))?;
             expected.sort();
             from_db.sort();
             assert_eq!(from_db, expected);
@@ -82,8 +501,487 @@ fn test_add_get_results() {
         .quickcheck(add_get_results as fn(_) -> rusqlite::Result<()>);
 }
 
+#[quickcheck]
+fn test_for_each_result_same_as_get_results(results: Vec<BenchResult>) -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    for result in &results {
+        db.add_result(sid, &key("a = b"), result.clone())?;
+        db.add_result(sid, &key("c = d"), result.clone())?;
+    }
+    let mut visited = Vec::new();
+    db.for_each_result(sid, "a = b", |result| {
+        visited.push(result);
+        Ok(())
+    })?;
+    let mut from_get = db.get_results(sid, "a = b")?;
+    visited.sort();
+    from_get.sort();
+    assert_eq!(visited, from_get);
+    let mut expected = results;
+    expected.sort();
+    assert_eq!(visited, expected);
+    Ok(())
+}
+
+#[test]
+fn test_get_summaries_and_unsummarized_results() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = BenchSessionId(1);
+    let compiler = BenchResultCompiler::new(Duration::from_millis(250), 0.0);
+    let other_compiler = BenchResultCompiler::new(Duration::from_millis(125), 0.0);
+    for bench in ["a = b", "c = d"] {
+        db.add_result(sid, &key(bench), BenchResult::success(1, Duration::ZERO))?;
+    }
+    db.add_result(
+        BenchSessionId(2),
+        &key("a = b"),
+        BenchResult::success(0, Duration::ZERO),
+    )?;
+    let compiled = compiler.compile(db.get_results(sid, "a = b")?);
+    db.set_summary(sid, "a = b", &compiler.describe(), &compiled)?;
+
+    assert_eq!(
+        db.get_summaries(sid, &compiler.describe())?,
+        BTreeMap::from([("a = b".to_string(), compiled)])
+    );
+    assert!(db
+        .get_summaries(sid, &other_compiler.describe())?
+        .is_empty());
+    assert_eq!(
+        db.get_unsummarized_results(sid, &compiler.describe())?,
+        BTreeMap::from([("c = d".to_string(), db.get_results(sid, "c = d")?)])
+    );
+    assert_eq!(
+        db.get_unsummarized_results(sid, &other_compiler.describe())?,
+        db.get_all_results(sid)?
+    );
+    Ok(())
+}
+
+#[quickcheck]
+fn test_get_all_results_same_as_get_results(
+    results_a: Vec<BenchResult>,
+    results_b: Vec<BenchResult>,
+) -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    for result in &results_a {
+        db.add_result(BenchSessionId(1), &key("a = b"), result.clone())?;
+        db.add_result(BenchSessionId(2), &key("a = b"), result.clone())?;
+    }
+    for result in &results_b {
+        db.add_result(BenchSessionId(1), &key("c = d"), result.clone())?;
+    }
+    let mut all = db.get_all_results(BenchSessionId(1))?;
+    assert_eq!(
+        all.keys().cloned().collect::<Vec<_>>(),
+        db.get_bench_names(BenchSessionId(1))?
+    );
+    for (bench, from_all) in &mut all {
+        let mut from_get = db.get_results(BenchSessionId(1), bench)?;
+        from_all.sort();
+        from_get.sort();
+        assert_eq!(from_all, &from_get);
+    }
+    assert!(db.get_all_results(BenchSessionId(3))?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_for_each_result_stops_on_error() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    for i in 0..5 {
+        db.add_result(sid, &key("a = b"), BenchResult::success(i, Duration::ZERO))?;
+    }
+    let mut num_visited = 0;
+    let outcome = db.for_each_result(sid, "a = b", |_| {
+        num_visited += 1;
+        if num_visited == 2 {
+            Err(rusqlite::Error::InvalidQuery)
+        } else {
+            Ok(())
+        }
+    });
+    assert!(outcome.is_err());
+    assert_eq!(num_visited, 2);
+    Ok(())
+}
+
+#[test]
+fn test_list_sessions() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    assert!(db.list_sessions(None)?.is_empty());
+    let sid1 = db.new_session_id()?;
+    let sid2 = BenchSessionId(sid1.0 + 10);
+    let result = BenchResult::success(0, Duration::ZERO);
+    db.add_result(sid1, &key("a = b"), result.clone())?;
+    db.add_result(sid1, &key("a = b"), result.clone())?;
+    db.add_result(sid1, &key("c = d"), result.clone())?;
+    db.add_result(sid2, &key("a = b"), result)?;
+    let info_only = BenchSessionId(sid1.0 + 5);
+    db.set_info(info_only, "status", "running")?;
+    db.set_info(sid1, "tag", "first")?;
+    assert_eq!(
+        db.list_sessions(None)?,
+        vec![
+            SessionSummary {
+                id: sid2,
+                start_time: sid2.start_time(),
+                num_benches: 1,
+            },
+            SessionSummary {
+                id: info_only,
+                start_time: info_only.start_time(),
+                num_benches: 0,
+            },
+            SessionSummary {
+                id: sid1,
+                start_time: sid1.start_time(),
+                num_benches: 2,
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_session_id_from_timestamp() {
+    let sid = BenchSessionId(1_700_000_000);
+    assert_eq!(BenchSessionId::from_timestamp(sid.start_time()), sid);
+    assert_eq!(
+        BenchSessionId::from_timestamp(sid.start_time() + Duration::from_millis(999)),
+        sid
+    );
+    assert_eq!(
+        BenchSessionId::from_timestamp(SystemTime::UNIX_EPOCH - Duration::from_secs(1)),
+        BenchSessionId(0)
+    );
+}
+
+#[test]
+fn test_list_sessions_since() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let cutoff = BenchSessionId(1_700_000_000);
+    let result = BenchResult::success(0, Duration::ZERO);
+    for id in [cutoff.0 - 1, cutoff.0, cutoff.0 + 1] {
+        db.add_result(BenchSessionId(id), &key("a = b"), result.clone())?;
+    }
+    let ids = |sessions: Vec<SessionSummary>| -> Vec<BenchSessionId> {
+        sessions.into_iter().map(|s| s.id).collect()
+    };
+    assert_eq!(
+        ids(db.list_sessions(Some(cutoff))?),
+        vec![BenchSessionId(cutoff.0 + 1), cutoff]
+    );
+    assert_eq!(
+        ids(db.list_sessions(Some(BenchSessionId::from_timestamp(
+            cutoff.start_time() + Duration::from_secs(2)
+        )))?),
+        vec![]
+    );
+    assert_eq!(db.list_sessions(None)?.len(), 3);
+    Ok(())
+}
+
+#[test]
+fn test_session_exists() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let info_only = db.new_session_id()?;
+    db.set_info(info_only, "machine", "laptop")?;
+    let populated = BenchSessionId(info_only.0 + 1);
+    db.set_info(populated, "machine", "laptop")?;
+    db.add_result(
+        populated,
+        &key("a = b"),
+        BenchResult::success(0, Duration::ZERO),
+    )?;
+    assert!(!db.session_exists(BenchSessionId(info_only.0 + 2))?);
+    assert!(db.session_exists(info_only)?);
+    assert!(db.session_exists(populated)?);
+    Ok(())
+}
+
+#[test]
+fn test_get_error_counts() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let other_sid = BenchSessionId(sid.0 + 1);
+    let error = |err: &str| BenchResult {
+        found_index: Err(err.into()),
+        elapsed: Duration::ZERO,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    db.add_result(sid, &key("b = x"), error("bad rule"))?;
+    db.add_result(sid, &key("a = x"), error("bad rule"))?;
+    db.add_result(sid, &key("a = x"), error("bad rule"))?;
+    db.add_result(sid, &key("c = x"), error("no word"))?;
+    db.add_result(sid, &key("c = x"), BenchResult::success(0, Duration::ZERO))?;
+    db.add_result(other_sid, &key("c = x"), error("no word"))?;
+    assert_eq!(
+        db.get_error_counts(sid)?,
+        vec![
+            ErrorCount {
+                err: "bad rule".into(),
+                count: 3,
+                example_bench: "a = x".into(),
+            },
+            ErrorCount {
+                err: "no word".into(),
+                count: 1,
+                example_bench: "c = x".into(),
+            },
+        ]
+    );
+    assert!(db.get_error_counts(BenchSessionId(sid.0 + 2))?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_get_error_kind_counts() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let error = |kind: Option<&str>, message: &str| {
+        BenchResult::error(
+            SearchError {
+                kind: kind.map(str::to_string),
+                message: message.into(),
+            },
+            Duration::ZERO,
+        )
+    };
+    db.add_result(sid, &key("b = x"), error(Some("BadRule"), "bad rule 1"))?;
+    db.add_result(sid, &key("a = x"), error(Some("BadRule"), "bad rule 2"))?;
+    db.add_result(sid, &key("c = x"), error(Some("BadRule"), "bad rule 3"))?;
+    db.add_result(sid, &key("c = x"), error(Some("NoWord"), "no word"))?;
+    db.add_result(sid, &key("d = x"), error(None, "old error"))?;
+    assert_eq!(
+        db.get_results(sid, "a = x")?[0].error_kind(),
+        Some("BadRule")
+    );
+    assert_eq!(
+        db.get_error_kind_counts(sid)?,
+        vec![
+            ErrorKindCount {
+                kind: Some("BadRule".into()),
+                count: 3,
+                example_err: "bad rule 2".into(),
+                example_bench: "a = x".into(),
+            },
+            ErrorKindCount {
+                kind: None,
+                count: 1,
+                example_err: "old error".into(),
+                example_bench: "d = x".into(),
+            },
+            ErrorKindCount {
+                kind: Some("NoWord".into()),
+                count: 1,
+                example_err: "no word".into(),
+                example_bench: "c = x".into(),
+            },
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_get_failed_benches() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let success = BenchResult::success(3, Duration::from_secs(1));
+    let not_found = BenchResult {
+        found_index: Ok(None),
+        elapsed: Duration::from_secs(2),
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    let error = BenchResult {
+        found_index: Err("oops".into()),
+        elapsed: Duration::ZERO,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    db.add_result(sid, &key("a = b"), success.clone())?;
+    db.add_result(sid, &key("a = b"), not_found.clone())?;
+    db.add_result(sid, &key("c = d"), not_found.clone())?;
+    db.add_result(sid, &key("c = d"), not_found)?;
+    db.add_result(sid, &key("e = f"), error.clone())?;
+    db.add_result(sid, &key("g = h"), success.clone())?;
+    db.add_result(BenchSessionId(sid.0 + 1), &key("x = y"), error)?;
+    assert_eq!(db.get_failed_benches(sid)?, vec!["c = d", "e = f"]);
+    assert!(db.get_failed_benches(BenchSessionId(sid.0 + 2))?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_get_never_found_benches() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let other_sid = BenchSessionId(sid.0 + 1);
+    let error = BenchResult {
+        found_index: Err("oops".into()),
+        elapsed: Duration::ZERO,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    assert!(db.get_never_found_benches()?.is_empty());
+    db.add_result(sid, &key("a = b"), BenchResult::not_found(Duration::ZERO))?;
+    db.add_result(
+        other_sid,
+        &key("a = b"),
+        BenchResult::success(2, Duration::ZERO),
+    )?;
+    db.add_result(sid, &key("c = d"), BenchResult::not_found(Duration::ZERO))?;
+    db.add_result(other_sid, &key("c = d"), error.clone())?;
+    db.add_result(other_sid, &key("e = f"), error)?;
+    assert_eq!(
+        db.get_never_found_benches()?,
+        vec![("c = d".to_string(), 2), ("e = f".to_string(), 1)]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_delete_successful_results() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let other_sid = BenchSessionId(sid.0 + 1);
+    let success = BenchResult::success(3, Duration::from_secs(1));
+    let not_found = BenchResult {
+        found_index: Ok(None),
+        elapsed: Duration::from_secs(2),
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    let error = BenchResult {
+        found_index: Err("oops".into()),
+        elapsed: Duration::ZERO,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    db.add_result(sid, &key("a = b"), success.clone())?;
+    db.add_result(sid, &key("a = b"), not_found.clone())?;
+    db.add_result(sid, &key("c = d"), success.clone())?;
+    db.add_result(sid, &key("e = f"), error.clone())?;
+    db.add_result(other_sid, &key("a = b"), success.clone())?;
+
+    assert_eq!(db.delete_successful_results(sid)?, 2);
+    assert_eq!(db.get_results(sid, "a = b")?, vec![not_found]);
+    assert!(db.get_results(sid, "c = d")?.is_empty());
+    assert_eq!(db.get_results(sid, "e = f")?, vec![error]);
+    assert_eq!(db.get_results(other_sid, "a = b")?, vec![success]);
+    assert_eq!(db.delete_successful_results(sid)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_add_get_traces() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let words = |ws: &[&str]| -> Vec<String> { ws.iter().map(|w| w.to_string()).collect() };
+    assert!(db.get_traces(sid, "ação")?.is_empty());
+    db.add_trace(sid, "ação", 0, &words(&["ação", "asão", "acção"]))?;
+    db.add_trace(sid, "ação", 1, &words(&[]))?;
+    db.add_trace(sid, "outro", 0, &words(&["outra"]))?;
+    db.add_trace(sid, "ação", 2, &words(&["ação", "assão"]))?;
+    db.add_trace(sid, "ação", 3, &words(&[]))?;
+    db.add_trace(BenchSessionId(sid.0 + 1), "ação", 0, &words(&["x"]))?;
+    assert_eq!(
+        db.get_traces(sid, "ação")?,
+        vec![
+            words(&["ação", "asão", "acção"]),
+            words(&[]),
+            words(&["ação", "assão"]),
+            words(&[]),
+        ]
+    );
+    assert_eq!(db.get_traces(sid, "outro")?, vec![words(&["outra"])]);
+    assert_eq!(db.get_traced_start_words(sid)?, words(&["ação", "outro"]));
+    Ok(())
+}
+
+#[test]
+fn test_set_get_weights() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    assert!(db.get_weights(sid)?.is_empty());
+    db.set_weight(sid, "a = b", 3.0)?;
+    db.set_weight(sid, "c = d", 2.0)?;
+    db.set_weight(sid, "c = d", 0.5)?;
+    db.set_weight(BenchSessionId(sid.0 + 1), "e = f", 4.0)?;
+    assert_eq!(
+        db.get_weights(sid)?,
+        BTreeMap::from([("a = b".to_string(), 3.0), ("c = d".to_string(), 0.5)])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_set_get_sections() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    for bench in ["a = b", "c = d", "e = f"] {
+        db.add_result(
+            sid,
+            &key(bench),
+            BenchResult::new(Ok(Some(0)), Duration::ZERO),
+        )?;
+    }
+    db.add_result(
+        sid,
+        &key("a = b"),
+        BenchResult::new(Ok(None), Duration::ZERO),
+    )?;
+    assert!(db.get_sections(sid)?.is_empty());
+    db.set_section(sid, "a = b", "nouns")?;
+    db.set_section(sid, "c = d", "verbs")?;
+    db.set_section(BenchSessionId(sid.0 + 1), "e = f", "verbs")?;
+    assert_eq!(
+        db.get_sections(sid)?,
+        BTreeMap::from([
+            ("a = b".to_string(), "nouns".to_string()),
+            ("c = d".to_string(), "verbs".to_string())
+        ])
+    );
+    Ok(())
+}
+
 #[quickcheck]
 fn test_session_id_display_fromstr(id: u64) {
     let session_id = BenchSessionId(id);
     assert_eq!(format!("{}", session_id).parse(), Ok(session_id));
 }
+
+#[test]
+fn test_session_id_fromstr_errors() {
+    for bad in ["", " ", "abc", "12a", "1.5", "-1", "18446744073709551616"] {
+        let err = bad.parse::<BenchSessionId>().unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with(&format!("invalid session ID {:?}: ", bad)),
+            "{}",
+            err
+        );
+    }
+    assert_eq!("18446744073709551615".parse(), Ok(BenchSessionId(u64::MAX)));
+}
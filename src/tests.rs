@@ -5,31 +5,307 @@ use super::*;
 
 use quickcheck::{Arbitrary, TestResult};
 use quickcheck_macros::*;
+use rusqlite::Connection;
 
 impl Arbitrary for BenchResult {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
         let found_index: Result<Option<u16>, String> = Result::arbitrary(g);
+        let stopped_by = match found_index {
+            Ok(None) => *g
+                .choose(&[
+                    None,
+                    Some(SearchLimit::Timeout),
+                    Some(SearchLimit::MaxIndex),
+                ])
+                .unwrap(),
+            _ => None,
+        };
+        let found_word = match found_index {
+            Ok(Some(_)) => Option::arbitrary(g),
+            _ => None,
+        };
         BenchResult {
-            found_index: found_index.map(|opt| opt.map(|n16| n16 as usize)),
+            found_index: found_index
+                .map(|opt| opt.map(|n16| n16 as usize))
+                .map_err(|message| SearchError {
+                    kind: Option::arbitrary(g),
+                    message,
+                }),
             elapsed: Duration::arbitrary(g),
+            stopped_by,
+            peak_rss: None,
+            found_word,
+            setup: None,
+            first_result: None,
         }
     }
 }
 
+fn arbitrary_trim_fraction(g: &mut quickcheck::Gen) -> f64 {
+    let mut fraction = f64::arbitrary(g);
+    while !fraction.is_finite() {
+        fraction = f64::arbitrary(g);
+    }
+    fraction.abs() % 0.5
+}
+
 impl Arbitrary for BenchResultCompiler {
     fn arbitrary(g: &mut quickcheck::Gen) -> Self {
-        let mut drop_fraction = f64::arbitrary(g);
-        while !drop_fraction.is_finite() {
-            drop_fraction = f64::arbitrary(g);
-        }
-        drop_fraction = drop_fraction.abs() % 1.0;
         BenchResultCompiler {
             index_equivalent: Duration::arbitrary(g),
-            drop_fraction,
+            trim: TrimSpec::new(arbitrary_trim_fraction(g), arbitrary_trim_fraction(g)),
+            index_cost: IndexCost::Linear,
+            bootstrap_resamples: u8::arbitrary(g).into(),
+            bootstrap_seed: u64::arbitrary(g),
+        }
+    }
+}
+
+/// Generates durations of whole milliseconds, which survive a round trip through seconds as `f64`.
+fn arbitrary_millis(g: &mut quickcheck::Gen) -> Duration {
+    Duration::from_millis(u32::arbitrary(g).into())
+}
+
+fn arbitrary_nonzero_u8(g: &mut quickcheck::Gen) -> NonZeroU8 {
+    NonZeroU8::new(u8::arbitrary(g)).unwrap_or(NonZeroU8::MIN)
+}
+
+/// TOML integers are signed 64-bit, so keep counts well inside that.
+fn arbitrary_count(g: &mut quickcheck::Gen) -> usize {
+    u32::arbitrary(g) as usize
+}
+
+impl Arbitrary for BenchRunCfg {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        let arbitrary_path = |g: &mut quickcheck::Gen| PathBuf::from(String::arbitrary(g));
+        BenchRunCfg {
+            machine: Option::arbitrary(g),
+            repeat: arbitrary_nonzero_u8(g),
+            repeat_failed: arbitrary_nonzero_u8(g),
+            warmup: u8::arbitrary(g),
+            retry_errors: u8::arbitrary(g),
+            timeout: arbitrary_millis(g),
+            timeout_check_interval: u32::arbitrary(g),
+            max_index: bool::arbitrary(g).then(|| arbitrary_count(g)),
+            max_index_fraction: bool::arbitrary(g).then(|| f64::from(u8::arbitrary(g)) / 256.0),
+            timeout_scale: *g.choose(&[0.5, 1.0, 1.5, 2.0]).unwrap(),
+            case_insensitive: bool::arbitrary(g),
+            total_budget: bool::arbitrary(g).then(|| arbitrary_millis(g)),
+            trace: bool::arbitrary(g),
+            trace_words: Vec::arbitrary(g),
+            measure_memory: bool::arbitrary(g),
+            exclude_setup: bool::arbitrary(g),
+            verbose: u8::arbitrary(g),
+            add_unaccented: bool::arbitrary(g),
+            normalization: *g
+                .choose(&[Normalization::None, Normalization::Nfc, Normalization::Nfd])
+                .unwrap(),
+            max_samples_per_bench: bool::arbitrary(g).then(|| arbitrary_count(g)),
+            rules_file: bool::arbitrary(g).then(|| arbitrary_path(g)),
+            dict_file: bool::arbitrary(g).then(|| arbitrary_path(g)),
+            bench_files: bool::arbitrary(g).then(|| {
+                (0..u8::arbitrary(g) % 4)
+                    .map(|_| arbitrary_path(g))
+                    .collect()
+            }),
+            out_db: arbitrary_path(g),
+        }
+    }
+}
+
+impl Arbitrary for CompiledBenchResult {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        let found = bool::arbitrary(g);
+        let found_index = get_range([u32::arbitrary(g) as usize, u32::arbitrary(g) as usize]);
+        let elapsed = get_range([arbitrary_millis(g), arbitrary_millis(g)]);
+        CompiledBenchResult {
+            score: if found {
+                Some(arbitrary_millis(g))
+            } else {
+                None
+            },
+            errors: Vec::arbitrary(g),
+            found_index: found_index.filter(|_| found),
+            elapsed: elapsed.filter(|_| found),
+            kept_scores: (0..u8::arbitrary(g) % 8)
+                .map(|_| arbitrary_millis(g))
+                .collect(),
+            kept_failures: (u8::arbitrary(g) % 4).into(),
+            elapsed_cov: Option::<u8>::arbitrary(g)
+                .map(|n| n as f64 / 16.0)
+                .filter(|_| found),
+            score_ci: get_range([arbitrary_millis(g), arbitrary_millis(g)]).filter(|_| found),
         }
     }
 }
 
+#[test]
+fn test_sample_std_dev() {
+    assert_eq!(sample_std_dev(&[]), None);
+    assert_eq!(sample_std_dev(&[2.0]), None);
+    assert_eq!(sample_std_dev(&[3.0, 3.0, 3.0]), Some(0.0));
+    assert_eq!(sample_std_dev(&[2.0, 4.0, 6.0]), Some(2.0));
+}
+
+#[test]
+fn test_coefficient_of_variation() {
+    assert_eq!(coefficient_of_variation(&[]), None);
+    assert_eq!(coefficient_of_variation(&[2.0]), None);
+    assert_eq!(coefficient_of_variation(&[0.0, 0.0]), None);
+    assert_eq!(coefficient_of_variation(&[3.0, 3.0, 3.0]), Some(0.0));
+    let cov = coefficient_of_variation(&[1.0, 3.0]).unwrap();
+    assert!((cov - 2.0f64.sqrt() / 2.0).abs() < 1e-12, "{}", cov);
+}
+
+#[test]
+fn test_compile_elapsed_cov() {
+    let compiler = BenchResultCompiler::new(Duration::ZERO, 0.0);
+    let found = |millis| BenchResult::success(0, Duration::from_millis(millis));
+    let compiled = compiler.compile([
+        found(100),
+        found(300),
+        BenchResult::not_found(Duration::from_secs(9)),
+    ]);
+    let cov = compiled.elapsed_cov.unwrap();
+    assert!((cov - 2.0f64.sqrt() / 2.0).abs() < 1e-9, "{}", cov);
+    assert_eq!(compiler.compile([found(100)]).elapsed_cov, None);
+}
+
+#[test]
+fn test_compile_score_ci() {
+    let compiler = BenchResultCompiler::new(Duration::ZERO, 0.2)
+        .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED);
+    let found = |millis| BenchResult::success(0, Duration::from_millis(millis));
+    let ci_width = |millis: &[u64]| {
+        let ci = compiler
+            .compile(millis.iter().map(|&m| found(m)))
+            .score_ci
+            .unwrap();
+        *ci.end() - *ci.start()
+    };
+
+    let same = compiler.compile((0..10).map(|_| found(200)));
+    let ci = same.score_ci.unwrap();
+    assert_eq!(ci.start(), ci.end());
+    assert_eq!(*ci.start(), same.score.unwrap());
+
+    let narrow = ci_width(&[190, 195, 200, 205, 210, 190, 195, 200, 205, 210]);
+    let wide = ci_width(&[100, 150, 200, 250, 300, 100, 150, 200, 250, 300]);
+    assert!(narrow > Duration::ZERO);
+    assert!(wide > narrow, "{:?} <= {:?}", wide, narrow);
+
+    // The same results always get the same interval, unless resampled differently
+    let results: Vec<BenchResult> = [100, 180, 230, 300].map(found).into();
+    let ci = compiler.compile_ref(&results).score_ci;
+    assert_eq!(compiler.compile_ref(&results).score_ci, ci);
+    assert_ne!(
+        compiler
+            .clone()
+            .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, 1)
+            .compile_ref(&results)
+            .score_ci,
+        ci
+    );
+    assert_eq!(
+        compiler.with_bootstrap(0, 0).compile_ref(&results).score_ci,
+        None
+    );
+}
+
+#[test]
+fn test_compile_score_ci_off_by_default() {
+    let compiler = BenchResultCompiler::new(Duration::ZERO, 0.2);
+    let results =
+        [100, 180, 230, 300].map(|millis| BenchResult::success(0, Duration::from_millis(millis)));
+    assert_eq!(compiler.compile_ref(&results).score_ci, None);
+    let bootstrapped = compiler
+        .clone()
+        .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED);
+    assert!(bootstrapped.compile_ref(&results).score_ci.is_some());
+    assert_eq!(bootstrapped.describe(), compiler.describe());
+}
+
+#[test]
+fn test_compile_score_ci_unbounded() {
+    let compiler = BenchResultCompiler::new(Duration::ZERO, 0.0)
+        .with_bootstrap(DEFAULT_BOOTSTRAP_RESAMPLES, DEFAULT_BOOTSTRAP_SEED);
+    let compiled = compiler.compile([
+        BenchResult::success(0, Duration::from_millis(100)),
+        BenchResult::not_found(Duration::from_secs(1)),
+    ]);
+    assert_eq!(compiled.score_ci, None);
+}
+
+#[quickcheck]
+fn test_compiled_result_serialize_deserialize(
+    compiled: CompiledBenchResult,
+) -> Result<(), toml::ser::Error> {
+    assert_eq!(toml::from_str(&toml::to_string(&compiled)?), Ok(compiled));
+    Ok(())
+}
+
+#[test]
+fn test_compiled_result_serialize_format() -> Result<(), toml::ser::Error> {
+    let compiled = CompiledBenchResult {
+        score: Some(Duration::from_secs_f64(1.5)),
+        errors: vec![],
+        found_index: Some(3..=7),
+        elapsed: Some(Duration::from_secs_f64(0.25)..=Duration::from_secs(1)),
+        kept_scores: vec![],
+        kept_failures: 0,
+        elapsed_cov: None,
+        score_ci: None,
+    };
+    assert_eq!(
+        toml::to_string(&compiled)?,
+        "score = 1.5\nerrors = []\nfound_index = [3, 7]\nelapsed = [0.25, 1.0]\n"
+    );
+    Ok(())
+}
+
+fn compile_millis(millis: impl IntoIterator<Item = u64>) -> CompiledBenchResult {
+    BenchResultCompiler::new(Duration::from_millis(125), 0.0).compile(
+        millis
+            .into_iter()
+            .map(|ms| BenchResult::success(0, Duration::from_millis(ms))),
+    )
+}
+
+#[test]
+fn test_significantly_better_clearly_different() {
+    let fast = compile_millis([100, 110, 120, 105, 115, 125, 95, 130]);
+    let slow = compile_millis([200, 210, 190, 220, 205, 215, 195, 230]);
+    assert!(fast.is_significantly_better_than(&slow, 0.05));
+    assert!(fast.is_significantly_better_than(&slow, 0.001));
+    assert!(!slow.is_significantly_better_than(&fast, 0.05));
+
+    let failed = BenchResultCompiler::new(Duration::from_millis(125), 0.0)
+        .compile((0..8).map(|_| BenchResult::not_found(Duration::from_secs(1))));
+    assert_eq!(failed.kept_failures, 8);
+    assert!(slow.is_significantly_better_than(&failed, 0.05));
+    assert!(!failed.is_significantly_better_than(&slow, 0.05));
+    assert!(!failed.is_significantly_better_than(&failed, 0.05));
+}
+
+#[test]
+fn test_significantly_better_overlapping() {
+    let a = compile_millis([100, 130, 160, 190, 220, 250, 280, 310]);
+    let b = compile_millis([115, 145, 175, 205, 235, 265, 295, 325]);
+    assert!(a.is_better_than(&b));
+    assert!(!a.is_significantly_better_than(&b, 0.05));
+    assert!(!b.is_significantly_better_than(&a, 0.05));
+    assert!(!a.is_significantly_better_than(&a, 0.05));
+}
+
+#[test]
+fn test_significantly_better_needs_samples() {
+    let fast = compile_millis([100, 110, 120, 105, 115, 125, 95, 130]);
+    let mut slow = compile_millis([200, 210, 190, 220, 205, 215, 195, 230]);
+    slow.kept_scores.clear();
+    assert!(!slow.has_samples());
+    assert!(!fast.is_significantly_better_than(&slow, 0.05));
+}
+
 #[test]
 fn test_runcfg_deserialize() {
     assert_eq!(
@@ -42,19 +318,100 @@ fn test_runcfg_deserialize() {
             "#
         ),
         Ok(BenchRunCfg {
-            repeat: 7,
-            repeat_failed: 2,
+            repeat: NonZeroU8::new(7).unwrap(),
+            repeat_failed: NonZeroU8::new(2).unwrap(),
+            warmup: 1,
+            retry_errors: 0,
             timeout: Duration::from_secs_f64(8.3),
+            total_budget: None,
+            timeout_check_interval: 64,
+            max_index: None,
+            max_index_fraction: None,
+            timeout_scale: 1.0,
+            case_insensitive: false,
+            trace: false,
+            trace_words: vec![],
+            measure_memory: false,
+            exclude_setup: false,
             verbose: 1,
+            add_unaccented: true,
+            normalization: Normalization::Nfc,
+            max_samples_per_bench: None,
             rules_file: None,
             dict_file: None,
-            bench_file: None,
+            bench_files: None,
             out_db: default_out_db(),
             machine: None,
         })
     );
 }
 
+#[test]
+fn test_runcfg_deserialize_timeout_units() -> Result<(), toml::de::Error> {
+    let timeout = |value: &str| -> Result<Duration, toml::de::Error> {
+        let cfg: BenchRunCfg = toml::from_str(&format!(
+            "repeat = 1\nrepeat_failed = 1\ntimeout = {}\n",
+            value
+        ))?;
+        Ok(cfg.timeout)
+    };
+    assert_eq!(timeout("8.3")?, Duration::from_secs_f64(8.3));
+    assert_eq!(timeout("8")?, Duration::from_secs(8));
+    assert_eq!(timeout(r#""8300ms""#)?, Duration::from_millis(8300));
+    assert_eq!(timeout(r#""8.3s""#)?, Duration::from_secs_f64(8.3));
+    assert_eq!(timeout(r#""2m""#)?, Duration::from_secs(120));
+    for bad in [r#""2h""#, r#""ms""#, r#""fast""#, "-1"] {
+        assert!(timeout(bad).is_err(), "{}", bad);
+    }
+    let err = timeout(r#""2h""#).unwrap_err().to_string();
+    assert!(err.contains("expected a number and ms, s or m"), "{}", err);
+
+    let cfg: BenchRunCfg =
+        toml::from_str("repeat = 1\nrepeat_failed = 1\ntimeout = 1\ntotal_budget = \"90m\"\n")?;
+    assert_eq!(cfg.total_budget, Some(Duration::from_secs(90 * 60)));
+    Ok(())
+}
+
+#[test]
+fn test_parse_duration() {
+    assert_eq!(parse_duration("250ms"), Ok(Duration::from_millis(250)));
+    assert_eq!(parse_duration(" 1.5 s "), Ok(Duration::from_millis(1500)));
+    assert_eq!(parse_duration("0.5m"), Ok(Duration::from_secs(30)));
+    assert_eq!(parse_duration("3"), Ok(Duration::from_secs(3)));
+    assert!(parse_duration("").is_err());
+    assert!(parse_duration("3 minutes").is_err());
+    assert!(parse_duration("-3s").is_err());
+}
+
+#[test]
+fn test_runcfg_deserialize_bench_files() -> Result<(), toml::de::Error> {
+    let required = "repeat = 7\nrepeat_failed = 2\ntimeout = 8.3\n";
+    let cfg: BenchRunCfg = toml::from_str(&format!("{}bench_file = \"old.txt\"\n", required))?;
+    assert_eq!(cfg.bench_files, Some(vec![PathBuf::from("old.txt")]));
+    let cfg: BenchRunCfg = toml::from_str(&format!(
+        "{}bench_files = [\"a.txt\", \"b.txt\"]\n",
+        required
+    ))?;
+    assert_eq!(
+        cfg.bench_files,
+        Some(vec![PathBuf::from("a.txt"), PathBuf::from("b.txt")])
+    );
+    let cfg: BenchRunCfg = toml::from_str(required)?;
+    assert_eq!(cfg.bench_files, None);
+    Ok(())
+}
+
+#[test]
+fn test_runcfg_deserialize_zero_repeats() {
+    for config in [
+        "repeat = 0\nrepeat_failed = 2\ntimeout = 8.3\n",
+        "repeat = 7\nrepeat_failed = 0\ntimeout = 8.3\n",
+    ] {
+        let err = toml::from_str::<BenchRunCfg>(config).unwrap_err();
+        assert!(err.to_string().contains("nonzero"), "{}", err);
+    }
+}
+
 #[test]
 fn test_runcfg_deserialize_verbose_default() -> Result<(), toml::de::Error> {
     let cfg: BenchRunCfg = toml::from_str(
@@ -68,16 +425,37 @@ fn test_runcfg_deserialize_verbose_default() -> Result<(), toml::de::Error> {
     Ok(())
 }
 
+#[quickcheck]
+fn test_runcfg_serialize_deserialize_arbitrary(cfg: BenchRunCfg) -> Result<(), toml::ser::Error> {
+    assert_eq!(toml::from_str(&toml::to_string(&cfg)?), Ok(cfg));
+    Ok(())
+}
+
 #[test]
 fn test_runcfg_serialize_deserialize() -> Result<(), toml::ser::Error> {
     let cfg = BenchRunCfg {
-        repeat: 20,
-        repeat_failed: 1,
+        repeat: NonZeroU8::new(20).unwrap(),
+        repeat_failed: NonZeroU8::new(1).unwrap(),
+        warmup: 1,
+        retry_errors: 0,
         verbose: 5,
+        add_unaccented: false,
+        normalization: Normalization::Nfd,
+        max_samples_per_bench: None,
+        total_budget: Some(Duration::from_secs(600)),
+        timeout_check_interval: 16,
+        max_index: None,
+        max_index_fraction: None,
+        timeout_scale: 1.0,
+        case_insensitive: false,
+        trace: true,
+        trace_words: vec!["ação".to_string()],
+        measure_memory: false,
+        exclude_setup: false,
         timeout: Duration::from_secs_f64(2.5),
         rules_file: None,
         dict_file: None,
-        bench_file: None,
+        bench_files: None,
         out_db: default_out_db(),
         machine: None,
     };
@@ -85,11 +463,356 @@ fn test_runcfg_serialize_deserialize() -> Result<(), toml::ser::Error> {
     Ok(())
 }
 
+#[test]
+fn test_runcfg_traces_normalized() -> Result<(), toml::de::Error> {
+    // The trace word is written decomposed, but the start words are normalized to NFC
+    let mut cfg: BenchRunCfg = toml::from_str(
+        "repeat = 1\nrepeat_failed = 1\ntimeout = 1\ntrace = true\ntrace_words = [\"ac\u{327}a\u{303}o\"]\n",
+    )?;
+    assert!(cfg.traces("ação"));
+    assert!(!cfg.traces("acao"));
+    cfg.trace = false;
+    assert!(!cfg.traces("ação"));
+    Ok(())
+}
+
+#[test]
+fn test_runcfg_deserialize_total_budget() -> Result<(), toml::de::Error> {
+    let cfg: BenchRunCfg = toml::from_str(
+        r#"
+        repeat = 7
+        repeat_failed = 3
+        timeout = 8.3
+        total_budget = 600
+        "#,
+    )?;
+    assert_eq!(cfg.total_budget, Some(Duration::from_secs(600)));
+    Ok(())
+}
+
+#[test]
+fn test_is_over_budget() {
+    assert!(!is_over_budget(None, Duration::from_secs(1_000_000)));
+    assert!(!is_over_budget(
+        Some(Duration::from_secs(10)),
+        Duration::from_secs(9)
+    ));
+    assert!(is_over_budget(
+        Some(Duration::from_secs(10)),
+        Duration::from_secs(10)
+    ));
+    assert!(is_over_budget(
+        Some(Duration::from_secs(10)),
+        Duration::from_secs(11)
+    ));
+    assert!(is_over_budget(Some(Duration::ZERO), Duration::ZERO));
+}
+
+#[test]
+fn test_runcfg_deserialize_timeout_check_interval_default() -> Result<(), toml::de::Error> {
+    let cfg: BenchRunCfg = toml::from_str("repeat = 7\nrepeat_failed = 3\ntimeout = 8.3\n")?;
+    assert_eq!(cfg.timeout_check_interval, 64);
+    Ok(())
+}
+
+#[quickcheck]
+fn test_periodic_check_timeout_overshoot(interval: u8, timeout: u16) -> bool {
+    // Each step takes one time unit; the timeout is checked only when it's due.
+    let interval = interval as u32;
+    let timeout = timeout as u32;
+    let mut check = PeriodicCheck::new(interval);
+    let mut step = 0;
+    loop {
+        step += 1;
+        if check.is_due() && step >= timeout {
+            break;
+        }
+    }
+    // The first step at which the timeout could be noticed
+    let first_over = timeout.max(1);
+    step >= first_over && step - first_over < interval.max(1)
+}
+
+#[test]
+fn test_periodic_check_interval() {
+    let mut check = PeriodicCheck::new(3);
+    let due: Vec<bool> = (0..7).map(|_| check.is_due()).collect();
+    assert_eq!(due, [false, false, true, false, false, true, false]);
+    let mut check = PeriodicCheck::new(0);
+    assert!(check.is_due());
+    assert!(check.is_due());
+}
+
+#[test]
+fn test_search_limits_max_index() -> Result<(), toml::de::Error> {
+    let run_cfg: BenchRunCfg = toml::from_str(
+        "repeat = 1\nrepeat_failed = 1\ntimeout = 1000\ntimeout_check_interval = 1\nmax_index = 2\n",
+    )?;
+    let start_time = Instant::now();
+    let mut limits = SearchLimits::new(&run_cfg, start_time);
+    let stops: Vec<Option<SearchLimit>> = (0..5)
+        .map(|num_words| limits.check(num_words, || start_time))
+        .collect();
+    assert_eq!(
+        stops,
+        [
+            None,
+            None,
+            None,
+            Some(SearchLimit::MaxIndex),
+            Some(SearchLimit::MaxIndex)
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_effective_max_index() -> Result<(), toml::de::Error> {
+    let cfg = |extra: &str| -> Result<BenchRunCfg, toml::de::Error> {
+        toml::from_str(&format!(
+            "repeat = 1\nrepeat_failed = 1\ntimeout = 10\n{}",
+            extra
+        ))
+    };
+    assert_eq!(cfg("")?.effective_max_index(1000), None);
+    assert_eq!(cfg("max_index = 50\n")?.effective_max_index(1000), Some(50));
+    assert_eq!(
+        cfg("max_index_fraction = 0.1\n")?.effective_max_index(1005),
+        Some(100)
+    );
+    let both = cfg("max_index = 50\nmax_index_fraction = 0.1\n")?;
+    assert_eq!(both.effective_max_index(1000), Some(50));
+    assert_eq!(both.effective_max_index(200), Some(20));
+    assert_eq!(both.effective_max_index(0), Some(0));
+    Ok(())
+}
+
+#[test]
+fn test_effective_timeout() -> Result<(), toml::de::Error> {
+    let base = "repeat = 1\nrepeat_failed = 1\ntimeout = 10\n";
+    let cfg: BenchRunCfg = toml::from_str(base)?;
+    assert_eq!(cfg.timeout_scale, 1.0);
+    assert_eq!(cfg.effective_timeout(), Duration::from_secs(10));
+    let cfg: BenchRunCfg = toml::from_str(&format!("{}timeout_scale = 1.5\n", base))?;
+    assert_eq!(cfg.effective_timeout(), Duration::from_secs(15));
+    assert_eq!(
+        cfg.effective_timeout(),
+        cfg.timeout.mul_f64(cfg.timeout_scale)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_search_limits_timeout() -> Result<(), toml::de::Error> {
+    let run_cfg: BenchRunCfg =
+        toml::from_str("repeat = 1\nrepeat_failed = 1\ntimeout = 1\ntimeout_check_interval = 3\n")?;
+    assert_eq!(run_cfg.max_index, None);
+    let start_time = Instant::now();
+    let mut limits = SearchLimits::new(&run_cfg, start_time);
+    let mut num_clock_reads = 0;
+    let mut stops = Vec::new();
+    for num_words in 0..4 {
+        stops.push(limits.check(num_words * 1000, || {
+            num_clock_reads += 1;
+            start_time + Duration::from_secs(2)
+        }));
+    }
+    assert_eq!(stops, [None, None, Some(SearchLimit::Timeout), None]);
+    assert_eq!(num_clock_reads, 1);
+
+    let mut limits = SearchLimits::new(&run_cfg, start_time);
+    let stops: Vec<Option<SearchLimit>> = (0..6)
+        .map(|_| limits.check(0, || start_time + Duration::from_millis(999)))
+        .collect();
+    assert_eq!(stops, [None; 6]);
+    Ok(())
+}
+
+/// Runs a search over `words`, with `None` for steps that find no word, and a clock
+/// that moves forward a second every time it's read.
+fn run_mock_search(
+    targets: &[&[&str]],
+    words: &[Option<&str>],
+    config: &str,
+) -> (SearchEnd, usize, Vec<(String, usize)>) {
+    let run_cfg: BenchRunCfg =
+        toml::from_str(&format!("repeat = 1\nrepeat_failed = 1\n{}", config)).unwrap();
+    let mut runner = BenchRunner::new();
+    for target in targets {
+        runner.add_targets(&target.iter().map(|t| t.to_string()).collect());
+    }
+    let start_time = Instant::now();
+    let mut limits = SearchLimits::new(&run_cfg, start_time);
+    let mut clock = start_time;
+    let mut hits = Vec::new();
+    let (end, num_words) = run_search(
+        &mut runner,
+        words.iter().copied(),
+        &mut limits,
+        || {
+            clock += Duration::from_secs(1);
+            clock
+        },
+        |_| {},
+        |target, word_idx, _| hits.push((bench_name(target), word_idx)),
+    );
+    (end, num_words, hits)
+}
+
+fn bench_name(targets: &BTreeSet<String>) -> String {
+    targets.iter().cloned().collect::<Vec<_>>().join(" | ")
+}
+
+#[test]
+fn test_run_search_completed() {
+    let (end, num_words, hits) = run_mock_search(
+        &[&["b"], &["d", "e"]],
+        &[Some("a"), Some("b"), None, Some("e"), Some("f")],
+        "timeout = 10\n",
+    );
+    assert_eq!(end, SearchEnd::Completed);
+    assert_eq!(num_words, 3);
+    assert_eq!(hits, [("b".to_string(), 1), ("d | e".to_string(), 2)]);
+}
+
+#[test]
+fn test_run_search_exhausted() {
+    let (end, num_words, hits) =
+        run_mock_search(&[&["b"], &["z"]], &[Some("a"), Some("b")], "timeout = 10\n");
+    assert_eq!(end, SearchEnd::Exhausted);
+    assert_eq!(num_words, 2);
+    assert_eq!(hits, [("b".to_string(), 1)]);
+}
+
+#[test]
+fn test_run_search_timed_out() {
+    let words = [Some("a"); 10];
+    let (end, num_words, hits) = run_mock_search(
+        &[&["z"]],
+        &words,
+        "timeout = 3.5\ntimeout_check_interval = 1\n",
+    );
+    assert_eq!(end, SearchEnd::Stopped(SearchLimit::Timeout));
+    assert_eq!(num_words, 4);
+    assert!(hits.is_empty());
+}
+
+#[test]
+fn test_run_search_max_index() {
+    let words = [Some("a"); 10];
+    let (end, num_words, _) = run_mock_search(&[&["z"]], &words, "timeout = 10\nmax_index = 5\n");
+    assert_eq!(end, SearchEnd::Stopped(SearchLimit::MaxIndex));
+    assert_eq!(num_words, 6);
+}
+
+#[test]
+fn test_run_search_nothing_to_find() {
+    let (end, num_words, _) = run_mock_search(&[], &[Some("a")], "timeout = 10\n");
+    assert_eq!(end, SearchEnd::Completed);
+    assert_eq!(num_words, 0);
+}
+
+#[test]
+fn test_runcfg_apply_overrides() {
+    let cfg = BenchRunCfg {
+        repeat: NonZeroU8::new(5).unwrap(),
+        repeat_failed: NonZeroU8::new(2).unwrap(),
+        warmup: 1,
+        retry_errors: 0,
+        verbose: 0,
+        add_unaccented: true,
+        normalization: Normalization::Nfc,
+        max_samples_per_bench: None,
+        total_budget: None,
+        timeout_check_interval: 64,
+        max_index: None,
+        max_index_fraction: None,
+        timeout_scale: 1.0,
+        case_insensitive: false,
+        trace: false,
+        trace_words: vec![],
+        measure_memory: false,
+        exclude_setup: false,
+        timeout: Duration::from_secs(15),
+        rules_file: None,
+        dict_file: None,
+        bench_files: None,
+        out_db: default_out_db(),
+        machine: None,
+    };
+
+    let mut unchanged = cfg.clone();
+    unchanged.apply_overrides(None, None, None);
+    assert_eq!(unchanged, cfg);
+
+    let mut changed = cfg.clone();
+    changed.apply_overrides(
+        NonZeroU8::new(1),
+        NonZeroU8::new(3),
+        Some(Duration::from_secs_f64(0.5)),
+    );
+    assert_eq!(changed.repeat.get(), 1);
+    assert_eq!(changed.repeat_failed.get(), 3);
+    assert_eq!(changed.timeout, Duration::from_secs_f64(0.5));
+
+    let mut partly_changed = cfg.clone();
+    partly_changed.apply_overrides(NonZeroU8::new(9), None, None);
+    assert_eq!(partly_changed.repeat.get(), 9);
+    assert_eq!(partly_changed.repeat_failed, cfg.repeat_failed);
+    assert_eq!(partly_changed.timeout, cfg.timeout);
+}
+
+#[test]
+fn test_runcfg_apply_quick_mode() {
+    let cfg: BenchRunCfg = toml::from_str(
+        r#"
+        repeat = 5
+        repeat_failed = 3
+        timeout = 15
+        "#,
+    )
+    .unwrap();
+    assert_eq!(cfg.warmup, 1);
+
+    let mut quick = cfg.clone();
+    quick.apply_quick_mode();
+    assert_eq!(quick.warmup, 0);
+    assert_eq!(quick.repeat.get(), 1);
+    assert_eq!(quick.repeat_failed.get(), 1);
+    assert_eq!(
+        BenchRunCfg {
+            warmup: cfg.warmup,
+            repeat: cfg.repeat,
+            repeat_failed: cfg.repeat_failed,
+            ..quick
+        },
+        cfg
+    );
+}
+
+#[test]
+fn test_parse_timeout_secs() {
+    assert_eq!(parse_timeout_secs("2.5"), Ok(Duration::from_secs_f64(2.5)));
+    assert_eq!(parse_timeout_secs("10"), Ok(Duration::from_secs(10)));
+    for bad in ["0", "-1", "abc", "", "inf", "NaN"] {
+        assert!(
+            parse_timeout_secs(bad).is_err(),
+            "{:?} should be rejected",
+            bad
+        );
+    }
+}
+
 #[quickcheck]
 fn test_bench_result_is_found_error(err: String, elapsed: Duration) -> bool {
     let result = BenchResult {
-        found_index: Err(err),
+        found_index: Err(err.into()),
         elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     };
     !result.is_found()
 }
@@ -99,6 +822,11 @@ fn test_bench_result_is_found_none(elapsed: Duration) -> bool {
     let result = BenchResult {
         found_index: Ok(None),
         elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     };
     !result.is_found()
 }
@@ -108,21 +836,64 @@ fn test_bench_result_is_found_some(index: usize, elapsed: Duration) -> bool {
     let result = BenchResult {
         found_index: Ok(Some(index)),
         elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     };
     result.is_found()
 }
 
+#[test]
+fn test_benchresult_order() {
+    let result = |found_index, millis| BenchResult {
+        found_index,
+        elapsed: Duration::from_millis(millis),
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    let in_order = [
+        result(Ok(Some(0)), 500),
+        result(Ok(Some(0)), 900),
+        result(Ok(Some(3)), 100),
+        result(Ok(None), 50),
+        result(Ok(None), 1000),
+        result(Err("zzz".into()), 10),
+        result(Err("aaa".into()), 20),
+        result(Err("aaa".into()), 30),
+        result(Err("bbb".into()), 30),
+    ];
+    for (i, a) in in_order.iter().enumerate() {
+        for (j, b) in in_order.iter().enumerate() {
+            assert_eq!(a.cmp(b), i.cmp(&j), "{:?} vs {:?}", a, b);
+        }
+    }
+}
+
+#[quickcheck]
+fn test_benchresult_order_total(a: BenchResult, b: BenchResult, c: BenchResult) -> bool {
+    let antisymmetric = a.cmp(&b) == b.cmp(&a).reverse();
+    let agrees_with_eq = (a.cmp(&b) == Ordering::Equal) == (a == b);
+    let transitive = !(a <= b && b <= c) || a <= c;
+    antisymmetric && agrees_with_eq && transitive
+}
+
 #[quickcheck]
 fn test_bencher_clear_successes(results: Vec<BenchResult>) {
     let mut bencher = Bencher::new();
     bencher.add_bench("one", &["two", "three"]);
-    let bencher_results: &mut Vec<BenchResult> = bencher
+    let bencher_results: &mut Vec<BenchResult> = &mut bencher
         .benches
         .get_mut("one")
         .unwrap()
         .values_mut()
         .next()
-        .unwrap();
+        .unwrap()
+        .results;
     for r in &results {
         bencher_results.push(r.clone());
     }
@@ -134,6 +905,7 @@ fn test_bencher_clear_successes(results: Vec<BenchResult>) {
         .values()
         .next()
         .unwrap()
+        .results
         .clone();
     bencher_results.sort();
     let mut expected: Vec<BenchResult> =
@@ -142,6 +914,498 @@ fn test_bencher_clear_successes(results: Vec<BenchResult>) {
     assert_eq!(bencher_results, expected);
 }
 
+#[quickcheck]
+fn test_bencher_clear_errors(results: Vec<BenchResult>) {
+    let mut bencher = Bencher::new();
+    bencher.add_bench("one", &["two", "three"]);
+    let samples = bencher
+        .benches
+        .get_mut("one")
+        .unwrap()
+        .values_mut()
+        .next()
+        .unwrap();
+    samples.results.extend(results.iter().cloned());
+    samples.num_runs = results.len();
+    bencher.clear_errors();
+    let samples = bencher.benches["one"].values().next().unwrap();
+    let expected: Vec<BenchResult> = results.iter().filter(|r| !r.is_error()).cloned().collect();
+    assert_eq!(samples.results, expected);
+    assert_eq!(samples.num_runs, expected.len());
+}
+
+#[quickcheck]
+fn test_reservoir_push_max_len(items: Vec<u8>, max_len: Option<u8>) -> bool {
+    let max_len = max_len.map(usize::from);
+    let mut reservoir = Vec::new();
+    for (num_seen, &item) in items.iter().enumerate() {
+        reservoir_push(&mut reservoir, num_seen, max_len, item, &mut thread_rng());
+        if reservoir.len() > max_len.unwrap_or(usize::MAX) {
+            return false;
+        }
+    }
+    if max_len.is_none_or(|max_len| max_len >= items.len()) {
+        reservoir == items
+    } else {
+        reservoir.len() == max_len.unwrap() && reservoir.iter().all(|item| items.contains(item))
+    }
+}
+
+#[test]
+fn test_bench_samples_keeps_counting() {
+    let mut samples = BenchSamples::default();
+    for i in 0..10 {
+        samples.push(
+            BenchResult::success(i, Duration::ZERO),
+            Some(3),
+            &mut thread_rng(),
+        );
+    }
+    assert_eq!(samples.results.len(), 3);
+    assert_eq!(samples.num_runs, 10);
+    samples.clear();
+    assert_eq!(samples, BenchSamples::default());
+}
+
+#[test]
+fn test_bencher_errors_to_retry() {
+    let mut bencher = Bencher::new();
+    bencher.add_bench("one", &["two"]);
+    bencher.add_bench("one", &["three"]);
+    bencher.add_bench("four", &["five"]);
+    bencher.add_bench("six", &["seven"]);
+    let mut push = |start_word: &str, target: &str, result: BenchResult| {
+        let samples = bencher
+            .benches
+            .get_mut(start_word)
+            .unwrap()
+            .get_mut(&BTreeSet::from([target.to_string()]))
+            .unwrap();
+        samples.push(result, None, &mut thread_rng());
+    };
+    push("one", "two", BenchResult::success(0, Duration::ZERO));
+    push("one", "two", BenchResult::error("oops", Duration::ZERO));
+    push("one", "three", BenchResult::not_found(Duration::ZERO));
+    push("one", "three", BenchResult::error("oops", Duration::ZERO));
+    push("four", "five", BenchResult::not_found(Duration::ZERO));
+    push("four", "five", BenchResult::success(3, Duration::ZERO));
+    push("six", "seven", BenchResult::error("oops", Duration::ZERO));
+    assert_eq!(bencher.start_words_with_errors(), vec!["one", "six"]);
+
+    // Disabled benches aren't retried
+    for samples in bencher.benches.get_mut("six").unwrap().values_mut() {
+        samples.disabled = true;
+    }
+    assert_eq!(bencher.start_words_with_errors(), vec!["one"]);
+
+    for samples in bencher.benches.get_mut("one").unwrap().values_mut() {
+        assert_eq!(samples.count_errors(), 1);
+        assert!(samples.take_error().unwrap().is_error());
+        assert_eq!(samples.num_runs, 1);
+        assert_eq!(samples.take_error(), None);
+        assert_eq!(samples.num_runs, 1);
+    }
+    assert!(bencher.start_words_with_errors().is_empty());
+}
+
+#[test]
+fn test_bencher_count_measured_benches() {
+    let mut bencher = Bencher::new();
+    bencher.add_bench("one", &["two"]);
+    bencher.add_bench("one", &["three"]);
+    bencher.add_bench("four", &["five"]);
+    assert_eq!(bencher.count_measured_benches(), 0);
+    let result = BenchResult::success(0, Duration::from_millis(5));
+    for samples in bencher.benches.get_mut("one").unwrap().values_mut() {
+        samples.push(result.clone(), None, &mut thread_rng());
+    }
+    assert_eq!(bencher.count_measured_benches(), 2);
+    bencher.clear_successes();
+    assert_eq!(bencher.count_measured_benches(), 0);
+}
+
+#[test]
+fn test_bencher_count_measured_benches_not_warmup() {
+    let mut bencher = Bencher::new();
+    bencher.add_bench("one", &["two"]);
+    let samples = bencher
+        .benches
+        .get_mut("one")
+        .unwrap()
+        .values_mut()
+        .next()
+        .unwrap();
+    samples.push(
+        BenchResult::not_found(Duration::from_millis(5)),
+        None,
+        &mut thread_rng(),
+    );
+    samples.warmup_runs = samples.num_runs;
+    assert_eq!(bencher.count_measured_benches(), 0);
+}
+
+#[test]
+fn test_bencher_load_add_unaccented() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches("ação = x\n".as_bytes(), false, Normalization::Nfc)?;
+    assert_eq!(bencher.benches.keys().collect::<Vec<_>>(), vec!["ação"]);
+
+    let mut bencher = Bencher::new();
+    bencher.load_benches("ação = x\n".as_bytes(), true, Normalization::Nfc)?;
+    assert_eq!(
+        bencher.benches.keys().collect::<Vec<_>>(),
+        vec!["acao", "ação"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_machine_id() {
+    let cpuinfo = "processor\t: 0\nvendor_id\t: GenuineIntel\nmodel name\t: Intel(R) Core(TM) i5\n\
+                   processor\t: 1\nmodel name\t: Intel(R) Core(TM) i5\n";
+    assert_eq!(
+        machine_id(Some("mybox\n"), Some(cpuinfo)),
+        "mybox (Intel(R) Core(TM) i5)"
+    );
+    assert_eq!(machine_id(Some("mybox"), None), "mybox");
+    assert_eq!(machine_id(None, Some("processor\t: 0\n")), "unknown-host");
+}
+
+#[test]
+fn test_auto_machine_id_is_stable() {
+    let machine = auto_machine_id();
+    assert!(!machine.is_empty());
+    assert_eq!(auto_machine_id(), machine);
+}
+
+#[test]
+fn test_format_bench_file() -> Result<(), BenchError> {
+    let unsorted = "\
+; Common mistakes
+zebra=zzz|aaa ; striped
+cat, bat = gato *3
+;; dog = cão
+
+[verbs]
+ fazer =  faser,fazer
+";
+    let expected = "\
+; Common mistakes
+bat, cat = gato *3
+;; dog   = cão
+zebra    = aaa | zzz ; striped
+
+[verbs]
+fazer = faser, fazer
+";
+    let formatted = format_bench_file(unsorted)?;
+    assert_eq!(formatted, expected);
+    assert_eq!(format_bench_file(&formatted)?, formatted);
+    assert!(matches!(
+        format_bench_file("a = b\nnot a bench =\n"),
+        Err(ParseErr { line_no: 2, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_benches_str() -> Result<(), BenchError> {
+    for text in ["a = b\nc = d", "ação = x | y\nc = d"] {
+        let mut from_str = Bencher::new();
+        from_str.load_benches_str(text)?;
+        let mut from_reader = Bencher::new();
+        from_reader.load_benches(std::io::Cursor::new(text), true, Normalization::Nfc)?;
+        assert_eq!(from_str.benches, from_reader.benches);
+    }
+
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b\nc = d")?;
+    assert_eq!(bencher.num_benches(), 2);
+    bencher.load_benches_str("ação = x")?;
+    assert!(bencher.benches.contains_key("acao"));
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_bom_crlf() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches(
+        "\u{feff}abc = x | y\r\nd, e = f\r\n; comment\r\n".as_bytes(),
+        false,
+        Normalization::Nfc,
+    )?;
+    assert_eq!(
+        bencher.benches.keys().collect::<Vec<_>>(),
+        vec!["abc", "d", "e"]
+    );
+    assert_eq!(
+        bencher.check_targets(&BTreeSet::new(), false),
+        vec!["abc = x | y", "d = f", "e = f"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bencher_check_not_empty() -> Result<(), BenchError> {
+    for text in ["", "  \n\t\n", "; just a comment\n", ";;a = b\n"] {
+        let mut bencher = Bencher::new();
+        bencher.load_benches_str(text)?;
+        assert!(
+            matches!(bencher.check_not_empty(), Err(NoBenches)),
+            "{:?}",
+            text
+        );
+    }
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b\n")?;
+    bencher.check_not_empty()
+}
+
+#[test]
+fn test_bencher_load_bidirectional() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("bulacha <=> bolacha\na, b <=> c *2\n")?;
+    assert_eq!(
+        bencher.check_targets(&BTreeSet::new(), false),
+        vec![
+            "a = c",
+            "b = c",
+            "bolacha = bulacha",
+            "bulacha = bolacha",
+            "c = a",
+            "c = b"
+        ]
+    );
+    assert_eq!(bencher.get_weights().get("c = b"), Some(&2));
+    assert_eq!(bencher.get_weights().get("b = c"), Some(&2));
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_sections() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str(
+        "a = b\n[nouns]\nó = d\n; comment\n\n[ verbs ] ; doing words\ne <=> f\n",
+    )?;
+    assert_eq!(bencher.num_benches(), 5);
+    assert_eq!(
+        bencher.get_sections(),
+        &BTreeMap::from([
+            ("o = d".to_string(), "nouns".to_string()),
+            ("ó = d".to_string(), "nouns".to_string()),
+            ("e = f".to_string(), "verbs".to_string()),
+            ("f = e".to_string(), "verbs".to_string()),
+        ])
+    );
+    bencher.retain_benches(|name| name != "f = e");
+    assert!(!bencher.get_sections().contains_key("f = e"));
+    assert!(matches!(
+        bencher.load_benches_str("[]\n"),
+        Err(BenchError::ParseErr { line_no: 1, .. })
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_disabled() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b\n;;ó = d *2\n; e = f\n")?;
+    assert_eq!(bencher.num_benches(), 1);
+    assert_eq!(bencher.disabled_count(), 2);
+    assert!(bencher.benches["ó"]
+        .values()
+        .all(|samples| samples.disabled));
+    assert!(bencher.benches["o"]
+        .values()
+        .all(|samples| samples.disabled));
+    assert!(!bencher.benches.contains_key("e"));
+    assert_eq!(bencher.get_weights().get("ó = d"), Some(&2));
+    assert_eq!(
+        bencher.check_targets(&BTreeSet::new(), false),
+        vec!["a = b".to_string()]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bencher_disabled_duplicate_stays_disabled() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str(";;a = b\nc = d\n")?;
+    bencher.load_benches_str("a = b\n;;c = d\n")?;
+    assert_eq!(bencher.num_benches(), 0);
+    assert_eq!(bencher.disabled_count(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_normalization() -> Result<(), BenchError> {
+    let precomposed = "a\u{e7}a = x\n";
+    let decomposed = "ac\u{327}a = x\n";
+    let start_words = |normalization| -> Result<Vec<String>, BenchError> {
+        let mut bencher = Bencher::new();
+        bencher.load_benches(precomposed.as_bytes(), false, normalization)?;
+        bencher.load_benches(decomposed.as_bytes(), false, normalization)?;
+        Ok(bencher.benches.keys().cloned().collect())
+    };
+    assert_eq!(start_words(Normalization::Nfc)?, vec!["a\u{e7}a"]);
+    assert_eq!(start_words(Normalization::Nfd)?, vec!["ac\u{327}a"]);
+    assert_eq!(start_words(Normalization::None)?.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn test_normalization_deserialize() -> Result<(), toml::de::Error> {
+    let cfg: BenchRunCfg = toml::from_str(
+        r#"
+        repeat = 7
+        repeat_failed = 3
+        timeout = 8.3
+        normalization = "nfd"
+        "#,
+    )?;
+    assert_eq!(cfg.normalization, Normalization::Nfd);
+    let cfg: BenchRunCfg = toml::from_str("repeat = 7\nrepeat_failed = 3\ntimeout = 8.3\n")?;
+    assert_eq!(cfg.normalization, Normalization::Nfc);
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_several_files() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    for text in ["a = b\nc = d\n", "a = e\nf = g *2\n"] {
+        bencher.load_benches(std::io::Cursor::new(text), true, Normalization::Nfc)?;
+    }
+    let names: BTreeSet<String> = bencher
+        .benches
+        .iter()
+        .flat_map(|(start_word, bench_map)| {
+            bench_map.keys().map(move |targets| {
+                let mut name = String::new();
+                set_bench_name(&mut name, start_word, targets, &BenchNameStyle::DEFAULT);
+                name
+            })
+        })
+        .collect();
+    assert_eq!(
+        names,
+        BTreeSet::from(["a = b", "a = e", "c = d", "f = g"].map(String::from))
+    );
+    assert_eq!(
+        bencher.get_weights(),
+        &BTreeMap::from([("f = g".to_string(), 2)])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bencher_retain_failed() -> Result<(), BenchError> {
+    let session = BenchSessionId::from_timestamp(std::time::SystemTime::UNIX_EPOCH);
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b\nc = d\n")?;
+    bencher.retain_failed(&BTreeSet::from(["c = d".to_string()]), session)?;
+    assert_eq!(bencher.num_benches(), 1);
+
+    // Nothing failed, or only benches that aren't in the file any more
+    for failed in [vec![], vec!["e = f"]] {
+        let mut bencher = Bencher::new();
+        bencher.load_benches_str("a = b\nc = d\n")?;
+        let failed = failed.into_iter().map(String::from).collect();
+        let err = bencher.retain_failed(&failed, session).unwrap_err();
+        assert!(matches!(err, NoFailedBenches { session: s } if s == session));
+        assert_eq!(
+            err.to_string(),
+            format!("no failed benches from session {} to retry", session)
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_bencher_retain_benches() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b | c, d\ne = f *2\ng = h *3\n")?;
+    let failed = BTreeSet::from(["a = b | c", "g = h"]);
+    bencher.retain_benches(|name| failed.contains(name));
+    let names: BTreeSet<String> = bencher
+        .benches
+        .iter()
+        .flat_map(|(start_word, bench_map)| {
+            bench_map.keys().map(move |targets| {
+                let mut name = String::new();
+                set_bench_name(&mut name, start_word, targets, &BenchNameStyle::DEFAULT);
+                name
+            })
+        })
+        .collect();
+    assert_eq!(names, BTreeSet::from(["a = b | c".into(), "g = h".into()]));
+    assert!(!bencher.benches.contains_key("e"));
+    assert_eq!(
+        bencher.get_weights(),
+        &BTreeMap::from([("g = h".to_string(), 3)])
+    );
+    Ok(())
+}
+
+#[test]
+fn test_bench_key_name() {
+    let key = BenchKey {
+        start_word: "um".into(),
+        targets: BTreeSet::from(["dois".into(), "*três".into()]),
+    };
+    assert_eq!(key.name(), "um = *três | dois");
+    assert_eq!(BenchKey::from_name(&key.name()), Some(key));
+    assert_eq!(BenchKey::from_name("um"), None);
+    assert_eq!(BenchKey::from_name(" = dois"), None);
+}
+
+#[test]
+fn test_bencher_check_targets() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches(
+        "um = dois\ntrês = quatro | cinco\nseis = sete | oito\nnove = dez*, *zes\n".as_bytes(),
+        false,
+        Normalization::Nfc,
+    )?;
+    assert_eq!(bencher.num_benches(), 5);
+    let dict_words = BTreeSet::from(["dois".to_string(), "oito".into(), "dezena".into()]);
+    assert_eq!(
+        bencher.check_targets(&dict_words, false),
+        vec!["nove = *zes", "três = cinco | quatro"]
+    );
+    assert_eq!(bencher.check_targets(&BTreeSet::new(), false).len(), 5);
+    Ok(())
+}
+
+#[test]
+fn test_bencher_check_targets_case_insensitive() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("um = lisboa\ndois = Évora\nseis = porto*\n")?;
+    let dict_words = BTreeSet::from(["Lisboa".to_string(), "évora".into(), "PORTOS".into()]);
+    assert_eq!(bencher.check_targets(&dict_words, false).len(), 3);
+    assert!(bencher.check_targets(&dict_words, true).is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_bencher_load_weights() -> Result<(), BenchError> {
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = b\nó, c = d | e, f *3\n")?;
+    assert_eq!(
+        bencher.get_weights(),
+        &BTreeMap::from([
+            ("c = d | e".to_string(), 3),
+            ("c = f".to_string(), 3),
+            ("o = d | e".to_string(), 3),
+            ("o = f".to_string(), 3),
+            ("ó = d | e".to_string(), 3),
+            ("ó = f".to_string(), 3),
+        ])
+    );
+    bencher.add_weighted_bench("c", &["f"], 1);
+    assert!(!bencher.get_weights().contains_key("c = f"));
+    Ok(())
+}
+
 #[test]
 fn test_bench_runner_is_done() {
     let mut runner = BenchRunner::new();
@@ -176,6 +1440,128 @@ fn test_bench_runner_on_word_found() {
     assert!(runner.is_done());
 }
 
+#[test]
+fn test_bench_runner_shuffle() {
+    let target_sets: Vec<BTreeSet<String>> = (0..20)
+        .map(|i| BTreeSet::from([format!("t{}", i), "shared".to_string()]))
+        .chain([BTreeSet::from(["t3".to_string()])])
+        .collect();
+    let mut runner = BenchRunner::new();
+    for targets in &target_sets {
+        runner.add_targets(targets);
+    }
+    runner.shuffle(&mut thread_rng());
+    assert_eq!(
+        runner.remaining_targets.iter().collect::<BTreeSet<_>>(),
+        target_sets.iter().collect::<BTreeSet<_>>()
+    );
+
+    let mut hits = Vec::new();
+    runner.on_word_found("t3", |target| hits.push(target.clone()));
+    hits.sort();
+    assert_eq!(hits, vec![target_sets[3].clone(), target_sets[20].clone()]);
+    assert_eq!(runner.remaining_targets.len(), 19);
+
+    let mut hits = BTreeSet::new();
+    runner.on_word_found("shared", |target| {
+        hits.insert(target.clone());
+    });
+    assert_eq!(hits.len(), 19);
+    assert!(!hits.contains(&target_sets[3]));
+    assert!(runner.is_done());
+}
+
+#[test]
+fn test_bench_runner_case_insensitive() {
+    let targets = BTreeSet::from(["lisboa".to_string()]);
+    let mut runner = BenchRunner::new();
+    runner.add_targets(&targets);
+    runner.on_word_found("Lisboa", |target| {
+        panic!("\"Lisboa\" hit unexpected target {:?}", target)
+    });
+    assert!(!runner.is_done());
+
+    let mut runner = BenchRunner::new().with_case_insensitive(true);
+    runner.add_targets(&targets);
+    let mut hits = Vec::new();
+    runner.on_word_found("LISBOA", |target| hits.push(target.clone()));
+    assert_eq!(hits, vec![targets]);
+    assert!(runner.is_done());
+}
+
+#[test]
+fn test_bench_runner_case_insensitive_targets_as_written() {
+    let mut runner = BenchRunner::new().with_case_insensitive(true);
+    let words = BTreeSet::from(["Évora".to_string()]);
+    let patterns = BTreeSet::from(["Ágü*".to_string()]);
+    runner.add_targets(&words);
+    runner.add_targets(&patterns);
+    let mut hits = Vec::new();
+    for word in ["évora", "ÁGUA", "águas", "ÁGÜERO"] {
+        runner.on_word_found(word, |target| hits.push((word, target.clone())));
+    }
+    assert_eq!(hits, vec![("évora", words), ("ÁGÜERO", patterns)]);
+    assert!(runner.is_done());
+}
+
+#[test]
+fn test_fold_case() {
+    assert!(matches!(fold_case("coração"), Cow::Borrowed("coração")));
+    assert_eq!(fold_case("Coração"), "coração");
+    assert_eq!(fold_case("ÇÃO"), "ção");
+}
+
+#[test]
+fn test_target_pattern_parse() {
+    assert_eq!(
+        TargetPattern::parse("*inho"),
+        Some(TargetPattern::Suffix("inho".into()))
+    );
+    assert_eq!(
+        TargetPattern::parse("casa*"),
+        Some(TargetPattern::Prefix("casa".into()))
+    );
+    assert_eq!(TargetPattern::parse("casa"), None);
+}
+
+#[test]
+fn test_bench_runner_suffix_pattern_hit() {
+    let mut runner = BenchRunner::new();
+    let targets = BTreeSet::from(["*inho".to_string()]);
+    runner.add_targets(&targets);
+    let mut hits = Vec::new();
+    for word in ["casa", "inhoque", "carrinho", "passarinho"] {
+        runner.on_word_found(word, |target| hits.push((word, target.clone())));
+    }
+    assert_eq!(hits, vec![("carrinho", targets)]);
+    assert!(runner.is_done());
+}
+
+#[test]
+fn test_bench_runner_suffix_pattern_miss() {
+    let mut runner = BenchRunner::new();
+    runner.add_targets(&BTreeSet::from(["*inho".to_string()]));
+    for word in ["casa", "inhoque", "carrinha", "inh"] {
+        runner.on_word_found(word, |target| {
+            panic!("{:?} hit unexpected target {:?}", word, target)
+        });
+    }
+    assert!(!runner.is_done());
+}
+
+#[test]
+fn test_bench_runner_pattern_with_words() {
+    let mut runner = BenchRunner::new();
+    runner.add_targets(&BTreeSet::from(["casa*".to_string(), "lar".to_string()]));
+    runner.add_targets(&BTreeSet::from(["lar".to_string()]));
+    let mut num_hits = 0;
+    runner.on_word_found("casarão", |_| num_hits += 1);
+    assert_eq!(num_hits, 1);
+    runner.on_word_found("lar", |_| num_hits += 1);
+    assert_eq!(num_hits, 2);
+    assert!(runner.is_done());
+}
+
 #[test]
 fn test_set_unaccented_already_unaccented() {
     let mut unaccented = String::new();
@@ -191,17 +1577,88 @@ fn test_set_unaccented() {
     assert_eq!(unaccented, "aeiou");
 }
 
+#[test]
+fn test_has_accents() {
+    assert!(has_accents("ação"));
+    assert!(has_accents("ac\u{327}a"));
+    assert!(!has_accents("acao"));
+    assert!(!has_accents(""));
+}
+
 #[test]
 fn test_set_bench_name() {
     let mut bench_name = String::new();
-    set_bench_name(&mut bench_name, "word", &BTreeSet::from(["target"]));
+    set_bench_name(
+        &mut bench_name,
+        "word",
+        &BTreeSet::from(["target"]),
+        &BenchNameStyle::DEFAULT,
+    );
     assert_eq!(bench_name, "word = target");
-    set_bench_name(&mut bench_name, "x", &BTreeSet::from(["a", "b"]));
+    set_bench_name(
+        &mut bench_name,
+        "x",
+        &BTreeSet::from(["a", "b"]),
+        &BenchNameStyle::DEFAULT,
+    );
     assert_eq!(bench_name, "x = a | b");
-    set_bench_name(&mut bench_name, "y", &BTreeSet::from(["a", "b", "c"]));
+    set_bench_name(
+        &mut bench_name,
+        "y",
+        &BTreeSet::from(["a", "b", "c"]),
+        &BenchNameStyle::DEFAULT,
+    );
     assert_eq!(bench_name, "y = a | b | c");
 }
 
+#[test]
+fn test_bench_key_name_path_style() {
+    let key = BenchKey {
+        start_word: "um".into(),
+        targets: BTreeSet::from(["dois".into(), "três*".into()]),
+    };
+    let name = key.name_with_style(&BenchNameStyle::PATH);
+    assert_eq!(name, "um/dois+três*");
+    assert_eq!(
+        BenchKey::from_name_with_style(&name, &BenchNameStyle::PATH),
+        Some(key)
+    );
+}
+
+#[test]
+fn test_set_bench_name_style() {
+    let style = BenchNameStyle {
+        assign: "->",
+        sep: "/",
+    };
+    let mut bench_name = String::new();
+    set_bench_name(&mut bench_name, "word", &BTreeSet::from(["target"]), &style);
+    assert_eq!(bench_name, "word->target");
+    set_bench_name(
+        &mut bench_name,
+        "y",
+        &BTreeSet::from(["a", "b", "c"]),
+        &style,
+    );
+    assert_eq!(bench_name, "y->a/b/c");
+}
+
+#[test]
+fn test_bench_key_name_with_style() {
+    let style = BenchNameStyle {
+        assign: ": ",
+        sep: "; ",
+    };
+    let key = BenchKey::new("x", &BTreeSet::from(["a".to_string(), "b".to_string()]));
+    assert_eq!(key.name_with_style(&style), "x: a; b");
+    assert_eq!(key.name_with_style(&BenchNameStyle::default()), key.name());
+    assert_eq!(
+        BenchKey::from_name_with_style(&key.name_with_style(&style), &style),
+        Some(key.clone())
+    );
+    assert_eq!(BenchKey::from_name_with_style("x = a | b", &style), None);
+}
+
 #[test]
 fn test_set_bench_name_canonical_order() {
     let mut bench_name1 = String::new();
@@ -210,11 +1667,13 @@ fn test_set_bench_name_canonical_order() {
         &mut bench_name1,
         "does",
         &BTreeSet::from_iter(["this", "work"].iter()),
+        &BenchNameStyle::DEFAULT,
     );
     set_bench_name(
         &mut bench_name2,
         "does",
         &BTreeSet::from_iter(["work", "this"].iter()),
+        &BenchNameStyle::DEFAULT,
     );
     assert_eq!(bench_name1, bench_name2);
 }
@@ -242,6 +1701,42 @@ fn test_get_range_empty() {
     assert!(get_range(&[] as &[i8]).is_none());
 }
 
+#[quickcheck]
+fn test_range_accumulator_values(values: Vec<i8>) -> bool {
+    let mut acc = RangeAccumulator::new();
+    for &value in &values {
+        acc.push(value);
+    }
+    acc.finish() == get_range(values)
+}
+
+#[quickcheck]
+fn test_range_accumulator_ranges(values: Vec<i8>, ranges: Vec<RangeInclusive<i8>>) -> TestResult {
+    if ranges.iter().any(RangeInclusive::is_empty) {
+        return TestResult::discard();
+    }
+    let mut acc = RangeAccumulator::new();
+    for (i, range) in ranges.iter().enumerate() {
+        acc.push_range(range);
+        if let Some(&value) = values.get(i) {
+            acc.push(value);
+        }
+    }
+    let pushed_values = values.iter().take(ranges.len());
+    let expected = get_range(
+        ranges
+            .iter()
+            .flat_map(|range| [*range.start(), *range.end()])
+            .chain(pushed_values.copied()),
+    );
+    TestResult::from_bool(acc.finish() == expected)
+}
+
+#[test]
+fn test_range_accumulator_empty() {
+    assert_eq!(RangeAccumulator::<i8>::new().finish(), None);
+}
+
 #[test]
 fn test_extend_range() {
     assert_eq!(extend_range(0..=5, 3), 0..=5);
@@ -282,12 +1777,64 @@ fn test_resultcompiler_score_index(
     let result_plus_one = BenchResult {
         found_index: Ok(Some(result.found_index.as_ref().unwrap().unwrap() + 1)),
         elapsed: result.elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    let orig_score = compiler.score(&result);
+    let new_score = compiler.score(&result_plus_one);
+    assert!(new_score > orig_score);
+    assert!((new_score - orig_score - compiler.index_equivalent.as_secs_f64()).abs() < 1e-8);
+    TestResult::passed()
+}
+
+#[quickcheck]
+fn test_resultcompiler_score_log_index(
+    index1: u16,
+    index2: u16,
+    elapsed: Duration,
+    index_equivalent: Duration,
+    base: u8,
+) {
+    let compiler =
+        BenchResultCompiler::new(index_equivalent, 0.0).with_index_cost(IndexCost::Log {
+            base: base as f64 + 1.5,
+        });
+    let (low, high) = (index1.min(index2), index1.max(index2));
+    let score = |index: u16| {
+        compiler.score(&BenchResult {
+            found_index: Ok(Some(index as usize)),
+            elapsed,
+            stopped_by: None,
+            peak_rss: None,
+            found_word: None,
+            setup: None,
+            first_result: None,
+        })
+    };
+    assert!(score(low) <= score(high));
+}
+
+#[test]
+fn test_resultcompiler_score_log_values() {
+    let compiler = BenchResultCompiler::new(Duration::from_secs(1), 0.0)
+        .with_index_cost(IndexCost::Log { base: 10.0 });
+    let score = |index| {
+        compiler.score(&BenchResult {
+            found_index: Ok(Some(index)),
+            elapsed: Duration::ZERO,
+            stopped_by: None,
+            peak_rss: None,
+            found_word: None,
+            setup: None,
+            first_result: None,
+        })
     };
-    let orig_score = compiler.score(&result);
-    let new_score = compiler.score(&result_plus_one);
-    assert!(new_score > orig_score);
-    assert!((new_score - orig_score - compiler.index_equivalent.as_secs_f64()).abs() < 1e-8);
-    TestResult::passed()
+    assert_eq!(score(0), 0.0);
+    assert!((score(9) - 1.0).abs() < 1e-8);
+    assert!((score(99) - 2.0).abs() < 1e-8);
 }
 
 #[quickcheck]
@@ -306,10 +1853,20 @@ fn test_resultcompiler_score_elapsed(
     let result1 = BenchResult {
         found_index: Ok(Some(index as usize)),
         elapsed: elapsed1,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     };
     let result2 = BenchResult {
         found_index: Ok(Some(index as usize)),
         elapsed: elapsed2,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     };
     let score_diff = compiler.score(&result1) - compiler.score(&result2);
     let elapsed_diff = elapsed1.as_secs_f64() - elapsed2.as_secs_f64();
@@ -326,8 +1883,13 @@ fn test_resultcompiler_collects_all_errors(
         return TestResult::discard();
     }
     results.extend(errors.iter().map(|(err, elapsed)| BenchResult {
-        found_index: Err(err.clone()),
+        found_index: Err(err.clone().into()),
         elapsed: *elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     }));
     let compiled = compiler.compile(results);
     for (err, _) in &errors {
@@ -336,6 +1898,46 @@ fn test_resultcompiler_collects_all_errors(
     TestResult::passed()
 }
 
+#[quickcheck]
+fn test_resultcompiler_compile_ref(
+    results: Vec<BenchResult>,
+    compiler: BenchResultCompiler,
+) -> TestResult {
+    if results.is_empty() {
+        return TestResult::discard();
+    }
+    let original = results.clone();
+    let compiled_ref = compiler.compile_ref(&results);
+    assert_eq!(results, original);
+    TestResult::from_bool(compiled_ref == compiler.compile(results))
+}
+
+#[test]
+fn test_resultcompiler_compile_verbose() {
+    let results = vec![
+        BenchResult::success(0, Duration::from_secs(3)),
+        BenchResult::not_found(Duration::from_secs(5)),
+        BenchResult::success(0, Duration::from_secs(1)),
+        BenchResult::success(1, Duration::from_millis(500)),
+    ];
+    let compiler = BenchResultCompiler::new(Duration::from_secs(1), 0.5);
+    let steps = compiler.compile_verbose(&results);
+    assert_eq!(
+        steps.scored,
+        vec![
+            (1.0, &results[2]),
+            (1.5, &results[3]),
+            (3.0, &results[0]),
+            (f64::INFINITY, &results[1]),
+        ]
+    );
+    assert_eq!(steps.drop_low, 1);
+    assert_eq!(steps.drop_high, 1);
+    assert_eq!(steps.kept(), &[(1.5, &results[3]), (3.0, &results[0])]);
+    assert_eq!(steps.compiled.score, Some(Duration::from_secs_f64(2.25)));
+    assert_eq!(steps.compiled, compiler.compile_ref(&results));
+}
+
 #[quickcheck]
 fn test_resultcompiler_index_range_no_drop(indices: Vec<Option<usize>>) -> TestResult {
     if indices.is_empty() {
@@ -345,6 +1947,11 @@ fn test_resultcompiler_index_range_no_drop(indices: Vec<Option<usize>>) -> TestR
     let compiled = compiler.compile(indices.iter().map(|&index| BenchResult {
         found_index: Ok(index),
         elapsed: Default::default(),
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     }));
     assert_eq!(
         compiled.found_index,
@@ -362,6 +1969,11 @@ fn test_resultcompiler_elapsed_range_no_drop(times: Vec<(Duration, bool)>) -> Te
     let compiled = compiler.compile(times.iter().copied().map(|(elapsed, found)| BenchResult {
         found_index: Ok(if found { Some(0) } else { None }),
         elapsed,
+        stopped_by: None,
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
     }));
     assert_eq!(
         compiled.elapsed,
@@ -463,3 +2075,739 @@ fn test_resultcompiler_drop_fraction_score() {
         Some(Duration::from_secs_f64(5.75))
     );
 }
+
+#[test]
+fn test_resultcompiler_asymmetric_trim_elapsed() {
+    let results = || (1..=8).map(|secs| BenchResult::success(0, Duration::from_secs(secs)));
+    assert_eq!(
+        BenchResultCompiler::with_trim(Duration::ZERO, TrimSpec::new(0.0, 0.25))
+            .compile(results())
+            .elapsed,
+        Some(Duration::from_secs(1)..=Duration::from_secs(6))
+    );
+    assert_eq!(
+        BenchResultCompiler::with_trim(Duration::ZERO, TrimSpec::new(0.25, 0.0))
+            .compile(results())
+            .elapsed,
+        Some(Duration::from_secs(3)..=Duration::from_secs(8))
+    );
+    assert_eq!(
+        BenchResultCompiler::with_trim(Duration::ZERO, TrimSpec::new(0.125, 0.375))
+            .compile(results())
+            .elapsed,
+        Some(Duration::from_secs(2)..=Duration::from_secs(5))
+    );
+    assert_eq!(
+        BenchResultCompiler::with_trim(Duration::ZERO, TrimSpec::new(0.0, 0.49))
+            .compile([
+                BenchResult::success(0, Duration::from_secs(10)),
+                BenchResult::success(0, Duration::from_secs(11)),
+                BenchResult::success(0, Duration::from_secs(12)),
+            ])
+            .elapsed,
+        Some(Duration::from_secs(10)..=Duration::from_secs(11))
+    );
+}
+
+#[test]
+fn test_resultcompiler_asymmetric_trim_score() {
+    assert_eq!(
+        BenchResultCompiler::with_trim(Duration::from_secs(2), TrimSpec::new(0.0, 0.25))
+            .compile([
+                BenchResult::success(1, Duration::from_secs_f64(0.5)),
+                BenchResult::success(2, Duration::from_secs_f64(1.0)),
+                BenchResult::success(3, Duration::ZERO),
+                BenchResult::success(9, Duration::from_secs_f64(2.0)),
+            ])
+            .score,
+        Some(Duration::from_secs_f64(4.5))
+    );
+}
+
+#[test]
+fn test_trim_spec_symmetric() {
+    assert_eq!(TrimSpec::symmetric(0.5), TrimSpec::new(0.25, 0.25));
+    assert_eq!(
+        BenchResultCompiler::new(Duration::ZERO, 0.5),
+        BenchResultCompiler::with_trim(Duration::ZERO, TrimSpec::new(0.25, 0.25))
+    );
+}
+
+#[test]
+#[should_panic]
+fn test_trim_spec_too_high() {
+    TrimSpec::new(0.0, 0.5);
+}
+
+fn write_temp_file(name: &str, contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "buscaluso-bench-test-{}-{}",
+        std::process::id(),
+        name
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn test_count_dict_words() {
+    assert_eq!(count_dict_words(""), 0);
+    assert_eq!(count_dict_words("um\ndois\n\n  \ntrês\n"), 3);
+}
+
+#[test]
+fn test_dict_filter() -> std::io::Result<()> {
+    let dict = "um\n dois \n\ntrês\nquatro\ncinco\n";
+    let filter = DictFilter::default();
+    assert!(filter.is_empty());
+    assert_eq!(
+        filter.filter(dict.as_bytes())?,
+        "um\ndois\ntrês\nquatro\ncinco\n"
+    );
+
+    let filter = DictFilter {
+        pattern: Some(Regex::new("^[ct]").unwrap()),
+        subset: None,
+    };
+    assert_eq!(filter.filter(dict.as_bytes())?, "três\ncinco\n");
+
+    let filter = DictFilter {
+        pattern: None,
+        subset: Some(BTreeSet::from([
+            "dois".into(),
+            "cinco".into(),
+            "seis".into(),
+        ])),
+    };
+    assert_eq!(filter.filter(dict.as_bytes())?, "dois\ncinco\n");
+
+    let filter = DictFilter {
+        pattern: Some(Regex::new("o$").unwrap()),
+        subset: Some(BTreeSet::from([
+            "dois".into(),
+            "cinco".into(),
+            "quatro".into(),
+        ])),
+    };
+    assert!(!filter.is_empty());
+    assert_eq!(filter.filter(dict.as_bytes())?, "quatro\ncinco\n");
+    Ok(())
+}
+
+#[test]
+fn test_set_session_info() -> rusqlite::Result<()> {
+    let run_cfg = BenchRunCfg {
+        repeat: NonZeroU8::new(1).unwrap(),
+        repeat_failed: NonZeroU8::new(1).unwrap(),
+        warmup: 1,
+        retry_errors: 0,
+        timeout: Duration::from_secs(1),
+        verbose: 0,
+        add_unaccented: true,
+        normalization: Normalization::Nfc,
+        max_samples_per_bench: None,
+        total_budget: None,
+        timeout_check_interval: 64,
+        max_index: None,
+        max_index_fraction: None,
+        timeout_scale: 1.0,
+        case_insensitive: false,
+        trace: false,
+        trace_words: vec![],
+        measure_memory: false,
+        exclude_setup: false,
+        rules_file: Some(STDIN_PATH.into()),
+        dict_file: Some("dict.txt".into()),
+        bench_files: None,
+        out_db: default_out_db(),
+        machine: Some("test".into()),
+    };
+    let rules = "a = b\n";
+    let dict = "um\ndois\n";
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let sid = db.new_session_id()?;
+    let cli_args = ["buscaluso-bench", "-c", "my bench.toml", "--repeat", "3"].map(String::from);
+    let bench_files = [
+        (Path::new("a.txt"), "a = b\n"),
+        (Path::new("b.txt"), "c = d\n"),
+    ];
+    set_session_info(
+        &mut db,
+        sid,
+        &run_cfg,
+        rules,
+        &LoadedDict::new(dict.to_string()),
+        &bench_files,
+        &cli_args,
+    )?;
+    assert_eq!(db.get_info(sid, "bench_files")?, r#"["a.txt","b.txt"]"#);
+    assert_eq!(
+        db.get_info(sid, "bench_files_hash")?,
+        format!(
+            r#"["{}","{}"]"#,
+            sha256_hex(b"a = b\n"),
+            sha256_hex(b"c = d\n")
+        )
+    );
+    assert_eq!(
+        db.get_info(sid, "cli_args")?,
+        r#"["buscaluso-bench","-c","my bench.toml","--repeat","3"]"#
+    );
+    assert_eq!(db.get_info(sid, "search_rules")?, rules);
+    assert_eq!(
+        db.get_info(sid, "search_rules_hash")?,
+        sha256_hex(rules.as_bytes())
+    );
+    assert_eq!(
+        db.get_info(sid, "search_dict_hash")?,
+        sha256_hex(dict.as_bytes())
+    );
+    assert_eq!(db.get_info(sid, "dict_word_count")?, "2");
+    assert_eq!(db.get_info(sid, CASE_INSENSITIVE_INFO)?, "false");
+    Ok(())
+}
+
+#[test]
+fn test_path_from_cli_or_env() {
+    let cli = || Some(PathBuf::from("cli.toml"));
+    let env = || Some(OsString::from("env.toml"));
+    assert_eq!(path_from_cli_or_env(cli(), env()), cli());
+    assert_eq!(path_from_cli_or_env(cli(), None), cli());
+    assert_eq!(
+        path_from_cli_or_env(None, env()),
+        Some(PathBuf::from("env.toml"))
+    );
+    assert_eq!(path_from_cli_or_env(None, None), None);
+    assert_eq!(path_from_cli_or_env(None, Some(OsString::new())), None);
+}
+
+#[test]
+fn test_is_stdin_path() {
+    assert!(is_stdin_path(Path::new("-")));
+    assert!(!is_stdin_path(Path::new("./-")));
+    assert!(!is_stdin_path(Path::new("bench.txt")));
+}
+
+#[test]
+fn test_open_input_file() -> std::io::Result<()> {
+    let path = write_temp_file("open-input.bench", "a = b\n");
+    let mut contents = String::new();
+    open_input(&path)?.read_to_string(&mut contents)?;
+    assert_eq!(contents, "a = b\n");
+    assert_eq!(sha256_hex(contents.as_bytes()), file_sha256_hex(&path)?);
+    std::fs::remove_file(path)
+}
+
+#[test]
+fn test_log_level() {
+    assert_eq!(log_level(0, false), LevelFilter::Warn);
+    assert_eq!(log_level(1, false), LevelFilter::Info);
+    assert_eq!(log_level(2, false), LevelFilter::Debug);
+    assert_eq!(log_level(3, false), LevelFilter::Trace);
+    assert_eq!(log_level(200, false), LevelFilter::Trace);
+    assert_eq!(log_level(0, true), LevelFilter::Warn);
+    assert_eq!(log_level(2, true), LevelFilter::Warn);
+}
+
+#[test]
+fn test_compare_sessions() -> rusqlite::Result<()> {
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let earlier = db.new_session_id()?;
+    let later = BenchSessionId::from_timestamp(earlier.start_time() + Duration::from_secs(1));
+    let key = |name| BenchKey::from_name(name).unwrap();
+    let found = |index, millis| BenchResult::success(index, Duration::from_millis(millis));
+    let not_found = BenchResult {
+        found_index: Ok(None),
+        elapsed: Duration::from_secs(1),
+        stopped_by: Some(SearchLimit::Timeout),
+        peak_rss: None,
+        found_word: None,
+        setup: None,
+        first_result: None,
+    };
+    for (bench, earlier_result, later_result) in [
+        ("faster = a", found(0, 1000), found(0, 500)),
+        ("slower = b", found(0, 250), found(1, 250)),
+        ("same = c", found(2, 500), found(2, 510)),
+        ("fixed = d", not_found.clone(), found(0, 100)),
+    ] {
+        db.add_result(earlier, &key(bench), earlier_result)?;
+        db.add_result(later, &key(bench), later_result)?;
+    }
+    db.add_result(later, &key("new = e"), found(0, 100))?;
+
+    let compiler = BenchResultCompiler::new(Duration::from_millis(125), 0.0);
+    let comparison = compare_sessions(&mut db, &compiler, earlier, later, 1.0 / 32.0)?;
+    assert_eq!(comparison.num_better, 2);
+    assert_eq!(comparison.num_worse, 1);
+    assert!((comparison.total_difference - (-0.5 + 0.125 + 0.01)).abs() < 1e-6);
+    Ok(())
+}
+
+#[test]
+fn test_weighted_average() {
+    assert_eq!(weighted_average([]), None);
+    assert_eq!(
+        weighted_average([(1.0, 1.0), (2.0, 1.0), (6.0, 1.0)]),
+        Some(3.0)
+    );
+    assert_eq!(
+        weighted_average([(1.0, 3.0), (2.0, 1.0), (6.0, 1.0)]),
+        Some(2.2)
+    );
+    assert_eq!(weighted_average([(1.0, 0.0), (5.0, 2.0)]), Some(5.0));
+}
+
+#[test]
+fn test_geometric_mean() {
+    let mean = geometric_mean([1.0, 2.0, 4.0]).unwrap();
+    assert!((mean - 2.0).abs() < 1e-12, "{}", mean);
+    let mean = geometric_mean([0.5, 8.0]).unwrap();
+    assert!((mean - 2.0).abs() < 1e-12, "{}", mean);
+    let mean = geometric_mean([1e200, 1e200, 1e-100]).unwrap();
+    assert!((mean / 1e100 - 1.0).abs() < 1e-9, "{}", mean);
+    let mean = geometric_mean([3.0]).unwrap();
+    assert!((mean - 3.0).abs() < 1e-12, "{}", mean);
+}
+
+#[test]
+fn test_geometric_mean_no_scores() {
+    let scores: [Option<f64>; 2] = [None, None];
+    assert_eq!(geometric_mean(scores.into_iter().flatten()), None);
+    assert_eq!(geometric_mean([]), None);
+}
+
+#[test]
+fn test_fmt_duration_precision() {
+    let duration = Some(Duration::from_micros(1_234_567));
+    assert_eq!(fmt_duration(&duration, 2), "1.23");
+    assert_eq!(fmt_duration(&duration, 6), "1.234567");
+    assert_eq!(fmt_duration(&None, 6), "--");
+    let range = Some(Duration::from_micros(250)..=Duration::from_micros(1_234_567));
+    assert_eq!(fmt_duration_range(&range, 2), "0.00 .. 1.23");
+    assert_eq!(fmt_duration_range(&range, 6), "0.000250 .. 1.234567");
+}
+
+#[test]
+fn test_session_stats_in_memory_run() -> Result<(), Box<dyn std::error::Error>> {
+    let mut search_cfg = BuscaCfg::new();
+    search_cfg.load_dictionary("um\ndois\ntrês\n".as_bytes())?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("[found]\nx = dois\n[missing]\ny = quatro\n")?;
+    let run_cfg: BenchRunCfg =
+        toml::from_str("repeat = 1\nrepeat_failed = 1\ntimeout = 10\nwarmup = 0\n")?;
+    bencher.run_benches(&search_cfg, &run_cfg);
+
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+    assert_eq!(
+        db.get_results(session, "x = dois")?[0].found_word(),
+        Some("dois")
+    );
+    let compiler = BenchResultCompiler::new(
+        Duration::from_secs_f64(COMPARE_INDEX_EQUIVALENT),
+        COMPARE_DROP_FRACTION,
+    );
+    let stats = session_stats(&mut db, &compiler, session)?;
+    assert_eq!(stats.all.num_benches, 2);
+    assert_eq!(stats.all.num_found, 1);
+    assert_eq!(stats.found_indices, vec![Some(1), None]);
+    assert_eq!(stats.section_groups["found"].num_found, 1);
+    assert_eq!(stats.section_groups["missing"].num_found, 0);
+    assert_eq!(stats.weighted_average_score, None);
+
+    let mut out = Vec::new();
+    stats.write_summary(&mut out, 4)?;
+    let out = String::from_utf8(out)?;
+    assert!(
+        out.starts_with("Found 1 / 2 (50.0%)\nAverage score: "),
+        "{}",
+        out
+    );
+
+    assert_eq!(
+        session_stats(
+            &mut db,
+            &compiler,
+            BenchSessionId::from_timestamp(std::time::UNIX_EPOCH)
+        )?,
+        SessionStats::default()
+    );
+    Ok(())
+}
+
+/// Remembers what it was told, in order.
+#[derive(Debug, Default)]
+struct RecordingObserver {
+    calls: Vec<String>,
+}
+
+impl RunObserver for RecordingObserver {
+    fn start(&mut self, num_to_do: u32) {
+        self.calls.push(format!("start {}", num_to_do));
+    }
+
+    fn word_done(&mut self, num_complete: u32) {
+        self.calls.push(format!("done {}", num_complete));
+    }
+
+    fn finish(&mut self) {
+        self.calls.push("finish".into());
+    }
+}
+
+#[test]
+fn test_run_benches_observed() -> Result<(), Box<dyn std::error::Error>> {
+    let mut search_cfg = BuscaCfg::new();
+    search_cfg.load_dictionary("um\ndois\n".as_bytes())?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = um\nb = dois | três\nc = dois\n")?;
+    let run_cfg: BenchRunCfg =
+        toml::from_str("repeat = 2\nrepeat_failed = 2\ntimeout = 10\nwarmup = 1\n")?;
+    let mut observer = RecordingObserver::default();
+    bencher.run_benches_observed(&search_cfg, &run_cfg, &mut observer);
+    assert_eq!(
+        observer.calls,
+        ["start 6", "done 1", "done 2", "done 3", "done 4", "done 5", "done 6", "finish"]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_progress_bar_observer() -> Result<(), Box<dyn std::error::Error>> {
+    let mut search_cfg = BuscaCfg::new();
+    search_cfg.load_dictionary("um\ndois\n".as_bytes())?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = um\nb = dois\n")?;
+    let run_cfg: BenchRunCfg =
+        toml::from_str("repeat = 2\nrepeat_failed = 2\ntimeout = 10\nwarmup = 0\n")?;
+
+    let mut observer = ProgressBarObserver::with_draw_target(ProgressDrawTarget::hidden());
+    observer.start(4);
+    observer.word_done(3);
+    let bar = observer.bar.clone().unwrap();
+    assert!(bar.is_hidden());
+    assert_eq!((bar.position(), bar.length()), (3, Some(4)));
+    observer.finish();
+    assert!(observer.bar.is_none());
+    assert!(bar.is_finished());
+
+    let mut observer = ProgressBarObserver::with_draw_target(ProgressDrawTarget::hidden());
+    bencher.run_benches_observed(&search_cfg, &run_cfg, &mut observer);
+    assert!(observer.bar.is_none());
+    assert_eq!(bencher.count_measured_benches(), 2);
+    Ok(())
+}
+
+/// A search that finds the same scripted words from every start word.
+enum ScriptedSearch {
+    Words(Vec<Option<&'static str>>),
+    /// Finds its words over and over, so that only a limit can stop it.
+    Endless(Vec<Option<&'static str>>),
+    Error(SearchError),
+}
+
+impl WordSearch for ScriptedSearch {
+    fn search<'s>(
+        &'s self,
+        _start_word: &str,
+    ) -> Result<Box<dyn Iterator<Item = Option<&'s str>> + 's>, SearchError> {
+        match self {
+            ScriptedSearch::Words(words) => Ok(Box::new(words.iter().copied())),
+            ScriptedSearch::Endless(words) => Ok(Box::new(words.iter().copied().cycle())),
+            ScriptedSearch::Error(err) => Err(err.clone()),
+        }
+    }
+}
+
+/// Runs the benches once on `search`, and gets back each one's stored results.
+fn run_scripted(
+    benches: &str,
+    search: &ScriptedSearch,
+    config: &str,
+) -> Result<BTreeMap<String, Vec<BenchResult>>, Box<dyn std::error::Error>> {
+    let run_cfg: BenchRunCfg = toml::from_str(&format!(
+        "repeat = 1\nrepeat_failed = 1\nwarmup = 0\n{}",
+        config
+    ))?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str(benches)?;
+    bencher.run_benches(search, &run_cfg);
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+    Ok(db.get_all_results(session)?)
+}
+
+#[test]
+fn test_run_benches_scripted_found() -> Result<(), Box<dyn std::error::Error>> {
+    let search = ScriptedSearch::Words(vec![Some("um"), None, Some("dois"), Some("três")]);
+    let results = run_scripted("a = três\na = quatro\n", &search, "timeout = 10\n")?;
+    let found = &results["a = três"];
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].found_index(), Ok(Some(2)));
+    assert_eq!(found[0].found_word(), Some("três"));
+    let not_found = &results["a = quatro"];
+    assert_eq!(not_found[0].found_index(), Ok(None));
+    assert_eq!(not_found[0].stopped_by(), None);
+    assert!(found[0].elapsed() <= not_found[0].elapsed());
+    Ok(())
+}
+
+#[test]
+fn test_run_benches_scripted_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    // With no time allowed, the clock is past the deadline at the first check, however fast it runs
+    let search = ScriptedSearch::Endless(vec![Some("um"), None, Some("dois")]);
+    let config = "timeout = 0\ntimeout_check_interval = 1\n";
+    let results = run_scripted("a = quatro\n", &search, config)?;
+    let result = &results["a = quatro"][0];
+    assert_eq!(result.found_index(), Ok(None));
+    assert_eq!(result.stopped_by(), Some(SearchLimit::Timeout));
+    Ok(())
+}
+
+/// A reader that always fails with `message`.
+struct FailingReader(&'static str);
+
+impl Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other(self.0))
+    }
+}
+
+#[test]
+fn test_search_error_from_busca_error() {
+    let busca_error = |message| {
+        BuscaCfg::new()
+            .load_rules(io::BufReader::new(FailingReader(message)))
+            .unwrap_err()
+    };
+    for (message, kind) in [
+        ("bad rule on line 3", "rules"),
+        ("No such file or directory (os error 2)", "io"),
+        ("stream did not contain valid UTF-8", "encoding"),
+        ("word not in dictionary", "dictionary"),
+        ("something else", "other"),
+    ] {
+        let err = SearchError::from_error(&busca_error(message));
+        assert_eq!(err.kind.as_deref(), Some(kind), "{}", message);
+        assert_eq!(err.message, message);
+    }
+}
+
+#[test]
+fn test_run_benches_scripted_error() -> Result<(), Box<dyn std::error::Error>> {
+    let search = ScriptedSearch::Error(SearchError {
+        kind: Some("RulesError".to_string()),
+        message: "no rules loaded".to_string(),
+    });
+    let results = run_scripted("a = dois\na = três\n", &search, "timeout = 10\n")?;
+    for bench in ["a = dois", "a = três"] {
+        let result = &results[bench][0];
+        assert_eq!(result.found_index(), Err("no rules loaded"), "{}", bench);
+        assert_eq!(result.error_kind(), Some("RulesError"));
+        assert_eq!(result.first_result(), None);
+    }
+    Ok(())
+}
+
+/// A clock that only moves when it's told to.
+struct FakeClock(Cell<Instant>);
+
+impl FakeClock {
+    fn advance(&self, by: Duration) {
+        self.0.set(self.0.get() + by);
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
+
+/// A search that takes `per_word` by `clock` to find each of its words, over and over.
+struct SlowSearch<'c> {
+    clock: &'c FakeClock,
+    per_word: Duration,
+    words: Vec<&'static str>,
+}
+
+impl WordSearch for SlowSearch<'_> {
+    fn search<'s>(
+        &'s self,
+        _start_word: &str,
+    ) -> Result<Box<dyn Iterator<Item = Option<&'s str>> + 's>, SearchError> {
+        Ok(Box::new(self.words.iter().cycle().map(|&word| {
+            self.clock.advance(self.per_word);
+            Some(word)
+        })))
+    }
+}
+
+#[test]
+fn test_run_benches_fake_clock_timeout() -> Result<(), Box<dyn std::error::Error>> {
+    let config =
+        "repeat = 1\nrepeat_failed = 1\nwarmup = 0\ntimeout = 10\ntimeout_check_interval = 4\n";
+    let run_cfg: BenchRunCfg = toml::from_str(config)?;
+    let clock = FakeClock(Cell::new(Instant::now()));
+    let search = SlowSearch {
+        clock: &clock,
+        per_word: Duration::from_secs(1),
+        words: vec!["um", "dois", "três"],
+    };
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = dois\na = quatro\n")?;
+    bencher.run_benches_timed(&search, &run_cfg, &mut LogProgress::default(), &clock);
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+
+    let found = &db.get_results(session, "a = dois")?[0];
+    assert_eq!(found.found_index(), Ok(Some(1)));
+    assert_eq!(found.elapsed(), Duration::from_secs(2));
+    assert_eq!(found.first_result(), Some(Duration::from_secs(1)));
+    // Past the timeout after 10 words, but it's only checked every 4 words
+    let timed_out = &db.get_results(session, "a = quatro")?[0];
+    assert_eq!(timed_out.found_index(), Ok(None));
+    assert_eq!(timed_out.stopped_by(), Some(SearchLimit::Timeout));
+    assert_eq!(timed_out.elapsed(), Duration::from_secs(12));
+    Ok(())
+}
+
+/// A `SlowSearch` that also takes `setup` by its clock before it finds any words.
+struct SlowSetupSearch<'c> {
+    setup: Duration,
+    search: SlowSearch<'c>,
+}
+
+impl WordSearch for SlowSetupSearch<'_> {
+    fn search<'s>(
+        &'s self,
+        start_word: &str,
+    ) -> Result<Box<dyn Iterator<Item = Option<&'s str>> + 's>, SearchError> {
+        self.search.clock.advance(self.setup);
+        self.search.search(start_word)
+    }
+}
+
+#[test]
+fn test_exclude_setup() -> Result<(), Box<dyn std::error::Error>> {
+    let config = "repeat = 1\nrepeat_failed = 1\ntimeout = 10\nwarmup = 0\n";
+    let mut run_cfg: BenchRunCfg = toml::from_str(config)?;
+    assert!(!run_cfg.exclude_setup);
+    let clock = FakeClock(Cell::new(Instant::now()));
+    let search = SlowSetupSearch {
+        setup: Duration::from_secs(5),
+        search: SlowSearch {
+            clock: &clock,
+            per_word: Duration::from_secs(1),
+            words: vec!["um", "dois"],
+        },
+    };
+    let run = |run_cfg: &BenchRunCfg| -> Result<BenchResult, Box<dyn std::error::Error>> {
+        let mut bencher = Bencher::new();
+        bencher.load_benches_str("a = dois\n")?;
+        bencher.run_benches_timed(&search, run_cfg, &mut LogProgress::default(), &clock);
+        let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+        let session = db.new_session_id()?;
+        bencher.store_results(&mut db, session)?;
+        Ok(db.get_results(session, "a = dois")?.remove(0))
+    };
+
+    let with_setup = run(&run_cfg)?;
+    assert_eq!(with_setup.setup(), Some(Duration::from_secs(5)));
+    assert_eq!(with_setup.elapsed(), Duration::from_secs(7));
+    run_cfg.exclude_setup = true;
+    let without_setup = run(&run_cfg)?;
+    assert_eq!(without_setup.setup(), Some(Duration::from_secs(5)));
+    assert_eq!(without_setup.elapsed(), Duration::from_secs(2));
+    Ok(())
+}
+
+#[test]
+fn test_first_result() -> Result<(), Box<dyn std::error::Error>> {
+    let config = "repeat = 1\nrepeat_failed = 1\ntimeout = 10\nwarmup = 0\n";
+    let run_cfg: BenchRunCfg = toml::from_str(config)?;
+    let mut search_cfg = BuscaCfg::new();
+    search_cfg.load_dictionary("um\ndois\n".as_bytes())?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = dois\na = quatro\n")?;
+    bencher.run_benches(&search_cfg, &run_cfg);
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+
+    // Both benches were run by the same search, so got the same first result
+    let found = &db.get_results(session, "a = dois")?[0];
+    assert_eq!(found.found_index(), Ok(Some(1)));
+    let first_result = found.first_result().unwrap();
+    assert!(first_result <= found.elapsed());
+    let not_found = &db.get_results(session, "a = quatro")?[0];
+    assert_eq!(not_found.first_result(), Some(first_result));
+    assert!(db.average_first_result(session)?.is_some());
+
+    // A search that finds no words at all has nothing to record
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = dois\n")?;
+    bencher.run_benches(&BuscaCfg::new(), &run_cfg);
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+    assert_eq!(db.get_results(session, "a = dois")?[0].first_result(), None);
+    assert_eq!(db.average_first_result(session)?, None);
+    Ok(())
+}
+
+#[test]
+fn test_found_all_at_stored() -> Result<(), Box<dyn std::error::Error>> {
+    let run_cfg: BenchRunCfg =
+        toml::from_str("repeat = 2\nrepeat_failed = 1\ntimeout = 10\nwarmup = 0\n")?;
+    let mut search_cfg = BuscaCfg::new();
+    search_cfg.load_dictionary("um\ndois\ntrês\n".as_bytes())?;
+    let mut bencher = Bencher::new();
+    bencher.load_benches_str("a = dois\nb = quatro\n")?;
+    bencher.run_benches(&search_cfg, &run_cfg);
+    assert_eq!(
+        bencher.get_found_all_at(),
+        &BTreeMap::from([("a".to_string(), vec![2, 2])])
+    );
+
+    let mut db = BenchDb::new(Connection::open_in_memory()?)?;
+    let session = db.new_session_id()?;
+    bencher.store_results(&mut db, session)?;
+    assert_eq!(db.get_info(session, FOUND_ALL_AT_INFO)?, r#"{"a":[2,2]}"#);
+    let compiler = BenchResultCompiler::new(Duration::ZERO, 0.0);
+    assert_eq!(
+        session_stats(&mut db, &compiler, session)?.average_found_all_at,
+        Some(2.0)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_average_found_all_at() {
+    assert_eq!(average_found_all_at(""), None);
+    assert_eq!(average_found_all_at("{}"), None);
+    assert_eq!(average_found_all_at(r#"{"a":[2,4],"b":[6]}"#), Some(4.0));
+}
+
+#[test]
+fn test_drain_until_quiet() {
+    let (tx, rx) = std::sync::mpsc::channel();
+    for i in 0..3 {
+        tx.send(i).unwrap();
+    }
+    assert_eq!(drain_until_quiet(&rx, Duration::from_millis(10)), 3);
+    assert_eq!(drain_until_quiet(&rx, Duration::ZERO), 0);
+    tx.send(3).unwrap();
+    drop(tx);
+    assert_eq!(drain_until_quiet(&rx, Duration::from_secs(10)), 1);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_current_rss_bytes() {
+    assert!(current_rss_bytes().unwrap() > 0);
+}